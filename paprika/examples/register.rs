@@ -71,11 +71,11 @@ fn main() {
     let buf = Arc::new(RwLock::new(VecDeque::<String>::with_capacity(10)));
     let manager = Arc::new(Manager::new(buf.clone()));
 
-    spice.init(Some(manager)); // register
+    spice.init(Some(manager)).unwrap(); // register
     spice.command("source tran.cir"); // results pointer array starts at same address
     spice.command("tran 10u 10m"); // ngspice recommends sending in control statements separately, not as part of netlist
 
-    spice.init(None); // unregister
+    spice.init(None).unwrap(); // unregister
     spice.command("echo echo command");
 
     let mut line = String::new();
@@ -0,0 +1,828 @@
+//! Stand-in for the ngspice shared library (sharedspice), to test paprika and its users without installing ngspice.
+//!
+//! Exports the sharedspice C ABI, except the XSPICE functions. Nothing is simulated: commands and breakpoints are
+//! recorded (the `history` command prints them), and analyses replay scripted results through the registered callbacks:
+//! - `op`: `v(in)` = 1, or the value of the external source `vext` if ngSpice_Init_Sync registered one, and `v(out)` half of it
+//! - `tran <step> <stop>`: `v(in)` a 1kHz sine of amplitude 1, `v(out)` half of it
+//! - `ac <lin|dec> <points> <fstart> <fstop>`: `v(in)` = 1, `v(out)` a first order low pass with its corner at 1kHz
+//!
+//! Analyses require a circuit, which any `source` command loads. `bg_` commands run in a background thread and
+//! `bg_halt` pauses it. `stop when time >= <time>` pauses a transient analysis after the point at time, until
+//! `resume`, and `delete all` removes the stop condition. `version -s` reports version `fake`. `codemodel <path>` loads
+//! nothing, but prints ngspice's error if the file is not a shared library.
+//!
+//! Like ngspice, its state is global to the loaded library: copies of the library file loaded at different paths are
+//! independent instances. The ident set by ngSpice_Init_Sync is passed to every callback.
+
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_double, c_int, c_short, c_void};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard};
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct NgComplex {
+    cx_real: c_double,
+    cx_imag: c_double,
+}
+#[repr(C)]
+pub struct NgVectorinfo {
+    v_name: *const c_char,
+    v_type: c_int,
+    v_flag: c_short,
+    v_realdata: *const c_double,
+    v_compdata: *const NgComplex,
+    v_length: c_int,
+}
+#[repr(C)]
+pub struct NgVecinfo {
+    number: c_int,
+    vecname: *const c_char,
+    is_real: bool,
+    pdvec: *const c_void,
+    pdvecscale: *const c_void,
+}
+#[repr(C)]
+pub struct NgVecinfoall {
+    name: *const c_char,
+    title: *const c_char,
+    date: *const c_char,
+    type_: *const c_char,
+    veccount: c_int,
+    vecs: *const *const NgVecinfo,
+}
+#[repr(C)]
+pub struct NgVecvalues {
+    name: *const c_char,
+    creal: c_double,
+    cimag: c_double,
+    is_scale: bool,
+    is_complex: bool,
+}
+#[repr(C)]
+pub struct NgVecvaluesall {
+    count: c_int,
+    index: c_int,
+    vecsa: *const *const NgVecvalues,
+}
+
+type SendChar = unsafe extern "C" fn(*const c_char, c_int, *const c_void) -> c_int;
+type ControlledExit = unsafe extern "C" fn(c_int, bool, bool, c_int, *const c_void) -> c_int;
+type SendData = unsafe extern "C" fn(*const NgVecvaluesall, c_int, c_int, *const c_void) -> c_int;
+type SendInitData = unsafe extern "C" fn(*const NgVecinfoall, c_int, *const c_void) -> c_int;
+type BgThreadRunning = unsafe extern "C" fn(bool, c_int, *const c_void) -> c_int;
+type GetSrcData =
+    unsafe extern "C" fn(*mut c_double, c_double, *const c_char, c_int, *const c_void) -> c_int;
+type GetSyncData = unsafe extern "C" fn(
+    c_double,
+    *mut c_double,
+    c_double,
+    c_int,
+    c_int,
+    c_int,
+    *const c_void,
+) -> c_int;
+
+/// vector flags, as in ngspice's `dvec.h`
+const VF_REAL: c_short = 1;
+const VF_COMPLEX: c_short = 2;
+/// vector types, as in ngspice's `sim.h`
+const SV_TIME: c_int = 1;
+const SV_FREQUENCY: c_int = 2;
+const SV_VOLTAGE: c_int = 3;
+const SV_CURRENT: c_int = 4;
+
+/// corner frequency of the scripted ac response and frequency of the scripted transient sine
+const SCRIPT_HZ: f64 = 1e3;
+/// name of the external source the scripted op asks the sync callbacks for
+const SCRIPT_VSRC: &str = "vext";
+
+/// callbacks registered by ngSpice_Init
+#[derive(Copy, Clone)]
+struct Callbacks {
+    send_char: Option<SendChar>,
+    send_stat: Option<SendChar>,
+    controlled_exit: Option<ControlledExit>,
+    send_data: Option<SendData>,
+    send_init_data: Option<SendInitData>,
+    bg_running: Option<BgThreadRunning>,
+    /// user pointer, passed back to every callback
+    user: usize,
+    /// ident set by ngSpice_Init_Sync, passed to every callback
+    ident: c_int,
+}
+
+/// callbacks registered by ngSpice_Init_Sync, only external voltage sources are asked for
+#[derive(Copy, Clone)]
+struct SyncCallbacks {
+    get_vsrc: Option<GetSrcData>,
+    user: usize,
+}
+
+/// results of an analysis, as a plot of vectors
+struct Plot {
+    name: String,
+    kind: String,
+    vecs: Vec<Vector>,
+}
+
+struct Vector {
+    name: String,
+    vtype: c_int,
+    complex: bool,
+    /// (real, imaginary) values
+    data: Vec<(f64, f64)>,
+}
+
+/// a transient analysis, paused if next is less than the number of points
+#[derive(Copy, Clone)]
+struct Tran {
+    step: f64,
+    stop: f64,
+    /// index of the next point to compute
+    next: usize,
+}
+
+impl Tran {
+    fn points(&self) -> usize {
+        (self.stop / self.step * (1.0 + 1e-9)).floor() as usize + 1
+    }
+}
+
+/// C data handed out by the API, valid until the next call returning the same kind of data
+struct Returned {
+    cur_plot: Option<CString>,
+    plot_names: Vec<CString>,
+    plot_ptrs: Vec<*const c_char>,
+    vec_names: Vec<CString>,
+    vec_ptrs: Vec<*const c_char>,
+    vec_name: Option<CString>,
+    vec_real: Vec<f64>,
+    vec_comp: Vec<NgComplex>,
+    vec_info: Option<Box<NgVectorinfo>>,
+}
+
+// the pointers only refer to data owned by Returned itself
+unsafe impl Send for Returned {}
+
+struct State {
+    callbacks: Callbacks,
+    sync: SyncCallbacks,
+    /// commands received since the last ngSpice_Init
+    commands: Vec<String>,
+    /// a circuit is loaded
+    circuit: bool,
+    /// plots, oldest first
+    plots: Vec<Plot>,
+    /// time of the `stop when time >= <time>` condition
+    stop_at: Option<f64>,
+    /// breakpoints set by ngSpice_SetBkpt
+    breakpoints: Vec<f64>,
+    /// transient analysis paused by a stop condition or bg_halt
+    paused: Option<Tran>,
+    returned: Returned,
+}
+
+static STATE: Mutex<State> = Mutex::new(State {
+    callbacks: Callbacks {
+        send_char: None,
+        send_stat: None,
+        controlled_exit: None,
+        send_data: None,
+        send_init_data: None,
+        bg_running: None,
+        user: 0,
+        ident: 0,
+    },
+    sync: SyncCallbacks {
+        get_vsrc: None,
+        user: 0,
+    },
+    commands: Vec::new(),
+    circuit: false,
+    plots: Vec::new(),
+    stop_at: None,
+    breakpoints: Vec::new(),
+    paused: None,
+    returned: Returned {
+        cur_plot: None,
+        plot_names: Vec::new(),
+        plot_ptrs: Vec::new(),
+        vec_names: Vec::new(),
+        vec_ptrs: Vec::new(),
+        vec_name: None,
+        vec_real: Vec::new(),
+        vec_comp: Vec::new(),
+        vec_info: None,
+    },
+});
+/// the background thread is running
+static RUNNING: AtomicBool = AtomicBool::new(false);
+/// bg_halt was received, the background analysis pauses at its next point
+static HALT: AtomicBool = AtomicBool::new(false);
+
+/// returns the state, which is never locked while calling back, as callbacks may call the API
+fn state() -> MutexGuard<'static, State> {
+    STATE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[no_mangle]
+pub extern "C" fn ngSpice_Init(
+    printfcn: Option<SendChar>,
+    statfcn: Option<SendChar>,
+    ngexit: Option<ControlledExit>,
+    sdata: Option<SendData>,
+    sinitdata: Option<SendInitData>,
+    bgtrun: Option<BgThreadRunning>,
+    userdata: *const c_void,
+) -> c_int {
+    let mut state = state();
+    state.callbacks = Callbacks {
+        send_char: printfcn,
+        send_stat: statfcn,
+        controlled_exit: ngexit,
+        send_data: sdata,
+        send_init_data: sinitdata,
+        bg_running: bgtrun,
+        user: userdata as usize,
+        // kept, as by ngspice
+        ident: state.callbacks.ident,
+    };
+    state.commands.clear();
+    state.circuit = false;
+    state.plots.clear();
+    state.stop_at = None;
+    state.breakpoints.clear();
+    state.paused = None;
+    0
+}
+
+/// # Safety
+/// ident must be null or point to an int
+#[no_mangle]
+pub unsafe extern "C" fn ngSpice_Init_Sync(
+    vsrcdat: Option<GetSrcData>,
+    _isrcdat: Option<GetSrcData>,
+    _syncdat: Option<GetSyncData>,
+    ident: *const c_int,
+    userdata: *const c_void,
+) -> c_int {
+    let mut state = state();
+    state.sync = SyncCallbacks {
+        get_vsrc: vsrcdat,
+        user: userdata as usize,
+    };
+    if !ident.is_null() {
+        state.callbacks.ident = *ident;
+    }
+    0
+}
+
+/// # Safety
+/// command must be null or a nul terminated string
+#[no_mangle]
+pub unsafe extern "C" fn ngSpice_Command(command: *const c_char) -> c_int {
+    if command.is_null() {
+        // clears the internal control structures
+        let mut state = state();
+        state.stop_at = None;
+        state.paused = None;
+        return 0;
+    }
+    let cmd = CStr::from_ptr(command).to_string_lossy().into_owned();
+    state().commands.push(cmd.clone());
+    match cmd.strip_prefix("bg_") {
+        Some(bg) => bg_command(bg),
+        None => run(&cmd),
+    }
+}
+
+/// # Safety
+/// vecname must be a nul terminated string
+#[no_mangle]
+pub unsafe extern "C" fn ngGet_Vec_Info(vecname: *const c_char) -> *const NgVectorinfo {
+    let name = CStr::from_ptr(vecname).to_string_lossy();
+    let mut state = state();
+    let state = &mut *state;
+    // `plotname.vecname`, or a vector of the current plot
+    let plot = name
+        .split_once('.')
+        .and_then(|(p, v)| Some((state.plots.iter().find(|plot| plot.name == p)?, v)))
+        .or_else(|| Some((state.plots.last()?, name.as_ref())));
+    let Some(vec) = plot.and_then(|(plot, v)| plot.vecs.iter().find(|vec| vec.name == v)) else {
+        return std::ptr::null();
+    };
+    let r = &mut state.returned;
+    let vec_name = r.vec_name.insert(CString::new(vec.name.as_str()).unwrap());
+    r.vec_real = vec.data.iter().map(|d| d.0).collect();
+    r.vec_comp = vec
+        .data
+        .iter()
+        .map(|d| NgComplex {
+            cx_real: d.0,
+            cx_imag: d.1,
+        })
+        .collect();
+    r.vec_info = Some(Box::new(NgVectorinfo {
+        v_name: vec_name.as_ptr(),
+        v_type: vec.vtype,
+        v_flag: if vec.complex { VF_COMPLEX } else { VF_REAL },
+        v_realdata: if vec.complex {
+            std::ptr::null()
+        } else {
+            r.vec_real.as_ptr()
+        },
+        v_compdata: if vec.complex {
+            r.vec_comp.as_ptr()
+        } else {
+            std::ptr::null()
+        },
+        v_length: vec.data.len() as c_int,
+    }));
+    r.vec_info.as_deref().unwrap()
+}
+
+#[no_mangle]
+pub extern "C" fn ngSpice_CurPlot() -> *const c_char {
+    let mut state = state();
+    let name = state
+        .plots
+        .last()
+        .map_or("const", |p| p.name.as_str())
+        .to_string();
+    state
+        .returned
+        .cur_plot
+        .insert(CString::new(name).unwrap())
+        .as_ptr()
+}
+
+#[no_mangle]
+pub extern "C" fn ngSpice_AllPlots() -> *const *const c_char {
+    let mut state = state();
+    // current plot first, ngspice's constants last
+    let mut names: Vec<CString> = state
+        .plots
+        .iter()
+        .rev()
+        .map(|p| CString::new(p.name.as_str()).unwrap())
+        .collect();
+    names.push(CString::new("const").unwrap());
+    let r = &mut state.returned;
+    r.plot_names = names;
+    r.plot_ptrs = null_terminated(&r.plot_names);
+    r.plot_ptrs.as_ptr()
+}
+
+/// # Safety
+/// plotname must be a nul terminated string
+#[no_mangle]
+pub unsafe extern "C" fn ngSpice_AllVecs(plotname: *const c_char) -> *const *const c_char {
+    let name = CStr::from_ptr(plotname).to_string_lossy();
+    let mut state = state();
+    let names: Vec<CString> = state
+        .plots
+        .iter()
+        .find(|p| p.name == name)
+        .map(|p| {
+            p.vecs
+                .iter()
+                .map(|v| CString::new(v.name.as_str()).unwrap())
+                .collect()
+        })
+        .unwrap_or_default();
+    let r = &mut state.returned;
+    r.vec_names = names;
+    r.vec_ptrs = null_terminated(&r.vec_names);
+    r.vec_ptrs.as_ptr()
+}
+
+#[no_mangle]
+pub extern "C" fn ngSpice_running() -> bool {
+    RUNNING.load(Ordering::SeqCst)
+}
+
+#[no_mangle]
+pub extern "C" fn ngSpice_SetBkpt(time: c_double) -> bool {
+    let mut state = state();
+    if !state.circuit {
+        return false;
+    }
+    state.breakpoints.push(time);
+    true
+}
+
+/// returns pointers to the strings, followed by a null pointer
+fn null_terminated(strings: &[CString]) -> Vec<*const c_char> {
+    strings
+        .iter()
+        .map(|s| s.as_ptr())
+        .chain(std::iter::once(std::ptr::null()))
+        .collect()
+}
+
+/// sends msg, e.g. `stdout ...` or `stderr ...`, to the SendChar callback
+fn print(msg: &str) {
+    let cb = state().callbacks;
+    if let Some(f) = cb.send_char {
+        let cmsg = CString::new(msg).unwrap();
+        unsafe { f(cmsg.as_ptr(), cb.ident, cb.user as *const c_void) };
+    }
+}
+
+/// sends msg, e.g. `tran: 50.0%`, to the SendStat callback
+fn status(msg: &str) {
+    let cb = state().callbacks;
+    if let Some(f) = cb.send_stat {
+        let cmsg = CString::new(msg).unwrap();
+        unsafe { f(cmsg.as_ptr(), cb.ident, cb.user as *const c_void) };
+    }
+}
+
+/// prints the error, returns the error code of ngSpice_Command
+fn error(msg: &str) -> c_int {
+    print(&format!("stderr Error: {}", msg));
+    1
+}
+
+/// runs cmd in the background thread, or halts it
+fn bg_command(cmd: &str) -> c_int {
+    if cmd == "halt" {
+        HALT.store(true, Ordering::SeqCst);
+        return 0;
+    }
+    if RUNNING.swap(true, Ordering::SeqCst) {
+        return error("background thread is running, halt it first");
+    }
+    HALT.store(false, Ordering::SeqCst);
+    let cmd = cmd.to_string();
+    std::thread::spawn(move || {
+        bg_running(false);
+        run(&cmd);
+        RUNNING.store(false, Ordering::SeqCst);
+        bg_running(true);
+    });
+    0
+}
+
+/// calls the BGThreadRunning callback, finished is false when the thread starts and true when it ends
+fn bg_running(finished: bool) {
+    let cb = state().callbacks;
+    if let Some(f) = cb.bg_running {
+        unsafe { f(finished, cb.ident, cb.user as *const c_void) };
+    }
+}
+
+/// executes a command in the calling thread
+fn run(cmd: &str) -> c_int {
+    let words: Vec<&str> = cmd.split_whitespace().collect();
+    let analysis = matches!(words.first(), Some(&("op" | "tran" | "ac" | "resume")));
+    if analysis && !state().circuit {
+        return error("there aren't any circuits loaded.");
+    }
+    match words.as_slice() {
+        [] => 0,
+        ["source", ..] => {
+            state().circuit = true;
+            0
+        }
+        ["op"] => op(),
+        ["tran", step, stop, ..] => match (parse_number(step), parse_number(stop)) {
+            (Some(step), Some(stop)) if step > 0.0 && stop >= 0.0 => {
+                state().paused = None;
+                tran(Tran {
+                    step,
+                    stop,
+                    next: 0,
+                })
+            }
+            _ => error(&format!("invalid tran parameters {}", cmd)),
+        },
+        ["ac", sweep, n, fstart, fstop] => {
+            match (
+                n.parse::<usize>(),
+                parse_number(fstart),
+                parse_number(fstop),
+            ) {
+                (Ok(n), Some(fstart), Some(fstop)) if fstart > 0.0 && fstop >= fstart => {
+                    ac(sweep, n.max(1), fstart, fstop)
+                }
+                _ => error(&format!("invalid ac parameters {}", cmd)),
+            }
+        }
+        ["resume"] => {
+            let paused = state().paused.take();
+            match paused {
+                Some(t) => tran(t),
+                None => error("no analysis to resume"),
+            }
+        }
+        ["stop", "when", "time", ">=", t] | ["stop", "when", "time", ">", t] => {
+            match parse_number(t) {
+                Some(t) => {
+                    state().stop_at = Some(t);
+                    0
+                }
+                None => error(&format!("invalid time {}", t)),
+            }
+        }
+        ["delete", "all"] => {
+            state().stop_at = None;
+            0
+        }
+        ["version", ..] => {
+            print("stdout ******");
+            print("stdout ** ngspice-fake : stand-in sharedspice library for testing");
+            print("stdout ******");
+            0
+        }
+        ["history"] => {
+            let (commands, breakpoints) = {
+                let state = state();
+                (state.commands.clone(), state.breakpoints.clone())
+            };
+            for (i, c) in commands.iter().enumerate() {
+                print(&format!("stdout {}\t{}", i + 1, c));
+            }
+            for t in breakpoints {
+                print(&format!("stdout breakpoint {:e}", t));
+            }
+            0
+        }
+        ["codemodel", ..] => {
+            // like ngspice, reports the error only in its output
+            let path = cmd["codemodel".len()..].trim().trim_matches('"');
+            if !is_library(path) {
+                print(&format!(
+                    "stderr Error: Library {} couldn't be loaded!",
+                    path
+                ));
+            }
+            0
+        }
+        ["echo", ..] => {
+            print(&format!("stdout {}", cmd["echo".len()..].trim()));
+            0
+        }
+        ["quit", ..] => {
+            let cb = state().callbacks;
+            if let Some(f) = cb.controlled_exit {
+                unsafe { f(0, false, true, cb.ident, cb.user as *const c_void) };
+            }
+            0
+        }
+        // anything else, e.g. alter or save, is only recorded
+        _ => 0,
+    }
+}
+
+/// returns true if the file at path starts as a shared library does, on any platform
+fn is_library(path: &str) -> bool {
+    let Ok(bytes) = std::fs::read(path) else {
+        return false;
+    };
+    [&b"\x7fELF"[..], b"MZ", b"\xcf\xfa\xed\xfe"]
+        .iter()
+        .any(|magic| bytes.starts_with(magic))
+}
+
+/// returns the value of the external voltage source name, if the sync callbacks are registered
+fn external_vsrc(time: f64, name: &str) -> Option<f64> {
+    let (sync, ident) = {
+        let state = state();
+        (state.sync, state.callbacks.ident)
+    };
+    let f = sync.get_vsrc?;
+    let cname = CString::new(name).unwrap();
+    let mut value = 0.0;
+    unsafe {
+        f(
+            &mut value,
+            time,
+            cname.as_ptr(),
+            ident,
+            sync.user as *const c_void,
+        )
+    };
+    Some(value)
+}
+
+/// starts a new plot of vectors (name, type, complex), returns its index after sending its description to the SendInitData callback
+fn new_plot(kind: &str, title: &str, vecs: &[(&str, c_int, bool)]) -> usize {
+    let (name, index) = {
+        let mut state = state();
+        let n = state.plots.iter().filter(|p| p.kind == kind).count() + 1;
+        let name = format!("{}{}", kind, n);
+        state.plots.push(Plot {
+            name: name.clone(),
+            kind: kind.to_string(),
+            vecs: vecs
+                .iter()
+                .map(|(name, vtype, complex)| Vector {
+                    name: name.to_string(),
+                    vtype: *vtype,
+                    complex: *complex,
+                    data: vec![],
+                })
+                .collect(),
+        });
+        (name, state.plots.len() - 1)
+    };
+    let cb = state().callbacks;
+    if let Some(f) = cb.send_init_data {
+        let cname = CString::new(name).unwrap();
+        let ctitle = CString::new(title).unwrap();
+        let cdate = CString::new("today").unwrap();
+        let ckind = CString::new(kind).unwrap();
+        let cvecnames: Vec<CString> = vecs.iter().map(|v| CString::new(v.0).unwrap()).collect();
+        let infos: Vec<NgVecinfo> = vecs
+            .iter()
+            .zip(&cvecnames)
+            .enumerate()
+            .map(|(i, (v, cn))| NgVecinfo {
+                number: i as c_int,
+                vecname: cn.as_ptr(),
+                is_real: !v.2,
+                pdvec: std::ptr::null(),
+                pdvecscale: std::ptr::null(),
+            })
+            .collect();
+        let pinfos: Vec<*const NgVecinfo> = infos.iter().map(|i| i as *const _).collect();
+        let all = NgVecinfoall {
+            name: cname.as_ptr(),
+            title: ctitle.as_ptr(),
+            date: cdate.as_ptr(),
+            type_: ckind.as_ptr(),
+            veccount: vecs.len() as c_int,
+            vecs: pinfos.as_ptr(),
+        };
+        unsafe { f(&all, cb.ident, cb.user as *const c_void) };
+    }
+    index
+}
+
+/// appends a point of (real, imaginary) values, one per vector, to the plot and sends it to the SendData callback
+fn send_point(plot: usize, values: &[(f64, f64)]) {
+    let (names, flags, index) = {
+        let mut state = state();
+        let p = &mut state.plots[plot];
+        for (v, value) in p.vecs.iter_mut().zip(values) {
+            v.data.push(*value);
+        }
+        let names: Vec<CString> = p
+            .vecs
+            .iter()
+            .map(|v| CString::new(v.name.as_str()).unwrap())
+            .collect();
+        let flags: Vec<bool> = p.vecs.iter().map(|v| v.complex).collect();
+        (names, flags, p.vecs[0].data.len() - 1)
+    };
+    let cb = state().callbacks;
+    if let Some(f) = cb.send_data {
+        let vals: Vec<NgVecvalues> = names
+            .iter()
+            .zip(values)
+            .zip(&flags)
+            .enumerate()
+            .map(|(i, ((n, v), complex))| NgVecvalues {
+                name: n.as_ptr(),
+                creal: v.0,
+                cimag: v.1,
+                is_scale: i == 0,
+                is_complex: *complex,
+            })
+            .collect();
+        let pvals: Vec<*const NgVecvalues> = vals.iter().map(|v| v as *const _).collect();
+        let all = NgVecvaluesall {
+            count: vals.len() as c_int,
+            index: index as c_int,
+            vecsa: pvals.as_ptr(),
+        };
+        unsafe {
+            f(
+                &all,
+                vals.len() as c_int,
+                cb.ident,
+                cb.user as *const c_void,
+            )
+        };
+    }
+}
+
+/// scripted dc operating point
+fn op() -> c_int {
+    let vin = external_vsrc(0.0, SCRIPT_VSRC).unwrap_or(1.0);
+    let plot = new_plot(
+        "op",
+        "fake operating point",
+        &[
+            ("v(in)", SV_VOLTAGE, false),
+            ("v(out)", SV_VOLTAGE, false),
+            ("v1#branch", SV_CURRENT, false),
+        ],
+    );
+    send_point(plot, &[(vin, 0.0), (vin / 2.0, 0.0), (-vin / 2e3, 0.0)]);
+    status("--ready--");
+    0
+}
+
+/// scripted transient analysis from point t.next, pausing at the stop condition or on bg_halt
+fn tran(t: Tran) -> c_int {
+    let plot = if t.next == 0 {
+        new_plot(
+            "tran",
+            "fake transient analysis",
+            &[
+                ("time", SV_TIME, false),
+                ("v(in)", SV_VOLTAGE, false),
+                ("v(out)", SV_VOLTAGE, false),
+            ],
+        )
+    } else {
+        state().plots.len() - 1
+    };
+    let points = t.points();
+    for i in t.next..points {
+        let time = i as f64 * t.step;
+        let vin = (2.0 * std::f64::consts::PI * SCRIPT_HZ * time).sin();
+        send_point(plot, &[(time, 0.0), (vin, 0.0), (vin / 2.0, 0.0)]);
+        status(&format!(
+            "tran: {:.1}%",
+            (i + 1) as f64 / points as f64 * 100.0
+        ));
+        let stop = state().stop_at.is_some_and(|at| time >= at * (1.0 - 1e-9));
+        if i + 1 < points && (stop || HALT.swap(false, Ordering::SeqCst)) {
+            state().paused = Some(Tran { next: i + 1, ..t });
+            print(&format!("stdout Simulation interrupted at time {:e}", time));
+            return 0;
+        }
+    }
+    status("--ready--");
+    0
+}
+
+/// scripted ac analysis, sweep is `lin` for n points or `dec` for n points per decade
+fn ac(sweep: &str, n: usize, fstart: f64, fstop: f64) -> c_int {
+    let freqs: Vec<f64> = match sweep {
+        _ if fstart == fstop => vec![fstart],
+        "lin" if n == 1 => vec![fstart],
+        "lin" => (0..n)
+            .map(|i| fstart + (fstop - fstart) * i as f64 / (n - 1) as f64)
+            .collect(),
+        "dec" => {
+            let total = ((fstop / fstart).log10() * n as f64).round() as usize;
+            (0..=total)
+                .map(|i| fstart * 10f64.powf(i as f64 / n as f64))
+                .collect()
+        }
+        _ => return error(&format!("unsupported ac sweep {}", sweep)),
+    };
+    let plot = new_plot(
+        "ac",
+        "fake ac analysis",
+        &[
+            ("frequency", SV_FREQUENCY, true),
+            ("v(in)", SV_VOLTAGE, true),
+            ("v(out)", SV_VOLTAGE, true),
+        ],
+    );
+    for f in freqs {
+        // 1 / (1 + jx)
+        let x = f / SCRIPT_HZ;
+        let d = 1.0 + x * x;
+        send_point(plot, &[(f, 0.0), (1.0, 0.0), (1.0 / d, -x / d)]);
+    }
+    status("--ready--");
+    0
+}
+
+/// parses a spice number with an optional scale suffix and unit, e.g. `10u`, `1meg` or `5ms`
+fn parse_number(s: &str) -> Option<f64> {
+    let s = s.to_lowercase();
+    let split = s
+        .find(|c: char| c.is_ascii_alphabetic() && c != 'e')
+        .or_else(|| {
+            // an exponent must be followed by a digit or sign, otherwise e is a unit
+            s.find('e').filter(|i| {
+                !s[i + 1..].starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == '+')
+            })
+        })
+        .unwrap_or(s.len());
+    let (num, suffix) = s.split_at(split);
+    let scale = if suffix.starts_with("meg") {
+        1e6
+    } else {
+        match suffix.chars().next() {
+            Some('t') => 1e12,
+            Some('g') => 1e9,
+            Some('k') => 1e3,
+            Some('m') => 1e-3,
+            Some('u') => 1e-6,
+            Some('n') => 1e-9,
+            Some('p') => 1e-12,
+            Some('f') => 1e-15,
+            _ => 1.0,
+        }
+    };
+    Some(num.parse::<f64>().ok()? * scale)
+}
@@ -0,0 +1,106 @@
+//! XSPICE code models
+//!
+//! Code model libraries (`.cm`), e.g. `digital.cm` or custom models, must be loaded with the `codemodel` command
+//! before sourcing a circuit using them. `PkSpice` keeps a list of directories to search for them.
+
+use std::ffi::{CStr, CString};
+use std::path::{Path, PathBuf};
+
+use crate::{PkSpice, PkSpiceError, PkSpiceManager};
+
+/// file extension of code model libraries
+const CM_EXTENSION: &str = "cm";
+
+impl<T> PkSpice<T>
+where
+    T: PkSpiceManager,
+{
+    /// API function known as ngCM_Input_Path in Ngspice User's Manual
+    /// Sets the input path for files loaded by code models, e.g. the input file of a `filesource`, if path is some.
+    /// Returns the current input path.
+    pub fn cm_input_path(&self, path: Option<&Path>) -> Result<Option<String>, PkSpiceError> {
+        let cpath = match path {
+            Some(p) => Some(c_path(p)?),
+            None => None,
+        };
+        let cm_input_path = self
+            .api
+            .cm_input_path
+            .as_ref()
+            .ok_or_else(|| PkSpiceError::SymbolNotFound(String::from("ngCM_Input_Path")))?;
+        let ret = cm_input_path(cpath.as_ref().map_or(std::ptr::null(), |p| p.as_ptr()));
+        if ret.is_null() {
+            return Ok(None);
+        }
+        unsafe { Ok(Some(CStr::from_ptr(ret).to_string_lossy().into_owned())) }
+    }
+
+    /// Adds dir to the end of the directories searched for code model libraries
+    pub fn add_codemodel_path(&mut self, dir: impl AsRef<Path>) {
+        let dir = dir.as_ref().to_path_buf();
+        if !self.cm_paths.contains(&dir) {
+            self.cm_paths.push(dir);
+        }
+    }
+
+    /// Returns the directories searched for code model libraries, in order
+    pub fn codemodel_paths(&self) -> &[PathBuf] {
+        &self.cm_paths
+    }
+
+    /// Returns the path of the code model library name, e.g. `digital` or `digital.cm`, in the first directory which has it.
+    /// name may also be a path to the library.
+    pub fn find_codemodel(&self, name: &str) -> Option<PathBuf> {
+        let mut file = PathBuf::from(name);
+        if file.extension().is_none() {
+            file.set_extension(CM_EXTENSION);
+        }
+        if file.is_absolute() || file.components().count() > 1 {
+            return file.is_file().then_some(file);
+        }
+        self.cm_paths
+            .iter()
+            .map(|dir| dir.join(&file))
+            .find(|p| p.is_file())
+    }
+
+    /// Returns all code model libraries in the code model directories
+    pub fn available_codemodels(&self) -> Vec<PathBuf> {
+        let mut ret = vec![];
+        for dir in &self.cm_paths {
+            let Ok(entries) = std::fs::read_dir(dir) else {
+                continue;
+            };
+            let mut found: Vec<PathBuf> = entries
+                .filter_map(|e| Some(e.ok()?.path()))
+                .filter(|p| p.is_file() && p.extension().is_some_and(|e| e == CM_EXTENSION))
+                .collect();
+            found.sort();
+            ret.extend(found);
+        }
+        ret
+    }
+
+    /// Loads the code model library name, found as by `find_codemodel`, with the `codemodel` command.
+    /// Must be called before sourcing a circuit which uses its models. Returns the path of the library.
+    /// ngspice does not return an error if it fails to load the library, but prints one, e.g.
+    /// `stderr Error: Library <path> couldn't be loaded!`: an error `PkDiagnostic` received by cb_send_char.
+    pub fn load_codemodel(&self, name: &str) -> Result<PathBuf, PkSpiceError> {
+        let path = self
+            .find_codemodel(name)
+            .ok_or_else(|| PkSpiceError::CodeModelNotFound(name.to_string()))?;
+        let spath = path
+            .to_str()
+            .ok_or_else(|| PkSpiceError::InvalidPath(path.clone()))?;
+        // ngspice splits command arguments at whitespace unless quoted
+        self.command(&format!("codemodel \"{}\"", spath));
+        Ok(path)
+    }
+}
+
+/// converts path to a C string for ngspice
+fn c_path(path: &Path) -> Result<CString, PkSpiceError> {
+    path.to_str()
+        .and_then(|s| CString::new(s).ok())
+        .ok_or_else(|| PkSpiceError::InvalidPath(path.to_path_buf()))
+}
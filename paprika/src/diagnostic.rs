@@ -0,0 +1,143 @@
+//! diagnostics
+//!
+//! Classifies the lines ngspice prints, as received by `cb_send_char` (e.g. `stderr Error: unknown model nmos1`),
+//! into errors, warnings and information, with the device, node or netlist line they refer to where present.
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// substrings of a lowercase message marking it an error, unless it is a warning
+const ERROR_MARKERS: [&str; 9] = [
+    "error",
+    "fatal",
+    "aborted",
+    "timestep too small",
+    "singular matrix",
+    "unknown",
+    "unable to find",
+    "could not",
+    "cannot",
+];
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+/// severity of a diagnostic, most severe first
+pub enum PkSeverity {
+    Error,
+    Warning,
+    Info,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+/// a line printed by ngspice, classified
+pub struct PkDiagnostic {
+    pub severity: PkSeverity,
+    /// the line without its `stdout`/`stderr` prefix
+    pub message: String,
+    /// lowercase name of the device instance the line refers to, e.g. `r1`
+    pub device: Option<String>,
+    /// lowercase name of the node the line refers to
+    pub node: Option<String>,
+    /// netlist line the line refers to
+    pub line: Option<usize>,
+}
+
+impl PkDiagnostic {
+    /// classifies a line as received by cb_send_char, i.e. prefixed by `stdout` or `stderr`
+    pub fn parse(msg: &str) -> Self {
+        let (stderr, text) = match msg.split_once(' ') {
+            Some(("stderr", text)) => (true, text),
+            Some(("stdout", text)) => (false, text),
+            _ => (false, msg),
+        };
+        let text = text.trim_end();
+        let lower = text.to_lowercase();
+        let severity = if lower.contains("warning") {
+            PkSeverity::Warning
+        } else if ERROR_MARKERS.iter().any(|m| lower.contains(m)) {
+            PkSeverity::Error
+        } else if lower.trim_start().starts_with("note") || !stderr {
+            PkSeverity::Info
+        } else {
+            PkSeverity::Warning
+        };
+        PkDiagnostic {
+            severity,
+            message: text.to_string(),
+            device: word_after(&lower, "instance "),
+            node: word_after(&lower, "node "),
+            line: word_after(&lower, "line ").and_then(|w| w.parse().ok()),
+        }
+    }
+}
+
+/// returns the word following keyword in the lowercase text, without quotes and punctuation
+fn word_after(lower: &str, keyword: &str) -> Option<String> {
+    lower.match_indices(keyword).find_map(|(i, _)| {
+        // whole keywords only, e.g. not `baseline 3`
+        if lower[..i].ends_with(|c: char| c.is_alphanumeric()) {
+            return None;
+        }
+        let word = lower[i + keyword.len()..]
+            .split_whitespace()
+            .next()?
+            .trim_matches(|c: char| matches!(c, '"' | '\'' | ':' | ',' | ';' | '.' | '(' | ')'));
+        (!word.is_empty()).then(|| word.to_string())
+    })
+}
+
+#[derive(Clone, Debug, Default)]
+/// diagnostics of a run, in the order ngspice printed them
+pub struct PkDiagnostics {
+    items: Vec<PkDiagnostic>,
+    /// incremented whenever the diagnostics change
+    generation: u64,
+}
+
+impl PkDiagnostics {
+    /// classifies and appends a line as received by cb_send_char. An indented line following a diagnostic which refers
+    /// to a netlist line, e.g. `Error on line 4 or its substitute:`, is the echo of that line: it names the device.
+    pub fn push(&mut self, msg: &str) {
+        let d = PkDiagnostic::parse(msg);
+        if d.message.trim().is_empty() {
+            return;
+        }
+        if let Some(last) = self.items.last_mut() {
+            if d.message.starts_with(char::is_whitespace)
+                && last.line.is_some()
+                && last.device.is_none()
+            {
+                let echo = d.message.trim();
+                last.device = echo.split_whitespace().next().map(|w| w.to_lowercase());
+                last.message = format!("{} {}", last.message, echo);
+                self.generation += 1;
+                return;
+            }
+        }
+        self.items.push(d);
+        self.generation += 1;
+    }
+    /// returns the diagnostics, in order
+    pub fn items(&self) -> &[PkDiagnostic] {
+        &self.items
+    }
+    /// returns the diagnostics at least as severe as severity
+    pub fn at_least(&self, severity: PkSeverity) -> impl Iterator<Item = &PkDiagnostic> {
+        self.items.iter().filter(move |d| d.severity <= severity)
+    }
+    /// returns true if there are errors
+    pub fn has_errors(&self) -> bool {
+        self.at_least(PkSeverity::Error).next().is_some()
+    }
+    /// returns a number which changes whenever the diagnostics do, to tell whether they need to be taken up again
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+    pub fn clear(&mut self) {
+        if !self.items.is_empty() {
+            self.items.clear();
+            self.generation += 1;
+        }
+    }
+}
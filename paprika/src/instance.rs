@@ -0,0 +1,74 @@
+//! isolated instances
+//!
+//! ngspice keeps its state in globals of the library, and the platform's loader loads a library file once per process,
+//! so every `PkSpice` linked to the same file drives the same simulator. An isolated instance links to a copy of the
+//! library at a unique temporary path instead, with state of its own: isolated instances may simulate different
+//! circuits, concurrently from different threads. Each has a distinct id, passed to the callbacks, which tells a manager
+//! registered with several instances which one is calling.
+
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicI32, Ordering};
+
+use crate::{locate_sharedspice, PkSpice, PkSpiceError, PkSpiceManager};
+
+/// id of the next isolated instance, instances linked to the library file itself have id 0
+static NEXT_ID: AtomicI32 = AtomicI32::new(1);
+
+/// copy of the sharedspice library file, removed when dropped
+pub(crate) struct LibraryCopy {
+    path: PathBuf,
+}
+
+impl LibraryCopy {
+    /// copies the library at path to the temporary directory, under a name unique to the process and id
+    fn new(path: &Path, id: i32) -> Result<Self, PkSpiceError> {
+        let Some(name) = path.file_name().filter(|_| path.is_file()) else {
+            return Err(PkSpiceError::SharedspiceNotFound(
+                path.as_os_str().to_os_string(),
+            ));
+        };
+        // keeps the file name, whose extension some platforms require
+        let copy = std::env::temp_dir().join(format!(
+            "paprika-{}-{}-{}",
+            std::process::id(),
+            id,
+            name.to_string_lossy()
+        ));
+        std::fs::copy(path, &copy)
+            .map_err(|e| PkSpiceError::SharedspiceNotCopied(copy.clone(), e.to_string()))?;
+        Ok(LibraryCopy { path: copy })
+    }
+}
+
+impl Drop for LibraryCopy {
+    fn drop(&mut self) {
+        // PkSpice unloads the library first, a loaded library can not be removed on windows
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+impl<T> PkSpice<T>
+where
+    T: PkSpiceManager,
+{
+    /// Links to a copy of the sharedspice library given by path, an instance with ngspice state of its own.
+    /// The copy is removed when the instance is dropped.
+    pub fn new_isolated(path: &OsStr) -> Result<PkSpice<T>, PkSpiceError> {
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let copy = LibraryCopy::new(Path::new(path), id)?;
+        let mut spice = PkSpice::new(copy.path.as_os_str())?;
+        spice.copy = Some(copy);
+        spice.id = id;
+        Ok(spice)
+    }
+    /// Links to a copy of the sharedspice library found by `locate_sharedspice`, see `new_isolated`
+    pub fn locate_isolated() -> Result<PkSpice<T>, PkSpiceError> {
+        PkSpice::new_isolated(locate_sharedspice()?.as_os_str())
+    }
+    /// Returns the id passed to the callbacks: unique to an isolated instance, 0 for an instance linked to the
+    /// library file itself
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+}
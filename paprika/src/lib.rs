@@ -7,7 +7,7 @@
 //! Dot analysis commands like `.tran 10u 10m` in the netlist is executed after `run` or `bg_run` is sent through `NgSpice_Command`.
 //! Safety must assume that callbacks are called from parallel thread after commanding `bg_run`.
 
-use std::{ffi::OsString, sync::Arc};
+use std::{ffi::OsString, path::PathBuf, sync::Arc};
 
 use libc::*;
 #[cfg(unix)]
@@ -17,6 +17,17 @@ use libloading::os::windows::Symbol as RawSymbol;
 use libloading::Library;
 mod structs;
 pub use structs::*;
+mod raw;
+pub use raw::*;
+mod codemodel;
+mod diagnostic;
+pub use diagnostic::*;
+mod instance;
+use instance::LibraryCopy;
+mod locate;
+pub use locate::*;
+mod progress;
+pub use progress::*;
 mod ngspice;
 use ngspice::*;
 
@@ -24,6 +35,69 @@ use ngspice::*;
 pub enum PkSpiceError {
     /// Sharedspice library is not found
     SharedspiceNotFound(OsString),
+    /// Sharedspice library is not at any of the paths searched
+    SharedspiceNotLocated(Vec<PathBuf>),
+    /// Sharedspice library could not be copied to the path, with the reason
+    SharedspiceNotCopied(PathBuf, String),
+    /// Code model library is not found in any of the code model paths
+    CodeModelNotFound(String),
+    /// Path can not be passed to ngspice, i.e. it is not valid unicode
+    InvalidPath(PathBuf),
+    /// Symbol is not exported by the sharedspice library, e.g. the library is too old or built without XSPICE
+    SymbolNotFound(String),
+    /// ngSpice_Init or one of its companions returned the error code
+    InitFailed(i32),
+    /// ngspice reported an error executing the command
+    CommandFailed(String),
+    /// A command is already running in ngspice's background thread
+    BackgroundRunning,
+}
+
+impl std::fmt::Display for PkSpiceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PkSpiceError::SharedspiceNotFound(p) => {
+                write!(f, "sharedspice library not found: {}", p.to_string_lossy())
+            }
+            PkSpiceError::SharedspiceNotLocated(searched) => write!(
+                f,
+                "sharedspice library not found, set {} to its path ({} paths searched)",
+                LIBRARY_PATH_VAR,
+                searched.len()
+            ),
+            PkSpiceError::SharedspiceNotCopied(p, reason) => {
+                write!(
+                    f,
+                    "sharedspice library not copied to {}: {}",
+                    p.display(),
+                    reason
+                )
+            }
+            PkSpiceError::CodeModelNotFound(name) => write!(f, "code model {} not found", name),
+            PkSpiceError::InvalidPath(p) => write!(f, "invalid path {}", p.display()),
+            PkSpiceError::SymbolNotFound(name) => {
+                write!(f, "sharedspice library does not export {}", name)
+            }
+            PkSpiceError::InitFailed(code) => write!(f, "ngspice init failed with {}", code),
+            PkSpiceError::CommandFailed(cmd) => write!(f, "ngspice command failed: {}", cmd),
+            PkSpiceError::BackgroundRunning => write!(f, "ngspice background thread is running"),
+        }
+    }
+}
+
+impl std::error::Error for PkSpiceError {}
+
+/// ngspice version and optional features, detected when the library is loaded
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PkSpiceInfo {
+    /// version as reported by ngspice, e.g. `39`, if it could be determined
+    pub version: Option<String>,
+    /// ngSpice_Init_Sync is available, for external sources
+    pub sync: bool,
+    /// ngSpice_SetBkpt is available
+    pub breakpoints: bool,
+    /// XSPICE functions are available, for event nodes and code models
+    pub xspice: bool,
 }
 
 type NgSpiceInit = extern "C" fn(
@@ -37,58 +111,133 @@ type NgSpiceInit = extern "C" fn(
     Option<unsafe extern "C" fn(bool, c_int, *const c_void) -> c_int>,
     *const c_void,
 ) -> c_int;
+type NgSpiceInitSync = extern "C" fn(
+    Option<
+        unsafe extern "C" fn(*mut c_double, c_double, *const c_char, c_int, *const c_void) -> c_int,
+    >,
+    Option<
+        unsafe extern "C" fn(*mut c_double, c_double, *const c_char, c_int, *const c_void) -> c_int,
+    >,
+    Option<
+        unsafe extern "C" fn(
+            c_double,
+            *mut c_double,
+            c_double,
+            c_int,
+            c_int,
+            c_int,
+            *const c_void,
+        ) -> c_int,
+    >,
+    *const c_int,
+    *const c_void,
+) -> c_int;
 type NgSpiceCommand = extern "C" fn(*const c_char) -> c_int;
 type NgSpiceVecInfo = extern "C" fn(*const c_char) -> *const NgVectorinfo;
 type NgSpiceCurPlot = extern "C" fn() -> *const c_char;
 type NgSpiceAllPlots = extern "C" fn() -> *const *const c_char;
 type NgSpiceAllVecs = extern "C" fn(*const c_char) -> *const *const c_char;
 type NgSpiceRunning = extern "C" fn() -> bool;
+type NgSpiceSetBkpt = extern "C" fn(c_double) -> bool;
+type NgCmInputPath = extern "C" fn(*const c_char) -> *const c_char;
+type NgSpiceEvtNodeInfo = extern "C" fn(*const c_char) -> *const NgEvtSharedData;
+type NgSpiceAllEvtNodes = extern "C" fn() -> *const *const c_char;
+type NgSpiceInitEvt = extern "C" fn(
+    Option<
+        unsafe extern "C" fn(
+            c_int,
+            c_double,
+            c_double,
+            *const c_char,
+            *const c_void,
+            c_int,
+            c_int,
+            c_int,
+            *const c_void,
+        ) -> c_int,
+    >,
+    Option<
+        unsafe extern "C" fn(
+            c_int,
+            c_int,
+            *const c_char,
+            *const c_char,
+            c_int,
+            *const c_void,
+        ) -> c_int,
+    >,
+    *const c_void,
+) -> c_int;
 
 #[allow(dead_code)]
 struct VTableV0 {
     init: RawSymbol<NgSpiceInit>,
+    init_sync: Option<RawSymbol<NgSpiceInitSync>>,
 
     command: RawSymbol<NgSpiceCommand>,
     get_vec_info: RawSymbol<NgSpiceVecInfo>,
+    cm_input_path: Option<RawSymbol<NgCmInputPath>>,
+    get_evt_node_info: Option<RawSymbol<NgSpiceEvtNodeInfo>>,
+    get_all_evt_nodes: Option<RawSymbol<NgSpiceAllEvtNodes>>,
+    init_evt: Option<RawSymbol<NgSpiceInitEvt>>,
 
     get_cur_plot: RawSymbol<NgSpiceCurPlot>,
     get_all_plots: RawSymbol<NgSpiceAllPlots>,
     get_all_vecs: RawSymbol<NgSpiceAllVecs>,
     is_running: RawSymbol<NgSpiceRunning>,
+    set_bkpt: Option<RawSymbol<NgSpiceSetBkpt>>,
 }
 
 impl VTableV0 {
-    unsafe fn get_symbol<T>(lib: &Library, sname: &[u8]) -> RawSymbol<T> {
-        let symbol = lib.get(sname).unwrap();
-        libloading::Symbol::<T>::into_raw(symbol)
+    unsafe fn get_symbol<T>(lib: &Library, sname: &[u8]) -> Result<RawSymbol<T>, PkSpiceError> {
+        match lib.get(sname) {
+            Ok(symbol) => Ok(libloading::Symbol::<T>::into_raw(symbol)),
+            Err(_) => Err(PkSpiceError::SymbolNotFound(symbol_name(sname))),
+        }
     }
 
-    unsafe fn new(lib: &Library) -> VTableV0 {
+    unsafe fn new(lib: &Library) -> Result<VTableV0, PkSpiceError> {
         // get symbols (same order as they appear in sharedspice.h)
-        VTableV0 {
-            init: VTableV0::get_symbol::<NgSpiceInit>(lib, b"ngSpice_Init\0"),
-            // b"ngSpice_Init_Sync\0";
-            command: VTableV0::get_symbol::<NgSpiceCommand>(lib, b"ngSpice_Command\0"),
-            get_vec_info: VTableV0::get_symbol::<NgSpiceVecInfo>(lib, b"ngGet_Vec_Info\0"),
-            // b"ngCM_Input_Path\0";
-            // b"ngGet_Evt_NodeInfo\0";
-            // b"ngSpice_AllEvtNodes\0";
-            // b"ngSpice_Init_Evt\0";
+        // symbols of later versions or of optional features, e.g. XSPICE, may be missing
+        Ok(VTableV0 {
+            init: VTableV0::get_symbol::<NgSpiceInit>(lib, b"ngSpice_Init\0")?,
+            init_sync: VTableV0::get_symbol::<NgSpiceInitSync>(lib, b"ngSpice_Init_Sync\0").ok(),
+            command: VTableV0::get_symbol::<NgSpiceCommand>(lib, b"ngSpice_Command\0")?,
+            get_vec_info: VTableV0::get_symbol::<NgSpiceVecInfo>(lib, b"ngGet_Vec_Info\0")?,
+            cm_input_path: VTableV0::get_symbol::<NgCmInputPath>(lib, b"ngCM_Input_Path\0").ok(),
+            get_evt_node_info: VTableV0::get_symbol::<NgSpiceEvtNodeInfo>(
+                lib,
+                b"ngGet_Evt_NodeInfo\0",
+            )
+            .ok(),
+            get_all_evt_nodes: VTableV0::get_symbol::<NgSpiceAllEvtNodes>(
+                lib,
+                b"ngSpice_AllEvtNodes\0",
+            )
+            .ok(),
+            init_evt: VTableV0::get_symbol::<NgSpiceInitEvt>(lib, b"ngSpice_Init_Evt\0").ok(),
             // b"ngSpice_Circ\0";
-            get_cur_plot: VTableV0::get_symbol::<NgSpiceCurPlot>(lib, b"ngSpice_CurPlot\0"),
-            get_all_plots: VTableV0::get_symbol::<NgSpiceAllPlots>(lib, b"ngSpice_AllPlots\0"),
-            get_all_vecs: VTableV0::get_symbol::<NgSpiceAllVecs>(lib, b"ngSpice_AllVecs\0"),
-            is_running: VTableV0::get_symbol::<NgSpiceRunning>(lib, b"ngSpice_running\0"),
-            // b"ngSpice_SetBkpt\0";
-        }
+            get_cur_plot: VTableV0::get_symbol::<NgSpiceCurPlot>(lib, b"ngSpice_CurPlot\0")?,
+            get_all_plots: VTableV0::get_symbol::<NgSpiceAllPlots>(lib, b"ngSpice_AllPlots\0")?,
+            get_all_vecs: VTableV0::get_symbol::<NgSpiceAllVecs>(lib, b"ngSpice_AllVecs\0")?,
+            is_running: VTableV0::get_symbol::<NgSpiceRunning>(lib, b"ngSpice_running\0")?,
+            set_bkpt: VTableV0::get_symbol::<NgSpiceSetBkpt>(lib, b"ngSpice_SetBkpt\0").ok(),
+        })
     }
 }
 
+/// returns the name of a symbol given as a nul terminated byte string
+fn symbol_name(sname: &[u8]) -> String {
+    String::from_utf8_lossy(sname.strip_suffix(b"\0").unwrap_or(sname)).into_owned()
+}
+
 pub trait PkSpiceManager {
     /// Callback known as SendChar in Ngspice User's Manual
     fn cb_send_char(&mut self, msg: String, id: i32);
     /// Callback known as SendStat in Ngspice User's Manual
     fn cb_send_stat(&mut self, msg: String, id: i32);
+    /// Called after cb_send_stat with the message parsed, if it reports progress
+    fn cb_progress(&mut self, _progress: PkProgress, _id: i32) {}
     /// Callback known as ControlledExit in Ngspice User's Manual
     fn cb_ctrldexit(&mut self, status: i32, is_immediate: bool, is_quit: bool, id: i32);
     /// Callback known as SendData in Ngspice User's Manual
@@ -98,6 +247,54 @@ pub trait PkSpiceManager {
     /// Callback known as BGThreadRunning in Ngspice User's Manual
     fn cb_bgt_state(&mut self, is_fin: bool, id: i32);
 }
+/// Companion to PkSpiceManager, for the callbacks registered with ngSpice_Init_Sync.
+/// Supplies the values of `EXTERNAL` voltage and current sources, e.g. `vext in 0 dc 0 external`, at each timepoint.
+pub trait PkSpiceSyncManager: PkSpiceManager {
+    /// Callback known as GetVSRCData in Ngspice User's Manual.
+    /// Returns the voltage of the external voltage source named name (lowercase) at time
+    fn cb_get_vsrc(&mut self, time: f64, name: String, id: i32) -> f64;
+    /// Callback known as GetISRCData in Ngspice User's Manual.
+    /// Returns the current of the external current source named name (lowercase) at time
+    fn cb_get_isrc(&mut self, time: f64, name: String, id: i32) -> f64;
+    /// Callback known as GetSyncData in Ngspice User's Manual, called at each time step.
+    /// delta may be reduced to shorten the next time step, e.g. to meet an event of an external model.
+    /// redostep and location are as passed by ngspice, telling where in the time step loop it is called from.
+    fn cb_sync(
+        &mut self,
+        _time: f64,
+        _delta: &mut f64,
+        _old_delta: f64,
+        _redostep: i32,
+        _id: i32,
+        _location: i32,
+    ) -> i32 {
+        0
+    }
+}
+/// Companion to PkSpiceManager, for the XSPICE event node callbacks registered with ngSpice_Init_Evt
+pub trait PkSpiceEvtManager: PkSpiceManager {
+    /// Callback known as SendInitEvtData in Ngspice User's Manual, called once per event node before the simulation.
+    /// Builds up a dictionary of node index to name and udn_name (node type, e.g. `d` for digital)
+    fn cb_send_init_evt_data(
+        &mut self,
+        node_index: i32,
+        max_index: i32,
+        name: String,
+        udn_name: String,
+        id: i32,
+    );
+    /// Callback known as SendEvtData in Ngspice User's Manual, called per event node when its value changes.
+    /// svalue is the value as printed, e.g. `1s` for a digital node, dvalue a real value for plotting. mode is op, dc or tran
+    fn cb_send_evt_data(
+        &mut self,
+        node_index: i32,
+        step: f64,
+        dvalue: f64,
+        svalue: String,
+        mode: i32,
+        id: i32,
+    );
+}
 /// Represents a link to the sharedspice library
 pub struct PkSpice<T>
 where
@@ -105,8 +302,17 @@ where
 {
     #[allow(dead_code)]
     library: Library,
+    /// copy of the library file linked to by an isolated instance, removed after the library is unloaded
+    #[allow(dead_code)]
+    copy: Option<LibraryCopy>,
     api: VTableV0,
     manager: Option<Arc<T>>,
+    /// directories searched for code model libraries, in order
+    cm_paths: Vec<PathBuf>,
+    /// version and features detected at load time
+    info: PkSpiceInfo,
+    /// ident passed to the callbacks, see `new_isolated`
+    id: i32,
 }
 
 impl<T> PkSpice<T>
@@ -114,8 +320,8 @@ where
     T: PkSpiceManager,
 {
     /// Links to a sharedspice library given by path.
-    /// Returns error if the file given by path does not exist.
-    /// Crashes if any expected symbols are not found, which will happen if path points to an incorrect file, or to a much older version of sharedspice.
+    /// Returns error if the file given by path does not exist, or does not export the symbols required,
+    /// which will happen if path points to an incorrect file, or to a much older version of sharedspice.
     pub fn new(path: &std::ffi::OsStr) -> Result<PkSpice<T>, PkSpiceError> {
         unsafe {
             let lib = match Library::new(path) {
@@ -124,19 +330,61 @@ where
                     return Err(PkSpiceError::SharedspiceNotFound(path.to_os_string()));
                 }
             };
-            let vtable = VTableV0::new(&lib); // todo handle symbol not found
-            Ok(PkSpice {
+            let vtable = VTableV0::new(&lib)?;
+            let info = PkSpiceInfo {
+                version: None,
+                sync: vtable.init_sync.is_some(),
+                breakpoints: vtable.set_bkpt.is_some(),
+                xspice: vtable.cm_input_path.is_some()
+                    && vtable.get_evt_node_info.is_some()
+                    && vtable.get_all_evt_nodes.is_some()
+                    && vtable.init_evt.is_some(),
+            };
+            let mut spice = PkSpice {
                 library: lib,
+                copy: None,
                 api: vtable,
                 manager: None,
-            })
+                cm_paths: vec![],
+                info,
+                id: 0,
+            };
+            spice.info.version = spice.detect_version();
+            Ok(spice)
+        }
+    }
+    /// Returns the ngspice version and optional features detected when the library was loaded
+    pub fn info(&self) -> &PkSpiceInfo {
+        &self.info
+    }
+    /// initializes ngspice to capture the output of the `version` command, returns the version reported
+    fn detect_version(&self) -> Option<String> {
+        let mut output: Vec<String> = vec![];
+        let ret = (self.api.init)(
+            Some(cb_capture),
+            None,
+            None,
+            None,
+            None,
+            None,
+            &mut output as *mut _ as *const c_void,
+        );
+        if ret != 0 {
+            return None;
         }
+        let cmd = std::ffi::CString::new("version -s").unwrap();
+        (self.api.command)(cmd.as_ptr());
+        // unregister before output goes out of scope
+        (self.api.init)(None, None, None, None, None, None, std::ptr::null());
+        parse_version(&output)
     }
     /// API function known as ngSpice_Init in Ngspice User's Manual
-    pub fn init(&mut self, manager: Option<Arc<T>>) -> i32 {
+    /// An isolated instance also sets its id as ngspice's ident, with ngSpice_Init_Sync without sync callbacks:
+    /// call init_sync afterwards to register them.
+    pub fn init(&mut self, manager: Option<Arc<T>>) -> Result<(), PkSpiceError> {
         // drop existing manager
         // keep reference to new manager
-        match manager {
+        let ret1 = match manager {
             Some(m) => {
                 let ret1 = (self.api.init)(
                     Some(cbw_send_char::<T>),
@@ -155,10 +403,55 @@ where
                 self.manager = None; // drop the previous manager, AFTER the new manager is registered
                 ret1
             }
+        };
+        init_result(ret1)?;
+        if self.id != 0 {
+            if let Some(init_sync) = &self.api.init_sync {
+                init_result(init_sync(None, None, None, &self.id, std::ptr::null()))?;
+            }
         }
+        Ok(())
+    }
+    /// API function known as ngSpice_Init_Sync in Ngspice User's Manual
+    /// Registers the sync callbacks of the manager registered by init, which must be called first.
+    /// Sets `self.id()` as ngspice's ident, passed to the callbacks.
+    pub fn init_sync(&mut self) -> Result<(), PkSpiceError>
+    where
+        T: PkSpiceSyncManager,
+    {
+        let init_sync = optional(&self.api.init_sync, "ngSpice_Init_Sync")?;
+        let ret = match &self.manager {
+            Some(m) => init_sync(
+                Some(cbw_get_vsrc_data::<T>),
+                Some(cbw_get_isrc_data::<T>),
+                Some(cbw_get_sync_data::<T>),
+                &self.id,
+                &**m as *const _ as *const c_void,
+            ),
+            None => init_sync(None, None, None, &self.id, std::ptr::null()),
+        };
+        init_result(ret)
+    }
+    /// API function known as ngSpice_Init_Evt in Ngspice User's Manual
+    /// Registers the event node callbacks of the manager registered by init, which must be called first.
+    pub fn init_evt(&mut self) -> Result<(), PkSpiceError>
+    where
+        T: PkSpiceEvtManager,
+    {
+        let init_evt = optional(&self.api.init_evt, "ngSpice_Init_Evt")?;
+        let ret = match &self.manager {
+            Some(m) => init_evt(
+                Some(cbw_send_evt_data::<T>),
+                Some(cbw_send_init_evt_data::<T>),
+                &**m as *const _ as *const c_void,
+            ),
+            None => init_evt(None, None, std::ptr::null()),
+        };
+        init_result(ret)
     }
     /// API function known as ngSpice_Command in Ngspice User's Manual
     /// If cmdstr is an empty string, NULL is sent to ngSpice_Command, which clears the internal control structures.
+    /// Returns true if ngspice reports an error.
     pub fn command(&self, cmdstr: &str) -> bool {
         let ret = if cmdstr.is_empty() {
             (self.api.command)(std::ptr::null())
@@ -173,6 +466,14 @@ where
         ret != 0
     }
 
+    /// Same as command, but returns an error if ngspice reports one
+    pub fn try_command(&self, cmdstr: &str) -> Result<(), PkSpiceError> {
+        if self.command(cmdstr) {
+            return Err(PkSpiceError::CommandFailed(cmdstr.to_string()));
+        }
+        Ok(())
+    }
+
     pub fn get_vec_info(&self, vecname: &str) -> PkVectorinfo {
         unsafe {
             let cvecname = std::ffi::CString::new(vecname).unwrap();
@@ -181,6 +482,36 @@ where
         }
     }
 
+    /// API function known as ngGet_Evt_NodeInfo in Ngspice User's Manual
+    /// Returns all values of the event node named nodename (`name` or `plotname.name`), None if there is no such node
+    /// or the library is built without XSPICE
+    pub fn get_evt_node_info(&self, nodename: &str) -> Option<PkEvtSharedData> {
+        let get_evt_node_info = self.api.get_evt_node_info.as_ref()?;
+        unsafe {
+            let cnodename = std::ffi::CString::new(nodename).unwrap();
+            let pevtshareddata = get_evt_node_info(cnodename.as_ptr());
+            if pevtshareddata.is_null() {
+                return None;
+            }
+            Some((*pevtshareddata).to_pk())
+        }
+    }
+
+    /// API function known as ngSpice_AllEvtNodes in Ngspice User's Manual
+    /// Returns no nodes if the library is built without XSPICE
+    pub fn get_all_evt_nodes(&self) -> Vec<String> {
+        let Some(get_all_evt_nodes) = &self.api.get_all_evt_nodes else {
+            return vec![];
+        };
+        unsafe {
+            let ppcstr = get_all_evt_nodes();
+            if ppcstr.is_null() {
+                return vec![];
+            }
+            c_strings(ppcstr)
+        }
+    }
+
     pub fn get_cur_plot(&self) -> String {
         unsafe {
             let pcstr = (self.api.get_cur_plot)();
@@ -209,6 +540,54 @@ where
     pub fn is_running(&self) -> bool {
         (self.api.is_running)()
     }
+
+    /// API function known as ngSpice_SetBkpt in Ngspice User's Manual
+    /// Sets a breakpoint at time, a time point the transient analysis is forced to hit. May be set before the analysis starts.
+    /// Combined with the `stop when time >= <time>` command, the analysis pauses exactly at time, to be resumed with
+    /// `delete all` (removing the stop condition) and `bg_command("resume")`.
+    /// Returns an error if the breakpoint could not be set, e.g. no circuit is loaded.
+    pub fn set_bkpt(&self, time: f64) -> Result<(), PkSpiceError> {
+        let set_bkpt = optional(&self.api.set_bkpt, "ngSpice_SetBkpt")?;
+        if !set_bkpt(time) {
+            return Err(PkSpiceError::CommandFailed(format!(
+                "ngSpice_SetBkpt {}",
+                time
+            )));
+        }
+        Ok(())
+    }
+
+    /// Executes cmdstr, e.g. `tran 10u 1m` or `resume`, in ngspice's background thread. Returns immediately.
+    /// cb_bgt_state is called when the thread starts and finishes, e.g. at the end of the analysis or at a breakpoint.
+    /// Returns error if a background thread is already running, or ngspice reports an error.
+    pub fn bg_command(&self, cmdstr: &str) -> Result<(), PkSpiceError> {
+        if self.is_running() {
+            return Err(PkSpiceError::BackgroundRunning);
+        }
+        let cmd = format!("bg_{}", cmdstr);
+        let ccmdstr = std::ffi::CString::new(cmd.as_str()).unwrap();
+        if (self.api.command)(ccmdstr.as_ptr()) != 0 {
+            return Err(PkSpiceError::CommandFailed(cmd));
+        }
+        Ok(())
+    }
+
+    /// Halts the command running in ngspice's background thread. The analysis may be continued with `bg_command("resume")`.
+    /// Returns error if ngspice reports an error.
+    pub fn bg_halt(&self) -> Result<(), PkSpiceError> {
+        let ccmdstr = std::ffi::CString::new("bg_halt").unwrap();
+        if (self.api.command)(ccmdstr.as_ptr()) != 0 {
+            return Err(PkSpiceError::CommandFailed(String::from("bg_halt")));
+        }
+        Ok(())
+    }
+
+    /// Blocks until the command running in ngspice's background thread finishes, polling every interval
+    pub fn bg_wait(&self, interval: std::time::Duration) {
+        while self.is_running() {
+            std::thread::sleep(interval);
+        }
+    }
 }
 
 unsafe fn c_strings(ptr: *const *const c_char) -> Vec<String> {
@@ -230,3 +609,37 @@ unsafe fn c_strings(ptr: *const *const c_char) -> Vec<String> {
     }
     vec
 }
+
+/// returns the optional symbol, or an error naming it if the library does not export it
+fn optional<'a, F>(
+    symbol: &'a Option<RawSymbol<F>>,
+    name: &str,
+) -> Result<&'a RawSymbol<F>, PkSpiceError> {
+    symbol
+        .as_ref()
+        .ok_or_else(|| PkSpiceError::SymbolNotFound(name.to_string()))
+}
+
+/// converts the return code of ngSpice_Init and its companions
+fn init_result(ret: c_int) -> Result<(), PkSpiceError> {
+    match ret {
+        0 => Ok(()),
+        code => Err(PkSpiceError::InitFailed(code)),
+    }
+}
+
+/// returns the version from the output of the `version` command, e.g. `39` from `stdout ** ngspice-39 : Circuit level simulation program`
+fn parse_version(output: &[String]) -> Option<String> {
+    output
+        .iter()
+        .flat_map(|line| line.split_whitespace())
+        .find_map(|token| token.strip_prefix("ngspice-"))
+        .map(|v| v.to_string())
+}
+
+/// SendChar callback collecting the output of ngspice into the Vec<String> user points to
+unsafe extern "C" fn cb_capture(msg: *const c_char, _id: c_int, user: *const c_void) -> c_int {
+    let output = &mut *(user as *mut Vec<String>);
+    output.push(std::ffi::CStr::from_ptr(msg).to_string_lossy().into_owned());
+    0
+}
@@ -0,0 +1,138 @@
+//! locating the sharedspice library
+//!
+//! The library is searched for, in order, at the path or directories in `NGSPICE_LIBRARY_PATH`, at the `sharedspice`
+//! setting of a `paprika.cfg` file in the current or the executable's directory, in the executable's directory,
+//! in the directories of the platform's library path variable, and in the platform's standard library directories.
+
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+use crate::{PkSpice, PkSpiceError, PkSpiceManager};
+
+/// environment variable holding the path of the sharedspice library, or directories to search for it
+pub const LIBRARY_PATH_VAR: &str = "NGSPICE_LIBRARY_PATH";
+/// config file holding a `sharedspice = <path>` line
+pub const CONFIG_FILE: &str = "paprika.cfg";
+/// key of the library path in the config file
+const CONFIG_KEY: &str = "sharedspice";
+
+/// file names of the sharedspice library, in order of preference
+#[cfg(target_family = "windows")]
+pub const SHAREDSPICE_NAMES: &[&str] = &["ngspice.dll", "sharedspice.dll"];
+#[cfg(target_os = "macos")]
+pub const SHAREDSPICE_NAMES: &[&str] = &["libngspice.dylib", "libngspice.0.dylib"];
+#[cfg(all(target_family = "unix", not(target_os = "macos")))]
+pub const SHAREDSPICE_NAMES: &[&str] = &["libngspice.so", "libngspice.so.0"];
+
+/// environment variable listing the directories the platform's loader searches
+#[cfg(target_family = "windows")]
+const LOADER_PATH_VAR: &str = "PATH";
+#[cfg(target_os = "macos")]
+const LOADER_PATH_VAR: &str = "DYLD_LIBRARY_PATH";
+#[cfg(all(target_family = "unix", not(target_os = "macos")))]
+const LOADER_PATH_VAR: &str = "LD_LIBRARY_PATH";
+
+/// standard library directories, relative ones are relative to the current directory
+#[cfg(target_family = "windows")]
+const STANDARD_DIRS: &[&str] = &[".", "paprika"];
+#[cfg(target_os = "macos")]
+const STANDARD_DIRS: &[&str] = &[
+    "/opt/homebrew/lib",
+    "/usr/local/lib",
+    "/opt/local/lib",
+    "/usr/lib",
+];
+#[cfg(all(target_family = "unix", not(target_os = "macos")))]
+const STANDARD_DIRS: &[&str] = &[
+    "/usr/local/lib",
+    "/usr/lib",
+    "/usr/lib64",
+    "/usr/lib/x86_64-linux-gnu",
+    "/usr/lib/aarch64-linux-gnu",
+    "/lib",
+];
+
+impl<T> PkSpice<T>
+where
+    T: PkSpiceManager,
+{
+    /// Links to the sharedspice library found by `locate_sharedspice`
+    pub fn locate() -> Result<PkSpice<T>, PkSpiceError> {
+        PkSpice::new(locate_sharedspice()?.as_os_str())
+    }
+}
+
+/// Returns the first sharedspice library found in the search order, see `sharedspice_candidates`.
+/// Returns error listing the paths searched if there is none.
+pub fn locate_sharedspice() -> Result<PathBuf, PkSpiceError> {
+    let candidates = sharedspice_candidates();
+    match candidates.iter().find(|p| p.is_file()) {
+        Some(p) => Ok(p.clone()),
+        None => Err(PkSpiceError::SharedspiceNotLocated(candidates)),
+    }
+}
+
+/// Returns the paths searched for the sharedspice library, in order
+pub fn sharedspice_candidates() -> Vec<PathBuf> {
+    sharedspice_candidates_in(std::env::var_os(LIBRARY_PATH_VAR).as_deref())
+}
+
+/// Returns the paths searched for the sharedspice library, in order,
+/// with library_path taking the place of the value of `NGSPICE_LIBRARY_PATH`
+pub fn sharedspice_candidates_in(library_path: Option<&OsStr>) -> Vec<PathBuf> {
+    let mut ret = vec![];
+    if let Some(var) = library_path {
+        let path = PathBuf::from(var);
+        if path.is_file() {
+            ret.push(path);
+        } else {
+            ret.extend(std::env::split_paths(var).flat_map(|dir| library_files(&dir)));
+        }
+    }
+    let exe_dir = std::env::current_exe()
+        .ok()
+        .and_then(|p| p.parent().map(Path::to_path_buf));
+    let config_dirs = [std::env::current_dir().ok(), exe_dir.clone()];
+    for dir in config_dirs.iter().flatten() {
+        if let Some(path) = config_setting(&dir.join(CONFIG_FILE)) {
+            ret.push(path);
+        }
+    }
+    if let Some(dir) = &exe_dir {
+        ret.extend(library_files(dir));
+    }
+    if let Some(var) = std::env::var_os(LOADER_PATH_VAR) {
+        ret.extend(std::env::split_paths(&var).flat_map(|dir| library_files(&dir)));
+    }
+    for dir in STANDARD_DIRS {
+        ret.extend(library_files(Path::new(dir)));
+    }
+    let mut seen = vec![];
+    ret.retain(|p| {
+        let new = !seen.contains(p);
+        seen.push(p.clone());
+        new
+    });
+    ret
+}
+
+/// returns the paths of the sharedspice library names in dir
+fn library_files(dir: &Path) -> Vec<PathBuf> {
+    if dir.as_os_str().is_empty() {
+        return vec![];
+    }
+    SHAREDSPICE_NAMES.iter().map(|n| dir.join(n)).collect()
+}
+
+/// returns the library path set in the config file at path, relative paths are relative to the config file
+fn config_setting(path: &Path) -> Option<PathBuf> {
+    let src = std::fs::read_to_string(path).ok()?;
+    let value = src.lines().find_map(|line| {
+        let (key, value) = line.split_once('=')?;
+        (key.trim() == CONFIG_KEY).then(|| value.trim())
+    })?;
+    if value.is_empty() {
+        return None;
+    }
+    Some(path.parent()?.join(value))
+}
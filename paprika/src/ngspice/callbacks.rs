@@ -1,5 +1,5 @@
 use crate::ngspice::*;
-use crate::PkSpiceManager;
+use crate::{PkProgress, PkSpiceEvtManager, PkSpiceManager, PkSpiceSyncManager};
 use libc::*;
 
 pub unsafe extern "C" fn cbw_send_char<T>(
@@ -28,11 +28,12 @@ where
     T: PkSpiceManager,
 {
     unsafe {
-        <T as PkSpiceManager>::cb_send_stat(
-            &mut *(user as *mut T),
-            std::ffi::CStr::from_ptr(msg).to_str().unwrap().to_owned(),
-            id,
-        );
+        let msg = std::ffi::CStr::from_ptr(msg).to_str().unwrap().to_owned();
+        let progress = PkProgress::parse(&msg);
+        <T as PkSpiceManager>::cb_send_stat(&mut *(user as *mut T), msg, id);
+        if let Some(progress) = progress {
+            <T as PkSpiceManager>::cb_progress(&mut *(user as *mut T), progress, id);
+        }
     }
     0
 }
@@ -103,3 +104,128 @@ where
     }
     0
 }
+pub unsafe extern "C" fn cbw_get_vsrc_data<T>(
+    value: *mut c_double,
+    time: c_double,
+    name: *const c_char,
+    id: c_int,
+    user: *const c_void,
+) -> c_int
+where
+    T: PkSpiceSyncManager,
+{
+    unsafe {
+        *value = <T as PkSpiceSyncManager>::cb_get_vsrc(
+            &mut *(user as *mut T),
+            time,
+            std::ffi::CStr::from_ptr(name)
+                .to_string_lossy()
+                .into_owned(),
+            id,
+        );
+    }
+    0
+}
+pub unsafe extern "C" fn cbw_get_isrc_data<T>(
+    value: *mut c_double,
+    time: c_double,
+    name: *const c_char,
+    id: c_int,
+    user: *const c_void,
+) -> c_int
+where
+    T: PkSpiceSyncManager,
+{
+    unsafe {
+        *value = <T as PkSpiceSyncManager>::cb_get_isrc(
+            &mut *(user as *mut T),
+            time,
+            std::ffi::CStr::from_ptr(name)
+                .to_string_lossy()
+                .into_owned(),
+            id,
+        );
+    }
+    0
+}
+pub unsafe extern "C" fn cbw_get_sync_data<T>(
+    time: c_double,
+    delta: *mut c_double,
+    old_delta: c_double,
+    redostep: c_int,
+    id: c_int,
+    location: c_int,
+    user: *const c_void,
+) -> c_int
+where
+    T: PkSpiceSyncManager,
+{
+    unsafe {
+        <T as PkSpiceSyncManager>::cb_sync(
+            &mut *(user as *mut T),
+            time,
+            &mut *delta,
+            old_delta,
+            redostep,
+            id,
+            location,
+        )
+    }
+}
+#[allow(clippy::too_many_arguments)]
+pub unsafe extern "C" fn cbw_send_evt_data<T>(
+    node_index: c_int,
+    step: c_double,
+    dvalue: c_double,
+    svalue: *const c_char,
+    _pvalue: *const c_void,
+    _plen: c_int,
+    mode: c_int,
+    id: c_int,
+    user: *const c_void,
+) -> c_int
+where
+    T: PkSpiceEvtManager,
+{
+    unsafe {
+        <T as PkSpiceEvtManager>::cb_send_evt_data(
+            &mut *(user as *mut T),
+            node_index,
+            step,
+            dvalue,
+            std::ffi::CStr::from_ptr(svalue)
+                .to_string_lossy()
+                .into_owned(),
+            mode,
+            id,
+        );
+    }
+    0
+}
+pub unsafe extern "C" fn cbw_send_init_evt_data<T>(
+    node_index: c_int,
+    max_index: c_int,
+    name: *const c_char,
+    udn_name: *const c_char,
+    id: c_int,
+    user: *const c_void,
+) -> c_int
+where
+    T: PkSpiceEvtManager,
+{
+    unsafe {
+        <T as PkSpiceEvtManager>::cb_send_init_evt_data(
+            &mut *(user as *mut T),
+            node_index,
+            max_index,
+            std::ffi::CStr::from_ptr(name)
+                .to_string_lossy()
+                .into_owned(),
+            std::ffi::CStr::from_ptr(udn_name)
+                .to_string_lossy()
+                .into_owned(),
+            id,
+        );
+    }
+    0
+}
@@ -5,22 +5,57 @@ use std::ffi::CStr;
 #[derive(Copy, Clone, Debug)]
 #[repr(C)]
 pub struct NgEvtData {
-    dcop: c_double,
-    step: c_int,
+    dcop: c_int,
+    step: c_double,
     node_value: *const c_char,
 }
+impl NgEvtData {
+    /// returns none if the event has no value
+    pub unsafe fn to_pk(self) -> Option<PkEvtData> {
+        if self.node_value.is_null() {
+            return None;
+        }
+        Some(PkEvtData {
+            dcop: self.dcop != 0,
+            step: self.step,
+            node_value: CStr::from_ptr(self.node_value)
+                .to_string_lossy()
+                .into_owned(),
+        })
+    }
+}
 #[derive(Copy, Clone, Debug)]
 #[repr(C)]
 pub struct NgEvtSharedData {
-    evt_dect: *const NgEvtData,
+    evt_dect: *const *const NgEvtData,
     num_steps: c_int,
 }
+impl NgEvtSharedData {
+    /// ngspice returns no events array for a node without events, null entries and values are skipped
+    pub unsafe fn to_pk(self) -> PkEvtSharedData {
+        if self.evt_dect.is_null() || self.num_steps <= 0 {
+            return PkEvtSharedData { evts: vec![] };
+        }
+        let evts_slice = std::slice::from_raw_parts(self.evt_dect, self.num_steps as usize);
+        PkEvtSharedData {
+            evts: evts_slice
+                .iter()
+                .filter(|e| !e.is_null())
+                .filter_map(|e| (**e).to_pk())
+                .collect(),
+        }
+    }
+}
 #[derive(Copy, Clone, Debug)]
 #[repr(C)]
 pub struct NgComplex {
     cx_real: c_double,
     cx_imag: c_double,
 }
+/// flags of NgVectorinfo telling real from complex data, as in ngspice's dvec.h
+const VF_REAL: c_short = 1;
+const VF_COMPLEX: c_short = 2;
+
 #[derive(Copy, Clone, Debug)]
 #[repr(C)]
 pub struct NgVectorinfo {
@@ -33,8 +68,8 @@ pub struct NgVectorinfo {
 }
 impl NgVectorinfo {
     pub unsafe fn to_pk(self) -> PkVectorinfo {
-        let (real, comp) = match self.v_type {
-            1 => {
+        let (real, comp) = match self.v_flag & (VF_REAL | VF_COMPLEX) {
+            VF_REAL => {
                 // real
                 let cvec = std::slice::from_raw_parts(self.v_realdata, self.v_length as usize);
                 // create vec containing 'count' number of PkVecvalues
@@ -46,7 +81,7 @@ impl NgVectorinfo {
                 }
                 (Some(vec), None)
             } // real
-            2 => {
+            VF_COMPLEX => {
                 // complex
                 let cvec = std::slice::from_raw_parts(self.v_compdata, self.v_length as usize);
                 // create vec containing 'count' number of PkVecvalues
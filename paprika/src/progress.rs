@@ -0,0 +1,52 @@
+//! progress
+//!
+//! Parses the status messages ngspice sends to `cb_send_stat` while an analysis runs, e.g. `tran: 45.3%`,
+//! and `--ready--` when it is done.
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// status message sent when an analysis is done
+const READY: &str = "--ready--";
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+/// progress of the running analysis
+pub struct PkProgress {
+    /// lowercase name of the analysis, e.g. `tran`, empty once done
+    pub analysis: String,
+    /// percentage done, from 0 to 100
+    pub percent: f64,
+}
+
+impl PkProgress {
+    /// parses a status message as received by cb_send_stat, returns None if it is not a progress report.
+    /// `--ready--` is parsed as 100% of no analysis.
+    pub fn parse(msg: &str) -> Option<Self> {
+        let msg = msg.trim();
+        if msg == READY {
+            return Some(PkProgress {
+                analysis: String::new(),
+                percent: 100.0,
+            });
+        }
+        let (analysis, percent) = msg.rsplit_once(':')?;
+        let percent: f64 = percent.trim().strip_suffix('%')?.trim_end().parse().ok()?;
+        let analysis = analysis.trim();
+        if analysis.is_empty() || !percent.is_finite() {
+            return None;
+        }
+        Some(PkProgress {
+            analysis: analysis.to_lowercase(),
+            percent: percent.clamp(0.0, 100.0),
+        })
+    }
+    /// returns true once the analysis is done
+    pub fn is_done(&self) -> bool {
+        self.analysis.is_empty()
+    }
+    /// returns the fraction done, from 0 to 1
+    pub fn fraction(&self) -> f64 {
+        self.percent / 100.0
+    }
+}
@@ -0,0 +1,352 @@
+//! Reading and writing ngspice raw files, as written by the `write` command.
+//!
+//! A raw file holds one or more plots, each a header followed by the values, either ASCII (`Values:`) or binary (`Binary:`).
+//! Binary values are little endian f64, two per value for complex plots.
+//! Vectors are read into `PkVectorinfo`, the same as returned by `PkSpice::get_vec_info`.
+
+use crate::PkVectorinfo;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// vector flag: real data (VF_REAL in ngspice)
+pub const VF_REAL: i16 = 1;
+/// vector flag: complex data (VF_COMPLEX in ngspice)
+pub const VF_COMPLEX: i16 = 2;
+
+/// vector type names as written in raw files, indexed by the vector type (simulation_types in ngspice)
+const TYPE_NAMES: [&str; 23] = [
+    "notype",
+    "time",
+    "frequency",
+    "voltage",
+    "current",
+    "voltage-density",
+    "current-density",
+    "sqr-voltage-density",
+    "sqr-current-density",
+    "sqr-voltage",
+    "sqr-current",
+    "pole",
+    "zero",
+    "s-param",
+    "temp-sweep",
+    "res-sweep",
+    "impedance",
+    "admittance",
+    "power",
+    "phase",
+    "decibel",
+    "capacitance",
+    "charge",
+];
+
+#[derive(Debug)]
+pub enum PkRawError {
+    /// Raw file could not be read or written
+    Io(std::io::Error),
+    /// Raw file is malformed
+    Parse(String),
+}
+
+impl std::fmt::Display for PkRawError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PkRawError::Io(e) => write!(f, "{}", e),
+            PkRawError::Parse(s) => write!(f, "malformed raw file: {}", s),
+        }
+    }
+}
+
+impl From<std::io::Error> for PkRawError {
+    fn from(e: std::io::Error) -> Self {
+        PkRawError::Io(e)
+    }
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+/// A plot of a raw file, i.e. the results of one analysis. The first vector is the scale.
+pub struct PkRawPlot {
+    pub title: String,
+    pub date: String,
+    /// e.g. `Transient Analysis`
+    pub plotname: String,
+    pub is_complex: bool,
+    pub vecs: Vec<PkVectorinfo>,
+}
+
+impl PkRawPlot {
+    /// returns the scale vector, e.g. time or frequency
+    pub fn scale(&self) -> Option<&PkVectorinfo> {
+        self.vecs.first()
+    }
+    /// returns the vector named name
+    pub fn vec(&self, name: &str) -> Option<&PkVectorinfo> {
+        self.vecs.iter().find(|v| v.name == name)
+    }
+    /// returns the number of points, i.e. the length of the scale vector
+    pub fn len(&self) -> usize {
+        self.scale().map(|v| v.length as usize).unwrap_or(0)
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// returns the vector type for a raw file type name
+fn stype_from_name(name: &str) -> i32 {
+    TYPE_NAMES.iter().position(|n| *n == name).unwrap_or(0) as i32
+}
+
+/// returns the raw file type name for a vector type
+fn stype_name(stype: i32) -> &'static str {
+    TYPE_NAMES
+        .get(stype as usize)
+        .copied()
+        .unwrap_or(TYPE_NAMES[0])
+}
+
+/// reads a line from bytes at pos, advancing pos past the line ending
+fn next_line<'a>(bytes: &'a [u8], pos: &mut usize) -> Option<&'a str> {
+    if *pos >= bytes.len() {
+        return None;
+    }
+    let rest = &bytes[*pos..];
+    let len = rest.iter().position(|b| *b == b'\n').unwrap_or(rest.len());
+    *pos += (len + 1).min(rest.len());
+    // header lines are ascii, anything else is reported as malformed by the caller
+    std::str::from_utf8(&rest[..len])
+        .ok()
+        .map(|s| s.trim_end_matches('\r'))
+}
+
+fn parse_err<T>(msg: impl Into<String>) -> Result<T, PkRawError> {
+    Err(PkRawError::Parse(msg.into()))
+}
+
+/// parses a float as written by ngspice, which may also write `nan` and `inf` in any case
+fn parse_f64(s: &str) -> Result<f64, PkRawError> {
+    match s.parse::<f64>() {
+        Ok(v) => Ok(v),
+        Err(_) => match s.to_lowercase().trim_start_matches(['+', '-']) {
+            "nan" => Ok(f64::NAN),
+            "inf" | "infinity" => Ok(if s.starts_with('-') {
+                f64::NEG_INFINITY
+            } else {
+                f64::INFINITY
+            }),
+            _ => parse_err(format!("invalid number {}", s)),
+        },
+    }
+}
+
+/// Parses the plots of a raw file.
+pub fn read_raw(bytes: &[u8]) -> Result<Vec<PkRawPlot>, PkRawError> {
+    let mut plots = vec![];
+    let mut pos = 0;
+    loop {
+        // skip blank lines between plots
+        let start = pos;
+        match next_line(bytes, &mut pos) {
+            None => break,
+            Some(l) if l.trim().is_empty() => continue,
+            Some(_) => pos = start,
+        }
+        plots.push(read_plot(bytes, &mut pos)?);
+    }
+    Ok(plots)
+}
+
+/// Parses one plot starting at pos, advancing pos to the end of the plot.
+fn read_plot(bytes: &[u8], pos: &mut usize) -> Result<PkRawPlot, PkRawError> {
+    let mut plot = PkRawPlot {
+        title: String::new(),
+        date: String::new(),
+        plotname: String::new(),
+        is_complex: false,
+        vecs: vec![],
+    };
+    let mut nvars = None;
+    let mut npoints = None;
+    let binary = loop {
+        let Some(line) = next_line(bytes, pos) else {
+            return parse_err("missing Values: or Binary: section");
+        };
+        let (key, value) = line.split_once(':').unwrap_or((line, ""));
+        let value = value.trim();
+        match key.trim() {
+            "Title" => plot.title = value.to_string(),
+            "Date" => plot.date = value.to_string(),
+            "Plotname" => plot.plotname = value.to_string(),
+            "Flags" => plot.is_complex = value.split_whitespace().any(|f| f == "complex"),
+            "No. Variables" => nvars = value.parse::<usize>().ok(),
+            "No. Points" => npoints = value.parse::<usize>().ok(),
+            "Variables" => {
+                let Some(n) = nvars else {
+                    return parse_err("Variables: before No. Variables:");
+                };
+                for _ in 0..n {
+                    let Some(var) = next_line(bytes, pos) else {
+                        return parse_err("too few variables");
+                    };
+                    let fields: Vec<&str> = var.split_whitespace().collect();
+                    if fields.len() < 3 {
+                        return parse_err(format!("invalid variable {}", var));
+                    }
+                    plot.vecs.push(PkVectorinfo {
+                        name: fields[1].to_string(),
+                        stype: stype_from_name(fields[2]),
+                        flag: if plot.is_complex { VF_COMPLEX } else { VF_REAL },
+                        realdata: None,
+                        compdata: None,
+                        length: 0,
+                    });
+                }
+            }
+            "Values" => break false,
+            "Binary" => break true,
+            // e.g. Command:, Option:
+            _ => {}
+        }
+    };
+    let Some(npoints) = npoints else {
+        return parse_err("missing No. Points:");
+    };
+    if plot.vecs.is_empty() {
+        return parse_err("missing Variables:");
+    }
+    let nvars = plot.vecs.len();
+    let width = if plot.is_complex { 2 } else { 1 };
+
+    // values in point order: point 0 of every vector, then point 1 of every vector, ...
+    let mut values = Vec::with_capacity(npoints * nvars * width);
+    if binary {
+        let want = npoints * nvars * width * 8;
+        let avail = (bytes.len() - *pos).min(want);
+        // a run which was interrupted leaves a partial last point, which is dropped
+        let whole = avail / (nvars * width * 8) * nvars * width * 8;
+        values.extend(
+            bytes[*pos..*pos + whole]
+                .chunks_exact(8)
+                .map(|c| f64::from_le_bytes(c.try_into().unwrap())),
+        );
+        *pos += avail;
+    } else {
+        let want = npoints * (nvars * width + 1);
+        let mut ntokens = 0;
+        while ntokens < want {
+            let start = *pos;
+            let Some(line) = next_line(bytes, pos) else {
+                break;
+            };
+            if line.starts_with("Title:") {
+                // next plot began early
+                *pos = start;
+                break;
+            }
+            for tok in line.split_whitespace() {
+                // every point begins with its index
+                if ntokens % (nvars * width + 1) != 0 {
+                    if plot.is_complex {
+                        let Some((re, im)) = tok.split_once(',') else {
+                            return parse_err(format!("invalid complex value {}", tok));
+                        };
+                        values.push(parse_f64(re)?);
+                        values.push(parse_f64(im)?);
+                        ntokens += 1;
+                    } else {
+                        values.push(parse_f64(tok)?);
+                    }
+                }
+                ntokens += 1;
+            }
+        }
+        values.truncate(values.len() / (nvars * width) * nvars * width);
+    }
+
+    let points = values.len() / (nvars * width);
+    for (i, v) in plot.vecs.iter_mut().enumerate() {
+        let col = (0..points).map(|p| (p * nvars + i) * width);
+        if plot.is_complex {
+            v.compdata = Some(
+                col.map(|k| num::Complex::new(values[k], values[k + 1]))
+                    .collect(),
+            );
+        } else {
+            v.realdata = Some(col.map(|k| values[k]).collect());
+        }
+        v.length = points as i32;
+    }
+    Ok(plot)
+}
+
+/// Reads the plots of the raw file at path.
+pub fn read_raw_file(path: &std::path::Path) -> Result<Vec<PkRawPlot>, PkRawError> {
+    read_raw(&std::fs::read(path)?)
+}
+
+/// Writes plots in raw file format, with binary or ASCII values.
+/// Vectors of a real plot are written from `realdata`, of a complex plot from `compdata` (or `realdata` with zero imaginary part).
+pub fn write_raw(plots: &[PkRawPlot], binary: bool) -> Vec<u8> {
+    let mut ret = Vec::new();
+    for plot in plots {
+        let npoints = plot.len();
+        let header = format!(
+            "Title: {}\nDate: {}\nPlotname: {}\nFlags: {}\nNo. Variables: {}\nNo. Points: {}\nVariables:\n",
+            plot.title,
+            plot.date,
+            plot.plotname,
+            if plot.is_complex { "complex" } else { "real" },
+            plot.vecs.len(),
+            npoints
+        );
+        ret.extend_from_slice(header.as_bytes());
+        for (i, v) in plot.vecs.iter().enumerate() {
+            ret.extend_from_slice(
+                format!("\t{}\t{}\t{}\n", i, v.name, stype_name(v.stype)).as_bytes(),
+            );
+        }
+        ret.extend_from_slice(if binary { b"Binary:\n" } else { b"Values:\n" });
+
+        let value = |v: &PkVectorinfo, p: usize| -> num::Complex<f64> {
+            if let Some(c) = v.compdata.as_ref().and_then(|d| d.get(p)) {
+                *c
+            } else {
+                let re = v.realdata.as_ref().and_then(|d| d.get(p)).copied();
+                num::Complex::new(re.unwrap_or(0.0), 0.0)
+            }
+        };
+        for p in 0..npoints {
+            if !binary {
+                ret.extend_from_slice(format!(" {}", p).as_bytes());
+            }
+            for v in &plot.vecs {
+                let c = value(v, p);
+                match (binary, plot.is_complex) {
+                    (true, true) => {
+                        ret.extend_from_slice(&c.re.to_le_bytes());
+                        ret.extend_from_slice(&c.im.to_le_bytes());
+                    }
+                    (true, false) => ret.extend_from_slice(&c.re.to_le_bytes()),
+                    (false, true) => {
+                        ret.extend_from_slice(format!("\t{:.15e},{:.15e}\n", c.re, c.im).as_bytes())
+                    }
+                    (false, false) => {
+                        ret.extend_from_slice(format!("\t{:.15e}\n", c.re).as_bytes())
+                    }
+                }
+            }
+        }
+    }
+    ret
+}
+
+/// Writes plots to a raw file at path, with binary or ASCII values.
+pub fn write_raw_file(
+    path: &std::path::Path,
+    plots: &[PkRawPlot],
+    binary: bool,
+) -> Result<(), PkRawError> {
+    std::fs::write(path, write_raw(plots, binary))?;
+    Ok(())
+}
@@ -67,3 +67,74 @@ pub struct PkVectorinfo {
     pub compdata: Option<Vec<num::Complex<f64>>>,
     pub length: i32,
 }
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+/// Struct known as evt_data in Ngspice User's Manual: the value of an event node from a point in time
+pub struct PkEvtData {
+    pub dcop: bool,
+    /// simulation time
+    pub step: f64,
+    /// value as printed by ngspice, e.g. `1s` for a digital node
+    pub node_value: String,
+}
+impl PkEvtData {
+    /// returns the value of a digital node, None if the node is not digital
+    pub fn digital(&self) -> Option<PkDigital> {
+        PkDigital::parse(&self.node_value)
+    }
+}
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+/// Struct known as evt_shared_data in Ngspice User's Manual: all values of an event node after a simulation
+pub struct PkEvtSharedData {
+    pub evts: Vec<PkEvtData>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+/// logic level of a digital node
+pub enum PkDigitalState {
+    Zero,
+    One,
+    Unknown,
+}
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+/// drive strength of a digital node
+pub enum PkDigitalStrength {
+    Strong,
+    Resistive,
+    HiImpedance,
+    Undetermined,
+}
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+/// value of a digital node: one of 0s, 1s, Us, 0r, 1r, Ur, 0z, 1z, Uz, 0u, 1u, Uu
+pub struct PkDigital {
+    pub state: PkDigitalState,
+    pub strength: PkDigitalStrength,
+}
+impl PkDigital {
+    /// parses a digital node value as printed by ngspice, e.g. `1s`
+    pub fn parse(s: &str) -> Option<Self> {
+        let mut chars = s.trim().chars();
+        let state = match chars.next()? {
+            '0' => PkDigitalState::Zero,
+            '1' => PkDigitalState::One,
+            'U' => PkDigitalState::Unknown,
+            _ => return None,
+        };
+        let strength = match chars.next()? {
+            's' => PkDigitalStrength::Strong,
+            'r' => PkDigitalStrength::Resistive,
+            'z' => PkDigitalStrength::HiImpedance,
+            'u' => PkDigitalStrength::Undetermined,
+            _ => return None,
+        };
+        if chars.next().is_some() {
+            return None;
+        }
+        Some(PkDigital { state, strength })
+    }
+}
@@ -1,6 +1,6 @@
 use std::collections::VecDeque;
 use std::ffi::OsStr;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
 
 use paprika::*;
 
@@ -51,27 +51,683 @@ impl paprika::PkSpiceManager for Manager {
 
 #[test]
 fn test_cmd_echo() {
-    let mut spice = PkSpice::<Manager>::new(OsStr::new("ngspice.dll")).unwrap();
+    let _lock = FAKE.lock().unwrap_or_else(|e| e.into_inner());
+    let mut spice = fake_spice::<Manager>();
     let buf = Arc::new(RwLock::new(VecDeque::<String>::with_capacity(10)));
     let manager = Arc::new(Manager::new(buf.clone()));
 
-    spice.init(Some(manager)); // register
+    spice.init(Some(manager)).unwrap(); // register
 
     spice.command("echo echo command");
     let s = (*buf.write().unwrap()).pop_back().unwrap();
     assert_eq!(s, "stdout echo command");
     spice.command("quit");
-} // cannot run tests in parallel
+}
+
+/// value of an external source given time and source name
+type Source = Box<dyn FnMut(f64, &str) -> f64 + Send + Sync>;
+/// manager supplying external source values from a closure
+struct SyncManager {
+    source: Source,
+    names: Vec<String>,
+    ids: Vec<i32>,
+}
+#[allow(unused_variables)]
+impl paprika::PkSpiceManager for SyncManager {
+    fn cb_send_char(&mut self, msg: String, id: i32) {}
+    fn cb_send_stat(&mut self, msg: String, id: i32) {}
+    fn cb_ctrldexit(&mut self, status: i32, is_immediate: bool, is_quit: bool, id: i32) {}
+    fn cb_send_init(&mut self, pkvecinfoall: PkVecinfoall, id: i32) {}
+    fn cb_send_data(&mut self, pkvecvaluesall: PkVecvaluesall, count: i32, id: i32) {}
+    fn cb_bgt_state(&mut self, is_fin: bool, id: i32) {}
+}
+#[allow(unused_variables)]
+impl paprika::PkSpiceSyncManager for SyncManager {
+    fn cb_get_vsrc(&mut self, time: f64, name: String, id: i32) -> f64 {
+        let v = (self.source)(time, &name);
+        self.names.push(name);
+        self.ids.push(id);
+        v
+    }
+    fn cb_get_isrc(&mut self, time: f64, name: String, id: i32) -> f64 {
+        (self.source)(time, &name)
+    }
+}
+
+/// manager recording the event node dictionary and value changes
+#[derive(Default)]
+struct EvtManager {
+    nodes: Vec<(i32, String, String)>,
+    evts: Vec<(i32, f64, String)>,
+}
+#[allow(unused_variables)]
+impl paprika::PkSpiceManager for EvtManager {
+    fn cb_send_char(&mut self, msg: String, id: i32) {}
+    fn cb_send_stat(&mut self, msg: String, id: i32) {}
+    fn cb_ctrldexit(&mut self, status: i32, is_immediate: bool, is_quit: bool, id: i32) {}
+    fn cb_send_init(&mut self, pkvecinfoall: PkVecinfoall, id: i32) {}
+    fn cb_send_data(&mut self, pkvecvaluesall: PkVecvaluesall, count: i32, id: i32) {}
+    fn cb_bgt_state(&mut self, is_fin: bool, id: i32) {}
+}
+#[allow(unused_variables)]
+impl paprika::PkSpiceEvtManager for EvtManager {
+    fn cb_send_init_evt_data(
+        &mut self,
+        node_index: i32,
+        max_index: i32,
+        name: String,
+        udn_name: String,
+        id: i32,
+    ) {
+        self.nodes.push((node_index, name, udn_name));
+    }
+    fn cb_send_evt_data(
+        &mut self,
+        node_index: i32,
+        step: f64,
+        dvalue: f64,
+        svalue: String,
+        mode: i32,
+        id: i32,
+    ) {
+        self.evts.push((node_index, step, svalue));
+    }
+}
+
+#[test]
+#[ignore = "needs ngspice built with XSPICE, set NGSPICE_LIBRARY_PATH to it"]
+fn test_evt_nodes() {
+    let mut spice = PkSpice::<EvtManager>::locate().unwrap();
+    let manager = Arc::new(EvtManager::default());
+
+    spice.init(Some(manager.clone())).unwrap(); // register
+    spice.init_evt().unwrap();
+
+    spice.command("source digital.cir");
+    spice.command("tran 10u 3m");
+    let mut nodes = spice.get_all_evt_nodes();
+    nodes.sort();
+    assert_eq!(nodes, vec!["din", "dout"]);
+    assert!(manager
+        .nodes
+        .iter()
+        .any(|(_, n, udn)| n == "dout" && udn == "d"));
+    assert!(!manager.evts.is_empty());
+
+    // the inverter output toggles with the input, every half period
+    let dout = spice.get_evt_node_info("dout").unwrap();
+    let levels: Vec<_> = dout
+        .evts
+        .iter()
+        .filter(|e| !e.dcop)
+        .map(|e| e.digital().unwrap().state)
+        .collect();
+    assert!(levels.len() >= 5);
+    assert!(levels.windows(2).all(|w| w[0] != w[1]));
+    assert!(spice.get_evt_node_info("nonexistent").is_none());
+    spice.command("quit");
+}
+
+#[test]
+fn test_digital_values() {
+    assert_eq!(
+        PkDigital::parse("1s"),
+        Some(PkDigital {
+            state: PkDigitalState::One,
+            strength: PkDigitalStrength::Strong
+        })
+    );
+    assert_eq!(
+        PkDigital::parse("Uz").unwrap().strength,
+        PkDigitalStrength::HiImpedance
+    );
+    assert_eq!(PkDigital::parse("0u").unwrap().state, PkDigitalState::Zero);
+    assert_eq!(PkDigital::parse("1.5"), None);
+    assert_eq!(PkDigital::parse("1sx"), None);
+}
+
+#[test]
+#[ignore = "needs ngspice, set NGSPICE_LIBRARY_PATH to it"]
+fn test_info() {
+    let spice = PkSpice::<Manager>::locate().unwrap();
+    let info = spice.info();
+    // version -s reports e.g. ngspice-39
+    assert!(info
+        .version
+        .as_deref()
+        .is_some_and(|v| v.chars().next().is_some_and(|c| c.is_ascii_digit())));
+    assert!(info.sync);
+    assert!(info.breakpoints);
+    assert_eq!(
+        PkSpiceError::SymbolNotFound(String::from("ngSpice_SetBkpt")).to_string(),
+        "sharedspice library does not export ngSpice_SetBkpt"
+    );
+}
+
+/// a shared library of the platform, which is not ngspice
+#[cfg(all(unix, not(target_os = "macos")))]
+const SYSTEM_LIBRARY: &str = "libc.so.6";
+#[cfg(target_os = "macos")]
+const SYSTEM_LIBRARY: &str = "/usr/lib/libSystem.B.dylib";
+#[cfg(windows)]
+const SYSTEM_LIBRARY: &str = "kernel32.dll";
+
+#[test]
+fn test_symbol_not_found() {
+    match PkSpice::<Manager>::new(OsStr::new(SYSTEM_LIBRARY)) {
+        Err(PkSpiceError::SymbolNotFound(name)) => assert_eq!(name, "ngSpice_Init"),
+        Err(e) => panic!("unexpected error: {}", e),
+        Ok(_) => panic!("{} linked as sharedspice", SYSTEM_LIBRARY),
+    }
+}
 
 #[test]
-fn test_dcop() {
-    let mut spice = PkSpice::<Manager>::new(OsStr::new("ngspice.dll")).unwrap();
+fn test_locate_sharedspice() {
+    let dir = std::env::temp_dir().join(format!("paprika_test_locate_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let lib = dir.join(SHAREDSPICE_NAMES[0]);
+    std::fs::write(&lib, b"").unwrap();
+
+    // a directory in the variable is searched before anything else
+    assert_eq!(sharedspice_candidates_in(Some(dir.as_os_str()))[0], lib);
+    // so is a path to the library itself
+    assert_eq!(sharedspice_candidates_in(Some(lib.as_os_str()))[0], lib);
+    // found, but not a library
+    assert!(matches!(
+        PkSpice::<Manager>::new(lib.as_os_str()),
+        Err(PkSpiceError::SharedspiceNotFound(_))
+    ));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_codemodel_errors() {
+    let _lock = FAKE.lock().unwrap_or_else(|e| e.into_inner());
+    let mut spice = fake_spice::<Manager>();
     let buf = Arc::new(RwLock::new(VecDeque::<String>::with_capacity(10)));
     let manager = Arc::new(Manager::new(buf.clone()));
 
-    spice.init(Some(manager)); // register
+    spice.init(Some(manager)).unwrap(); // register
+    spice.add_codemodel_path("tests/fixtures");
+    spice.add_codemodel_path("tests/fixtures");
+    assert_eq!(spice.codemodel_paths().len(), 1);
+    assert!(spice.available_codemodels().is_empty());
 
-    spice.command("source dcop.cir");
-    spice.command("op");
+    assert!(matches!(
+        spice.load_codemodel("nonexistent"),
+        Err(PkSpiceError::CodeModelNotFound(_))
+    ));
+    // found, but not a library: ngspice reports it in its output
+    let path = spice
+        .load_codemodel("tests/fixtures/tran_ascii.raw")
+        .unwrap();
+    assert_eq!(
+        path,
+        std::path::PathBuf::from("tests/fixtures/tran_ascii.raw")
+    );
+    assert!(buf
+        .read()
+        .unwrap()
+        .iter()
+        .any(|m| PkDiagnostic::parse(m).severity == PkSeverity::Error));
+    spice.command("quit");
+}
+
+#[test]
+#[ignore = "needs ngspice built with XSPICE, set NGSPICE_LIBRARY_PATH to it"]
+fn test_cm_input_path() {
+    let mut spice = PkSpice::<Manager>::locate().unwrap();
+    let buf = Arc::new(RwLock::new(VecDeque::<String>::with_capacity(10)));
+    let manager = Arc::new(Manager::new(buf));
+
+    spice.init(Some(manager)).unwrap(); // register
+    let dir = std::env::current_dir().unwrap();
+    spice.cm_input_path(Some(&dir)).unwrap();
+    assert_eq!(spice.cm_input_path(None).unwrap().as_deref(), dir.to_str());
+    spice.command("quit");
+}
+
+#[test]
+fn test_diagnostics() {
+    let d = PkDiagnostic::parse("stderr Warning: singular matrix:  check node \"out\"");
+    assert_eq!(d.severity, PkSeverity::Warning);
+    assert_eq!(d.node.as_deref(), Some("out"));
+    let d = PkDiagnostic::parse(
+        "stderr doAnalyses: TRAN:  Timestep too small; time = 1e-06: trouble with q1-instance Q1",
+    );
+    assert_eq!(d.severity, PkSeverity::Error);
+    assert_eq!(d.device.as_deref(), Some("q1"));
+    assert_eq!(
+        PkDiagnostic::parse("stdout Note: No compatibility mode selected!").severity,
+        PkSeverity::Info
+    );
+    assert_eq!(
+        PkDiagnostic::parse("stdout Doing analysis at TEMP = 27.000000").severity,
+        PkSeverity::Info
+    );
+
+    let mut diagnostics = PkDiagnostics::default();
+    diagnostics.push("stdout Circuit: * amplifier");
+    diagnostics.push("stderr Error on line 4 or its substitute:");
+    let generation = diagnostics.generation();
+    diagnostics.push("stderr   m1 d g s b nmos1 w=1u");
+    assert_ne!(diagnostics.generation(), generation);
+    let generation = diagnostics.generation();
+    diagnostics.push("stderr ");
+    assert_eq!(diagnostics.generation(), generation);
+    diagnostics.push("stderr unable to find definition of model nmos1");
+    let items = diagnostics.items();
+    assert_eq!(items.len(), 3);
+    assert_eq!(items[1].line, Some(4));
+    assert_eq!(items[1].device.as_deref(), Some("m1"));
+    assert_eq!(items[2].severity, PkSeverity::Error);
+    assert!(diagnostics.has_errors());
+    assert_eq!(diagnostics.at_least(PkSeverity::Warning).count(), 2);
+    diagnostics.clear();
+    assert!(!diagnostics.has_errors());
+    let generation = diagnostics.generation();
+    diagnostics.clear();
+    assert_eq!(diagnostics.generation(), generation);
+}
+
+#[test]
+fn test_progress() {
+    assert_eq!(
+        PkProgress::parse("tran: 45.3%"),
+        Some(PkProgress {
+            analysis: String::from("tran"),
+            percent: 45.3
+        })
+    );
+    assert_eq!(PkProgress::parse("AC:  100.0%").unwrap().analysis, "ac");
+    let ready = PkProgress::parse("--ready--").unwrap();
+    assert!(ready.is_done());
+    assert_eq!(ready.fraction(), 1.0);
+    assert_eq!(PkProgress::parse("Reference value :  1.00000e-03"), None);
+    assert_eq!(PkProgress::parse("tran: %"), None);
+}
+
+#[test]
+fn test_raw_ascii_multiple_plots() {
+    let plots = read_raw_file(std::path::Path::new("tests/fixtures/tran_ascii.raw")).unwrap();
+    assert_eq!(plots.len(), 2);
+    let tran = &plots[0];
+    assert_eq!(tran.plotname, "Transient Analysis");
+    assert!(!tran.is_complex);
+    assert_eq!(tran.len(), 4);
+    assert_eq!(tran.scale().unwrap().name, "time");
+    let vout = tran.vec("v(out)").unwrap();
+    assert_eq!(vout.flag, VF_REAL);
+    assert_eq!(vout.realdata.as_ref().unwrap()[1], 6.321205588285577e-1);
+    assert!(vout.compdata.is_none());
+    let ib = tran.vec("v1#branch").unwrap();
+    assert_eq!(ib.realdata.as_ref().unwrap()[3], -4.978706836786394e-5);
+
+    let op = &plots[1];
+    assert_eq!(op.plotname, "Operating Point");
+    assert_eq!(op.len(), 1);
+    assert_eq!(op.vec("v(out)").unwrap().realdata, Some(vec![1.0]));
+}
+
+#[test]
+fn test_raw_ascii_complex() {
+    let plots = read_raw_file(std::path::Path::new("tests/fixtures/ac_ascii.raw")).unwrap();
+    assert_eq!(plots.len(), 1);
+    let ac = &plots[0];
+    assert!(ac.is_complex);
+    assert_eq!(ac.len(), 3);
+    let freq = ac.scale().unwrap().compdata.as_ref().unwrap();
+    assert_eq!(freq[2], num::Complex::new(1e4, 0.0));
+    let vout = ac.vec("v(out)").unwrap();
+    assert_eq!(vout.flag, VF_COMPLEX);
+    assert_eq!(
+        vout.compdata.as_ref().unwrap()[1],
+        num::Complex::new(9.619397662556434e-1, -1.913417161825449e-1)
+    );
+}
+
+#[test]
+fn test_raw_binary() {
+    let plots = read_raw_file(std::path::Path::new("tests/fixtures/tran_binary.raw")).unwrap();
+    assert_eq!(plots.len(), 1);
+    assert_eq!(plots[0].len(), 3);
+    assert_eq!(
+        plots[0].vec("v(out)").unwrap().realdata,
+        Some(vec![0.0, 0.6321205588285577, 0.8646647167633873])
+    );
+}
+
+#[test]
+fn test_raw_roundtrip() {
+    let mut plots = read_raw_file(std::path::Path::new("tests/fixtures/tran_ascii.raw")).unwrap();
+    plots.extend(read_raw_file(std::path::Path::new("tests/fixtures/ac_ascii.raw")).unwrap());
+    for binary in [false, true] {
+        let read = read_raw(&write_raw(&plots, binary)).unwrap();
+        assert_eq!(read.len(), plots.len());
+        for (a, b) in plots.iter().zip(&read) {
+            assert_eq!(a.plotname, b.plotname);
+            assert_eq!(a.is_complex, b.is_complex);
+            for (va, vb) in a.vecs.iter().zip(&b.vecs) {
+                assert_eq!(va.name, vb.name);
+                assert_eq!(va.stype, vb.stype);
+                assert_eq!(va.length, vb.length);
+                assert_eq!(va.realdata, vb.realdata);
+                assert_eq!(va.compdata, vb.compdata);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_raw_malformed() {
+    assert!(matches!(
+        read_raw(b"Title: x\nNo. Points: 1\nValues:\n"),
+        Err(PkRawError::Parse(_))
+    ));
+}
+
+/// serializes the tests using the stand-in library, which has global state like ngspice
+static FAKE: Mutex<()> = Mutex::new(());
+
+/// returns the path of the stand-in sharedspice library, which cargo builds next to the tests
+fn fake_path() -> std::path::PathBuf {
+    let dir = std::env::current_exe().unwrap();
+    let name = format!(
+        "{}fakespice{}",
+        std::env::consts::DLL_PREFIX,
+        std::env::consts::DLL_SUFFIX
+    );
+    dir.with_file_name(name)
+}
+
+/// links to the stand-in sharedspice library
+fn fake_spice<T: PkSpiceManager>() -> PkSpice<T> {
+    PkSpice::new(fake_path().as_os_str()).unwrap()
+}
+
+#[test]
+fn test_fake_info() {
+    let _lock = FAKE.lock().unwrap_or_else(|e| e.into_inner());
+    let mut spice = fake_spice::<EvtManager>();
+    assert_eq!(
+        *spice.info(),
+        PkSpiceInfo {
+            version: Some(String::from("fake")),
+            sync: true,
+            breakpoints: true,
+            xspice: false,
+        }
+    );
+    // built without XSPICE
+    assert!(matches!(
+        spice.init_evt(),
+        Err(PkSpiceError::SymbolNotFound(_))
+    ));
+    assert!(matches!(
+        spice.cm_input_path(None),
+        Err(PkSpiceError::SymbolNotFound(_))
+    ));
+    assert!(spice.get_all_evt_nodes().is_empty());
+    assert!(spice.get_evt_node_info("out").is_none());
+}
+
+#[test]
+fn test_fake_op() {
+    let _lock = FAKE.lock().unwrap_or_else(|e| e.into_inner());
+    let mut spice = fake_spice::<Manager>();
+    let buf = Arc::new(RwLock::new(VecDeque::<String>::with_capacity(10)));
+    let manager = Arc::new(Manager::new(buf.clone()));
+
+    spice.init(Some(manager.clone())).unwrap(); // register
+    assert!(matches!(
+        spice.try_command("op"),
+        Err(PkSpiceError::CommandFailed(_))
+    ));
+    let mut diagnostics = PkDiagnostics::default();
+    for msg in buf.read().unwrap().iter() {
+        diagnostics.push(msg);
+    }
+    assert!(diagnostics.has_errors());
+    spice.try_command("source dcop.cir").unwrap();
+    spice.try_command("op").unwrap();
+
+    let info = &manager.vec_pkvecinfoall[0];
+    assert_eq!(info.name, "op1");
+    assert_eq!(info.count, 3);
+    assert_eq!(info.vecs[1].name, "v(out)");
+    assert!(info.vecs[1].is_real);
+    let values = &manager.vec_pkvecvalsall[0];
+    assert_eq!(values.count, 3);
+    assert!(values.vecsa[0].is_scale);
+    assert_eq!(values.vecsa[1].name, "v(out)");
+    assert_eq!(values.vecsa[1].creal, 0.5);
+
+    assert_eq!(spice.get_cur_plot(), "op1");
+    assert_eq!(spice.get_all_plots(), ["op1", "const"]);
+    assert_eq!(spice.get_all_vecs("op1"), ["v(in)", "v(out)", "v1#branch"]);
+    let vout = spice.get_vec_info("op1.v(out)");
+    assert_eq!(vout.realdata, Some(vec![0.5]));
+    assert_eq!(vout.length, 1);
+
+    spice.command("history");
+    let history: Vec<String> = buf.read().unwrap().iter().cloned().collect();
+    assert!(history.contains(&String::from("stdout 2\tsource dcop.cir")));
     spice.command("quit");
+    assert!(manager.quit_flag);
+}
+
+#[test]
+fn test_fake_ac() {
+    let _lock = FAKE.lock().unwrap_or_else(|e| e.into_inner());
+    let mut spice = fake_spice::<Manager>();
+    let buf = Arc::new(RwLock::new(VecDeque::<String>::with_capacity(10)));
+    let manager = Arc::new(Manager::new(buf));
+
+    spice.init(Some(manager.clone())).unwrap(); // register
+    spice.try_command("source ac.cir").unwrap();
+    spice.try_command("ac dec 10 100 10k").unwrap();
+
+    assert_eq!(manager.vec_pkvecvalsall.len(), 21);
+    assert!(manager.vec_pkvecvalsall[0].vecsa[2].is_complex);
+    let vout = spice.get_vec_info("v(out)");
+    assert_eq!(vout.realdata, None);
+    // the corner of the low pass, at 1k
+    let at_corner = vout.compdata.unwrap()[10];
+    assert!((at_corner.re - 0.5).abs() < 1e-9);
+    assert!((at_corner.im + 0.5).abs() < 1e-9);
+}
+
+#[test]
+fn test_fake_breakpoint() {
+    let _lock = FAKE.lock().unwrap_or_else(|e| e.into_inner());
+    let mut spice = fake_spice::<Manager>();
+    let buf = Arc::new(RwLock::new(VecDeque::<String>::with_capacity(10)));
+    let manager = Arc::new(Manager::new(buf));
+
+    spice.init(Some(manager.clone())).unwrap(); // register
+    spice.command("source tran.cir");
+    spice.set_bkpt(5e-3).unwrap();
+    spice.command("stop when time >= 5m");
+    spice.bg_command("tran 10u 10m").unwrap();
+    spice.bg_wait(std::time::Duration::from_millis(1));
+    let time = spice.get_vec_info("time").realdata.unwrap();
+    assert!((*time.last().unwrap() - 5e-3).abs() < 1e-12);
+
+    spice.command("delete all");
+    spice.bg_command("resume").unwrap();
+    spice.bg_wait(std::time::Duration::from_millis(1));
+    let time = spice.get_vec_info("time").realdata.unwrap();
+    assert_eq!(time.len(), 1001);
+    assert!((*time.last().unwrap() - 10e-3).abs() < 1e-12);
+    assert_eq!(manager.vec_pkvecvalsall.len(), 1001);
+    // resumed in the same plot
+    assert_eq!(spice.get_all_plots(), ["tran1", "const"]);
+}
+
+#[test]
+fn test_fake_external_source() {
+    let _lock = FAKE.lock().unwrap_or_else(|e| e.into_inner());
+    let mut spice = fake_spice::<SyncManager>();
+    let manager = Arc::new(SyncManager {
+        source: Box::new(|_, name| if name == "vext" { 2.0 } else { 0.0 }),
+        names: vec![],
+        ids: vec![],
+    });
+
+    spice.init(Some(manager.clone())).unwrap(); // register
+    spice.init_sync().unwrap();
+
+    spice.command("source external.cir");
+    spice.command("op");
+    let vout = spice.get_vec_info("v(out)");
+    assert_eq!(vout.realdata.unwrap()[0], 1.0);
+    assert_eq!(manager.names, ["vext"]);
+    assert_eq!(manager.ids, [0]);
+
+    // an isolated instance passes its id to the sync callbacks as well
+    let mut spice = PkSpice::<SyncManager>::new_isolated(fake_path().as_os_str()).unwrap();
+    let manager = Arc::new(SyncManager {
+        source: Box::new(|_, _| 2.0),
+        names: vec![],
+        ids: vec![],
+    });
+    spice.init(Some(manager.clone())).unwrap(); // register
+    spice.init_sync().unwrap();
+    spice.command("source external.cir");
+    spice.command("op");
+    assert_eq!(manager.ids, [spice.id()]);
+}
+
+/// manager recording the progress reports
+#[derive(Default)]
+struct ProgressManager {
+    progress: Vec<PkProgress>,
+}
+#[allow(unused_variables)]
+impl paprika::PkSpiceManager for ProgressManager {
+    fn cb_send_char(&mut self, msg: String, id: i32) {}
+    fn cb_send_stat(&mut self, msg: String, id: i32) {}
+    fn cb_progress(&mut self, progress: PkProgress, id: i32) {
+        self.progress.push(progress);
+    }
+    fn cb_ctrldexit(&mut self, status: i32, is_immediate: bool, is_quit: bool, id: i32) {}
+    fn cb_send_init(&mut self, pkvecinfoall: PkVecinfoall, id: i32) {}
+    fn cb_send_data(&mut self, pkvecvaluesall: PkVecvaluesall, count: i32, id: i32) {}
+    fn cb_bgt_state(&mut self, is_fin: bool, id: i32) {}
+}
+
+#[test]
+fn test_fake_progress() {
+    let _lock = FAKE.lock().unwrap_or_else(|e| e.into_inner());
+    let mut spice = fake_spice::<ProgressManager>();
+    let manager = Arc::new(ProgressManager::default());
+
+    spice.init(Some(manager.clone())).unwrap(); // register
+    spice.command("source tran.cir");
+    spice.command("tran 1m 10m");
+    let (done, running) = manager.progress.split_last().unwrap();
+    assert!(done.is_done());
+    assert_eq!(running.len(), 11);
+    assert!(running.iter().all(|p| p.analysis == "tran"));
+    assert!(running.windows(2).all(|w| w[0].percent < w[1].percent));
+    assert_eq!(running.last().unwrap().percent, 100.0);
+}
+
+/// manager recording the ids passed to its callbacks, and the output
+#[derive(Default)]
+struct IdManager {
+    ids: Vec<i32>,
+    output: Vec<String>,
+}
+#[allow(unused_variables)]
+impl paprika::PkSpiceManager for IdManager {
+    fn cb_send_char(&mut self, msg: String, id: i32) {
+        self.ids.push(id);
+        self.output.push(msg);
+    }
+    fn cb_send_stat(&mut self, msg: String, id: i32) {
+        self.ids.push(id);
+    }
+    fn cb_ctrldexit(&mut self, status: i32, is_immediate: bool, is_quit: bool, id: i32) {
+        self.ids.push(id);
+    }
+    fn cb_send_init(&mut self, pkvecinfoall: PkVecinfoall, id: i32) {
+        self.ids.push(id);
+    }
+    fn cb_send_data(&mut self, pkvecvaluesall: PkVecvaluesall, count: i32, id: i32) {
+        self.ids.push(id);
+    }
+    fn cb_bgt_state(&mut self, is_fin: bool, id: i32) {
+        self.ids.push(id);
+    }
+}
+
+#[test]
+fn test_fake_isolated() {
+    // no FAKE lock, every instance links to a copy of the library
+    const INSTANCES: usize = 4;
+    let barrier = Arc::new(std::sync::Barrier::new(INSTANCES));
+    let threads: Vec<_> = (1..=INSTANCES)
+        .map(|n| {
+            let barrier = barrier.clone();
+            std::thread::spawn(move || {
+                let mut spice =
+                    PkSpice::<IdManager>::new_isolated(fake_path().as_os_str()).unwrap();
+                let manager = Arc::new(IdManager::default());
+                spice.init(Some(manager.clone())).unwrap(); // register
+                barrier.wait();
+
+                let tran = format!("tran {}m 10m", n);
+                spice.command("source tran.cir");
+                spice.bg_command(&tran).unwrap();
+                spice.bg_wait(std::time::Duration::from_millis(1));
+                spice.command("history");
+                // the instance saw only its own commands
+                assert_eq!(
+                    manager.output,
+                    [
+                        String::from("stdout 1\tsource tran.cir"),
+                        format!("stdout 2\tbg_{}", tran),
+                        String::from("stdout 3\thistory"),
+                    ]
+                );
+                assert_eq!(spice.get_all_plots(), ["tran1", "const"]);
+                let time = spice.get_vec_info("time").realdata.unwrap();
+                assert_eq!(time.len(), 10 / n + 1);
+                // callbacks are routed by id
+                assert_ne!(spice.id(), 0);
+                assert!(manager.ids.iter().all(|id| *id == spice.id()));
+                spice.id()
+            })
+        })
+        .collect();
+    let mut ids: Vec<i32> = threads.into_iter().map(|t| t.join().unwrap()).collect();
+    ids.sort();
+    ids.dedup();
+    assert_eq!(ids.len(), INSTANCES);
+    // the copies are removed with their instances
+    let name = fake_path()
+        .file_name()
+        .unwrap()
+        .to_string_lossy()
+        .into_owned();
+    for id in ids {
+        let copy =
+            std::env::temp_dir().join(format!("paprika-{}-{}-{}", std::process::id(), id, name));
+        assert!(!copy.exists());
+    }
+}
+
+#[test]
+fn test_fake_shared_library() {
+    let _lock = FAKE.lock().unwrap_or_else(|e| e.into_inner());
+    // instances linked to the same library file share its state
+    let mut a = fake_spice::<IdManager>();
+    let b = fake_spice::<IdManager>();
+    a.init(None).unwrap();
+    a.command("source tran.cir");
+    a.command("op");
+    assert_eq!(b.get_cur_plot(), "op1");
+    assert_eq!(a.id(), 0);
+    assert_eq!(b.id(), 0);
 }
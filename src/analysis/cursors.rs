@@ -0,0 +1,145 @@
+//! cursors
+//! A/B measurement cursors which snap to a trace
+
+use crate::analysis::trace::Trace;
+use crate::transforms::{Point, VCTransform, VSPoint};
+use iced::widget::canvas::{self, stroke, Frame, LineCap, LineDash, Path, Stroke};
+use iced::Color;
+
+/// identifies one of the two measurement cursors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CursorId {
+    A,
+    B,
+}
+
+impl CursorId {
+    fn label(&self) -> &'static str {
+        match self {
+            CursorId::A => "A",
+            CursorId::B => "B",
+        }
+    }
+    fn color(&self) -> Color {
+        match self {
+            CursorId::A => Color::from_rgb(0.4, 1.0, 0.4),
+            CursorId::B => Color::from_rgb(1.0, 0.5, 0.3),
+        }
+    }
+}
+
+/// pair of vertical measurement cursors
+#[derive(Debug, Clone, Default)]
+pub struct Cursors {
+    /// x position of cursor A, if placed
+    a: Option<f32>,
+    /// x position of cursor B, if placed
+    b: Option<f32>,
+    /// name of the trace the cursors snap to
+    pub trace: Option<String>,
+}
+
+impl Cursors {
+    /// returns the x position of cursor id, if placed
+    pub fn x(&self, id: CursorId) -> Option<f32> {
+        match id {
+            CursorId::A => self.a,
+            CursorId::B => self.b,
+        }
+    }
+    /// place cursor id at x, snapping to the nearest data point of trace if given
+    pub fn place(&mut self, id: CursorId, x: f32, trace: Option<&Trace>) {
+        let x = trace
+            .and_then(|t| t.nearest_point(x))
+            .map(|p| p.x)
+            .unwrap_or(x);
+        match id {
+            CursorId::A => self.a = Some(x),
+            CursorId::B => self.b = Some(x),
+        }
+    }
+    /// remove both cursors
+    pub fn clear(&mut self) {
+        self.a = None;
+        self.b = None;
+    }
+    /// returns the placed cursor whose line is within tolerance of canvas x position csp_x, if any
+    pub fn cursor_near(&self, vct: VCTransform, csp_x: f32, tolerance: f32) -> Option<CursorId> {
+        [CursorId::A, CursorId::B].into_iter().find(|id| {
+            self.x(*id).is_some_and(|x| {
+                (vct.transform_point(VSPoint::new(x, 0.0)).x - csp_x).abs() <= tolerance
+            })
+        })
+    }
+    /// returns the cursor readout, with y values taken from trace
+    pub fn readout(&self, trace: Option<&Trace>) -> CursorReadout {
+        let pt = |x: Option<f32>| -> Option<VSPoint> {
+            let x = x?;
+            let y = trace.and_then(|t| t.value_at(x)).unwrap_or(f32::NAN);
+            Some(VSPoint::new(x, y))
+        };
+        CursorReadout {
+            a: pt(self.a),
+            b: pt(self.b),
+        }
+    }
+    /// draw the cursor lines across the full height of the frame, with a marker where they cross trace
+    pub fn draw(&self, vct: VCTransform, trace: Option<&Trace>, frame: &mut Frame) {
+        for id in [CursorId::A, CursorId::B] {
+            let Some(x) = self.x(id) else {
+                continue;
+            };
+            let csp_x = vct.transform_point(VSPoint::new(x, 0.0)).x;
+            let stroke = Stroke {
+                width: 1.0,
+                style: stroke::Style::Solid(id.color()),
+                line_cap: LineCap::Butt,
+                line_dash: LineDash {
+                    segments: &[6.0, 3.0],
+                    offset: 0,
+                },
+                ..Stroke::default()
+            };
+            let line = Path::line(
+                iced::Point::new(csp_x, 0.0),
+                iced::Point::new(csp_x, frame.height()),
+            );
+            frame.stroke(&line, stroke);
+            frame.fill_text(canvas::Text {
+                content: id.label().to_string(),
+                position: iced::Point::new(csp_x + 3.0, 2.0),
+                color: id.color(),
+                size: 14.0,
+                ..Default::default()
+            });
+            if let Some(y) = trace.and_then(|t| t.value_at(x)) {
+                let csp = vct.transform_point(VSPoint::new(x, y));
+                frame.fill(&Path::circle(Point::from(csp).into(), 3.0), id.color());
+            }
+        }
+    }
+}
+
+/// cursor positions and the differences between them
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CursorReadout {
+    /// position of cursor A on the trace
+    pub a: Option<VSPoint>,
+    /// position of cursor B on the trace
+    pub b: Option<VSPoint>,
+}
+
+impl CursorReadout {
+    /// returns B - A
+    pub fn delta(&self) -> Option<VSPoint> {
+        Some(VSPoint::new(self.b?.x - self.a?.x, self.b?.y - self.a?.y))
+    }
+    /// returns 1/dx, e.g. the frequency if the cursors are a period apart
+    pub fn inv_dx(&self) -> Option<f32> {
+        self.delta().map(|d| 1.0 / d.x)
+    }
+    /// returns dy/dx
+    pub fn slope(&self) -> Option<f32> {
+        self.delta().map(|d| d.y / d.x)
+    }
+}
@@ -0,0 +1,564 @@
+//! expr
+//! Expressions over named traces, e.g. `v(out)/v(in)` or `deriv(v(c1))`, to derive new waveforms without re-simulating
+
+use crate::analysis::spectrum;
+use crate::analysis::trace::{interpolate, Trace};
+use crate::transforms::VSPoint;
+
+/// error parsing or evaluating an expression
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprError {
+    /// the expression is malformed
+    Parse(String),
+    /// the expression references a trace which does not exist
+    UnknownTrace(String),
+    /// the expression calls a function which does not exist, or with the wrong number of arguments
+    UnknownFunction(String),
+    /// the expression does not reference any trace
+    NoTrace,
+}
+
+impl std::fmt::Display for ExprError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExprError::Parse(s) => write!(f, "parse error: {}", s),
+            ExprError::UnknownTrace(s) => write!(f, "unknown trace: {}", s),
+            ExprError::UnknownFunction(s) => write!(f, "unknown function: {}", s),
+            ExprError::NoTrace => write!(f, "expression does not reference a trace"),
+        }
+    }
+}
+
+/// evaluate src over traces, returning the derived waveform.
+/// Traces on different scale vectors are resampled onto the scale of the left operand.
+pub fn evaluate(src: &str, traces: &[&Trace]) -> Result<Vec<VSPoint>, ExprError> {
+    let tokens = tokenize(src)?;
+    let mut parser = Parser { tokens, pos: 0 };
+    let ast = parser.expr()?;
+    if parser.pos != parser.tokens.len() {
+        return Err(ExprError::Parse(format!(
+            "unexpected {:?}",
+            parser.tokens[parser.pos]
+        )));
+    }
+    match ast.eval(traces)? {
+        Value::Series(pts) => Ok(pts),
+        Value::Scalar(_) => Err(ExprError::NoTrace),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Num(f32),
+    /// identifier - trace or function name
+    Ident(String),
+    Op(char),
+    LParen,
+    RParen,
+    Comma,
+}
+
+/// returns true for characters which may appear in a vector name, e.g. `v1#branch`, `@m1[id]`
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || "_#.:@[]$".contains(c)
+}
+
+/// parse a number with optional spice scale suffix and unit, e.g. `10u`, `1.5meg`, `10ms`.
+/// As in spice, letters following the scale suffix are ignored.
+pub(crate) fn parse_number(s: &str) -> Option<f32> {
+    let lower = s.to_lowercase();
+    // the longest numeric prefix, e.g. `1e-3` of `1e-3s`
+    let (v, rest) = (1..=lower.len()).rev().find_map(|i| {
+        let num = lower.get(..i)?;
+        if !num
+            .chars()
+            .all(|c| c.is_ascii_digit() || "+-.e".contains(c))
+        {
+            return None;
+        }
+        Some((num.parse::<f32>().ok()?, &lower[i..]))
+    })?;
+    if !rest.chars().all(char::is_alphabetic) {
+        return None;
+    }
+    let scale = [
+        ("meg", 1e6),
+        ("mil", 25.4e-6),
+        ("f", 1e-15),
+        ("p", 1e-12),
+        ("n", 1e-9),
+        ("u", 1e-6),
+        ("m", 1e-3),
+        ("k", 1e3),
+        ("g", 1e9),
+        ("t", 1e12),
+    ]
+    .iter()
+    .find(|(sfx, _)| rest.starts_with(sfx))
+    .map_or(1.0, |(_, scale)| *scale);
+    Some(v * scale)
+}
+
+fn tokenize(src: &str) -> Result<Vec<Token>, ExprError> {
+    let chars: Vec<char> = src.chars().collect();
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            ' ' | '\t' => i += 1,
+            '+' | '-' | '*' | '/' | '^' => {
+                tokens.push(Token::Op(c));
+                i += 1;
+            }
+            '(' => {
+                tokens.push(Token::LParen);
+                i += 1;
+            }
+            ')' => {
+                tokens.push(Token::RParen);
+                i += 1;
+            }
+            ',' => {
+                tokens.push(Token::Comma);
+                i += 1;
+            }
+            c if c.is_ascii_digit() || c == '.' => {
+                let start = i;
+                while i < chars.len()
+                    && (chars[i].is_ascii_alphanumeric()
+                        || chars[i] == '.'
+                        // exponent sign, e.g. 1e-3
+                        || ((chars[i] == '-' || chars[i] == '+')
+                            && matches!(chars[i - 1], 'e' | 'E')))
+                {
+                    i += 1;
+                }
+                let s: String = chars[start..i].iter().collect();
+                let v = parse_number(&s)
+                    .ok_or_else(|| ExprError::Parse(format!("invalid number {}", s)))?;
+                tokens.push(Token::Num(v));
+            }
+            c if is_ident_char(c) => {
+                let start = i;
+                while i < chars.len() && is_ident_char(chars[i]) {
+                    i += 1;
+                }
+                tokens.push(Token::Ident(chars[start..i].iter().collect()));
+            }
+            _ => return Err(ExprError::Parse(format!("unexpected character {}", c))),
+        }
+    }
+    Ok(tokens)
+}
+
+/// expression syntax tree
+#[derive(Debug, Clone)]
+enum Node {
+    Num(f32),
+    Trace(String),
+    Neg(Box<Node>),
+    Binary(char, Box<Node>, Box<Node>),
+    Call(String, Vec<Node>),
+}
+
+/// recursive descent parser
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+    fn next(&mut self) -> Option<Token> {
+        let t = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        t
+    }
+    fn expect(&mut self, t: Token) -> Result<(), ExprError> {
+        match self.next() {
+            Some(n) if n == t => Ok(()),
+            n => Err(ExprError::Parse(format!("expected {:?}, found {:?}", t, n))),
+        }
+    }
+    /// expr := term (('+' | '-') term)*
+    fn expr(&mut self) -> Result<Node, ExprError> {
+        let mut lhs = self.term()?;
+        while let Some(Token::Op(op @ ('+' | '-'))) = self.peek().cloned() {
+            self.pos += 1;
+            lhs = Node::Binary(op, Box::new(lhs), Box::new(self.term()?));
+        }
+        Ok(lhs)
+    }
+    /// term := unary (('*' | '/') unary)*
+    fn term(&mut self) -> Result<Node, ExprError> {
+        let mut lhs = self.unary()?;
+        while let Some(Token::Op(op @ ('*' | '/'))) = self.peek().cloned() {
+            self.pos += 1;
+            lhs = Node::Binary(op, Box::new(lhs), Box::new(self.unary()?));
+        }
+        Ok(lhs)
+    }
+    /// unary := '-' unary | power
+    fn unary(&mut self) -> Result<Node, ExprError> {
+        if let Some(Token::Op('-')) = self.peek() {
+            self.pos += 1;
+            return Ok(Node::Neg(Box::new(self.unary()?)));
+        }
+        self.power()
+    }
+    /// power := atom ('^' unary)?
+    fn power(&mut self) -> Result<Node, ExprError> {
+        let base = self.atom()?;
+        if let Some(Token::Op('^')) = self.peek() {
+            self.pos += 1;
+            return Ok(Node::Binary('^', Box::new(base), Box::new(self.unary()?)));
+        }
+        Ok(base)
+    }
+    /// node or device name within v() or i()
+    fn node_name(&mut self) -> Result<String, ExprError> {
+        match self.next() {
+            Some(Token::Ident(name)) => Ok(name),
+            Some(Token::Num(v)) => Ok(v.to_string()),
+            t => Err(ExprError::Parse(format!("unexpected {:?}", t))),
+        }
+    }
+    /// atom := number | '(' expr ')' | function '(' args ')' | trace
+    fn atom(&mut self) -> Result<Node, ExprError> {
+        match self.next() {
+            Some(Token::Num(v)) => Ok(Node::Num(v)),
+            Some(Token::LParen) => {
+                let e = self.expr()?;
+                self.expect(Token::RParen)?;
+                Ok(e)
+            }
+            Some(Token::Ident(name)) => {
+                if self.peek() != Some(&Token::LParen) {
+                    return Ok(Node::Trace(name));
+                }
+                let lname = name.to_lowercase();
+                if lname == "v" || lname == "i" {
+                    // spice style vector reference, e.g. v(out), i(v1)
+                    self.pos += 1;
+                    let inner = self.node_name()?;
+                    let node = Node::Trace(format!("{}({})", lname, inner));
+                    if lname == "v" && self.peek() == Some(&Token::Comma) {
+                        // differential voltage, e.g. v(a,b)
+                        self.pos += 1;
+                        let other = Node::Trace(format!("v({})", self.node_name()?));
+                        self.expect(Token::RParen)?;
+                        return Ok(Node::Binary('-', Box::new(node), Box::new(other)));
+                    }
+                    self.expect(Token::RParen)?;
+                    return Ok(node);
+                }
+                self.pos += 1;
+                let mut args = vec![];
+                if self.peek() != Some(&Token::RParen) {
+                    args.push(self.expr()?);
+                    while self.peek() == Some(&Token::Comma) {
+                        self.pos += 1;
+                        args.push(self.expr()?);
+                    }
+                }
+                self.expect(Token::RParen)?;
+                Ok(Node::Call(lname, args))
+            }
+            t => Err(ExprError::Parse(format!("unexpected {:?}", t))),
+        }
+    }
+}
+
+/// intermediate value during evaluation
+#[derive(Debug, Clone)]
+enum Value {
+    Scalar(f32),
+    Series(Vec<VSPoint>),
+}
+
+impl Value {
+    /// apply f to every value
+    fn map(self, f: impl Fn(f32) -> f32) -> Value {
+        match self {
+            Value::Scalar(v) => Value::Scalar(f(v)),
+            Value::Series(pts) => {
+                Value::Series(pts.into_iter().map(|p| VSPoint::new(p.x, f(p.y))).collect())
+            }
+        }
+    }
+    /// combine two values with f. Series on different scales are resampled onto the scale of self.
+    fn zip(self, other: Value, f: impl Fn(f32, f32) -> f32) -> Value {
+        match (self, other) {
+            (Value::Scalar(a), Value::Scalar(b)) => Value::Scalar(f(a, b)),
+            (Value::Series(a), Value::Scalar(b)) => Value::Series(
+                a.into_iter()
+                    .map(|p| VSPoint::new(p.x, f(p.y, b)))
+                    .collect(),
+            ),
+            (Value::Scalar(a), Value::Series(b)) => Value::Series(
+                b.into_iter()
+                    .map(|p| VSPoint::new(p.x, f(a, p.y)))
+                    .collect(),
+            ),
+            (Value::Series(a), Value::Series(b)) => {
+                let same_scale = a.len() == b.len() && a.iter().zip(&b).all(|(p, q)| p.x == q.x);
+                let pts = if same_scale {
+                    a.iter()
+                        .zip(&b)
+                        .map(|(p, q)| VSPoint::new(p.x, f(p.y, q.y)))
+                        .collect()
+                } else {
+                    a.iter()
+                        .filter_map(|p| Some(VSPoint::new(p.x, f(p.y, interpolate(&b, p.x)?))))
+                        .collect()
+                };
+                Value::Series(pts)
+            }
+        }
+    }
+}
+
+/// derivative with respect to x, by central differences over non-uniform steps.
+/// Of points repeating an x, e.g. at ngspice's breakpoints, only the last is kept
+fn deriv(pts: &[VSPoint]) -> Vec<VSPoint> {
+    let pts: Vec<VSPoint> = pts
+        .iter()
+        .enumerate()
+        .filter(|(i, p)| pts.get(i + 1).is_none_or(|q| q.x != p.x))
+        .map(|(_, p)| *p)
+        .collect();
+    let n = pts.len();
+    if n < 2 {
+        return vec![];
+    }
+    (0..n)
+        .map(|i| {
+            let (p0, p1) = (pts[i.saturating_sub(1)], pts[(i + 1).min(n - 1)]);
+            VSPoint::new(pts[i].x, (p1.y - p0.y) / (p1.x - p0.x))
+        })
+        .collect()
+}
+
+/// cumulative integral with respect to x by the trapezoid rule
+fn integ(pts: &[VSPoint]) -> Vec<VSPoint> {
+    let mut sum = 0.0f64;
+    let mut ret = Vec::with_capacity(pts.len());
+    for (i, p) in pts.iter().enumerate() {
+        if i > 0 {
+            let p0 = pts[i - 1];
+            sum += (p.x - p0.x) as f64 * (p.y + p0.y) as f64 / 2.0;
+        }
+        ret.push(VSPoint::new(p.x, sum as f32));
+    }
+    ret
+}
+
+/// moving average over an x span of width, centered on each point
+fn movavg(pts: &[VSPoint], width: f32) -> Vec<VSPoint> {
+    let cumulative = integ(pts);
+    let (Some(first), Some(last)) = (pts.first(), pts.last()) else {
+        return vec![];
+    };
+    pts.iter()
+        .map(|p| {
+            let x0 = (p.x - width / 2.0).max(first.x);
+            let x1 = (p.x + width / 2.0).min(last.x);
+            let y = if x1 > x0 {
+                let i0 = interpolate(&cumulative, x0).unwrap_or_default();
+                let i1 = interpolate(&cumulative, x1).unwrap_or_default();
+                (i1 - i0) / (x1 - x0)
+            } else {
+                p.y
+            };
+            VSPoint::new(p.x, y)
+        })
+        .collect()
+}
+
+/// look up a trace by name. `v(x)` also matches a trace named `x`, and `i(x)` matches `x#branch`, as ngspice names them.
+fn find_trace(name: &str, traces: &[&Trace]) -> Result<Vec<VSPoint>, ExprError> {
+    let lname = name.to_lowercase();
+    let mut candidates = vec![lname.clone()];
+    if let Some(inner) = lname.strip_prefix("v(").and_then(|s| s.strip_suffix(')')) {
+        candidates.push(inner.to_string());
+    }
+    if let Some(inner) = lname.strip_prefix("i(").and_then(|s| s.strip_suffix(')')) {
+        candidates.push(format!("{}#branch", inner));
+    }
+    candidates
+        .iter()
+        .find_map(|c| traces.iter().find(|t| t.name.to_lowercase() == *c))
+        .map(|t| t.points.clone())
+        .ok_or_else(|| ExprError::UnknownTrace(name.to_string()))
+}
+
+impl Node {
+    fn eval(&self, traces: &[&Trace]) -> Result<Value, ExprError> {
+        match self {
+            Node::Num(v) => Ok(Value::Scalar(*v)),
+            Node::Trace(name) => Ok(Value::Series(find_trace(name, traces)?)),
+            Node::Neg(n) => Ok(n.eval(traces)?.map(|v| -v)),
+            Node::Binary(op, lhs, rhs) => {
+                let (l, r) = (lhs.eval(traces)?, rhs.eval(traces)?);
+                Ok(match op {
+                    '+' => l.zip(r, |a, b| a + b),
+                    '-' => l.zip(r, |a, b| a - b),
+                    '*' => l.zip(r, |a, b| a * b),
+                    '/' => l.zip(r, |a, b| a / b),
+                    _ => l.zip(r, f32::powf),
+                })
+            }
+            Node::Call(name, args) => {
+                let mut vals = args
+                    .iter()
+                    .map(|a| a.eval(traces))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let unknown = || ExprError::UnknownFunction(format!("{}/{}", name, args.len()));
+                match (name.as_str(), vals.len()) {
+                    ("abs", 1) => Ok(vals.remove(0).map(f32::abs)),
+                    ("sqrt", 1) => Ok(vals.remove(0).map(f32::sqrt)),
+                    ("log", 1) => Ok(vals.remove(0).map(f32::log10)),
+                    ("ln", 1) => Ok(vals.remove(0).map(f32::ln)),
+                    ("exp", 1) => Ok(vals.remove(0).map(f32::exp)),
+                    ("db", 1) => Ok(vals.remove(0).map(|v| 20.0 * v.abs().log10())),
+                    // instantaneous phase in degrees
+                    ("ph", 1) => match vals.remove(0) {
+                        Value::Series(pts) => Ok(Value::Series(spectrum::phase(&pts))),
+                        Value::Scalar(v) => Ok(Value::Scalar(if v < 0.0 { 180.0 } else { 0.0 })),
+                    },
+                    ("deriv", 1) => match vals.remove(0) {
+                        Value::Series(pts) => Ok(Value::Series(deriv(&pts))),
+                        Value::Scalar(_) => Ok(Value::Scalar(0.0)),
+                    },
+                    ("integ", 1) => match vals.remove(0) {
+                        Value::Series(pts) => Ok(Value::Series(integ(&pts))),
+                        Value::Scalar(_) => Err(ExprError::NoTrace),
+                    },
+                    ("movavg", 2) => match (vals.remove(0), vals.remove(0)) {
+                        (Value::Series(pts), Value::Scalar(w)) => {
+                            Ok(Value::Series(movavg(&pts, w)))
+                        }
+                        _ => Err(unknown()),
+                    },
+                    _ => Err(unknown()),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trace(name: &str, pts: &[(f32, f32)]) -> Trace {
+        let pts = pts.iter().map(|(x, y)| VSPoint::new(*x, *y)).collect();
+        Trace::new(name.to_string(), pts)
+    }
+
+    /// evaluates src over traces, returning the y values
+    fn ys(src: &str, traces: &[Trace]) -> Vec<f32> {
+        let traces: Vec<&Trace> = traces.iter().collect();
+        evaluate(src, &traces)
+            .unwrap()
+            .iter()
+            .map(|p| p.y)
+            .collect()
+    }
+
+    #[test]
+    fn precedence() {
+        let a = [trace("a", &[(0.0, 1.0), (1.0, 2.0)])];
+        assert_eq!(ys("a+2*3^2", &a), [19.0, 20.0]);
+        assert_eq!(ys("(a+2)*3", &a), [9.0, 12.0]);
+        assert_eq!(ys("a-1-1", &a), [-1.0, 0.0]);
+        assert_eq!(ys("a/2/2", &a), [0.25, 0.5]);
+        assert_eq!(ys("-a^2", &a), [-1.0, -4.0]);
+        assert_eq!(ys("2^-a", &a), [0.5, 0.25]);
+        let traces: Vec<&Trace> = a.iter().collect();
+        assert_eq!(evaluate("1+2", &traces), Err(ExprError::NoTrace));
+        assert!(matches!(evaluate("(a", &traces), Err(ExprError::Parse(_))));
+        assert!(matches!(evaluate("a)", &traces), Err(ExprError::Parse(_))));
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(parse_number("10u"), Some(10e-6));
+        assert_eq!(parse_number("1.5MEG"), Some(1.5e6));
+        assert_eq!(parse_number("10ms"), Some(10.0 * 1e-3));
+        assert_eq!(parse_number("2.2kohm"), Some(2.2e3));
+        assert_eq!(parse_number("1e-3"), Some(1e-3));
+        assert_eq!(parse_number("1e3s"), Some(1e3));
+        assert_eq!(parse_number("5v"), Some(5.0));
+        assert_eq!(parse_number("1n4148"), None);
+        assert_eq!(parse_number("nan"), None);
+        assert_eq!(parse_number("k"), None);
+        let a = [trace("a", &[(0.0, 1.0)])];
+        assert_eq!(ys("a*2k", &a), [2e3]);
+        assert_eq!(ys("a*10ms", &a), [10.0 * 1e-3]);
+    }
+
+    #[test]
+    fn vectors() {
+        let traces = [
+            trace("a", &[(0.0, 3.0), (1.0, 5.0)]),
+            trace("b", &[(0.0, 1.0), (1.0, 2.0)]),
+            trace("v1#branch", &[(0.0, 0.5), (1.0, 0.5)]),
+        ];
+        assert_eq!(ys("v(a)", &traces), [3.0, 5.0]);
+        assert_eq!(ys("v(a,b)", &traces), [2.0, 3.0]);
+        assert_eq!(ys("V(a, b)*i(v1)", &traces), [1.0, 1.5]);
+        let refs: Vec<&Trace> = traces.iter().collect();
+        assert_eq!(
+            evaluate("v(c)", &refs),
+            Err(ExprError::UnknownTrace(String::from("v(c)")))
+        );
+        assert_eq!(
+            evaluate("foo(a)", &refs),
+            Err(ExprError::UnknownFunction(String::from("foo/1")))
+        );
+    }
+
+    #[test]
+    fn resampling() {
+        let traces = [
+            trace("a", &[(0.0, 0.0), (1.0, 1.0), (2.0, 2.0)]),
+            trace("b", &[(0.0, 0.0), (0.5, 1.0), (1.5, 3.0), (2.0, 4.0)]),
+        ];
+        // onto the scale of the left operand
+        assert_eq!(ys("a+b", &traces), [0.0, 3.0, 6.0]);
+        assert_eq!(ys("b+a", &traces), [0.0, 1.5, 4.5, 6.0]);
+        // points beyond the right operand's scale are left out
+        let short = [traces[0].clone(), trace("b", &[(0.0, 1.0), (1.0, 1.0)])];
+        assert_eq!(ys("a*b", &short), [0.0, 1.0]);
+    }
+
+    #[test]
+    fn calculus() {
+        let pts: Vec<(f32, f32)> = (0..7).map(|i| (i as f32 / 2.0, i as f32)).collect();
+        let ramp = [trace("a", &pts), trace("one", &[(0.0, 1.0), (3.0, 1.0)])];
+        assert_eq!(ys("deriv(a)", &ramp), [2.0; 7]);
+        // a time point repeated at a breakpoint
+        let repeated = [trace(
+            "b",
+            &[(0.0, 0.0), (1.0, 1.0), (1.0, 1.0), (2.0, 2.0)],
+        )];
+        assert_eq!(ys("deriv(b)", &repeated), [1.0; 3]);
+        let single = [trace("c", &[(1.0, 0.0), (1.0, 1.0)])];
+        assert_eq!(ys("deriv(c)", &single), [0.0; 0]);
+        assert_eq!(ys("integ(one)", &ramp), [0.0, 3.0]);
+        assert_eq!(
+            ys("integ(a)", &ramp),
+            [0.0, 0.25, 1.0, 2.25, 4.0, 6.25, 9.0]
+        );
+        // centered on each point, clipped to the trace
+        assert_eq!(
+            ys("movavg(a, 1)", &ramp),
+            [0.5, 1.0, 2.0, 3.0, 4.0, 5.0, 5.5]
+        );
+        assert_eq!(ys("movavg(one, 1)", &ramp), [1.0, 1.0]);
+    }
+}
@@ -0,0 +1,163 @@
+//! lod
+//! Level of detail for long traces: min/max decimation per pixel column of the visible range,
+//! cached per power of two zoom level
+
+use crate::transforms::VSPoint;
+use std::cell::RefCell;
+
+/// number of zoom levels kept in cache
+const CACHE_LEVELS: usize = 4;
+
+/// a range of a trace decimated for one zoom level
+#[derive(Debug, Clone)]
+struct Level {
+    /// the points were decimated for an x scale (pixels per x unit) of 2^zoom
+    zoom: i32,
+    /// number of points of the trace when decimated, so that appended points invalidate the level
+    len: usize,
+    /// x range decimated, the points include one more to either side
+    x0: f32,
+    x1: f32,
+    /// decimated points, sorted by x
+    points: Vec<VSPoint>,
+}
+
+/// cache of decimated trace points, most recently used zoom level first
+#[derive(Debug, Clone, Default)]
+pub struct Lod {
+    levels: RefCell<Vec<Level>>,
+}
+
+impl Lod {
+    /// returns the points of pts decimated for x_scale, within x range (x0, x1) plus one point to either side
+    /// so that lines leaving the visible range are drawn. The number of points returned is bounded by
+    /// 4 per pixel column of the range, regardless of the number of points in pts.
+    ///
+    /// x_scale is rounded up to a power of two, so zooming reuses the cached level until the scale doubles.
+    /// A level covers the visible range with a margin of its width to either side, and is rebuilt from the
+    /// points in that range only once the visible range leaves it.
+    pub fn points(&self, pts: &[VSPoint], x_scale: f32, x0: f32, x1: f32) -> Vec<VSPoint> {
+        let (x0, x1) = (x0.min(x1), x0.max(x1));
+        let zoom = x_scale.log2().ceil() as i32;
+        let mut levels = self.levels.borrow_mut();
+        let i = match levels
+            .iter()
+            .position(|l| l.zoom == zoom && l.len == pts.len() && l.x0 <= x0 && x1 <= l.x1)
+        {
+            Some(i) => i,
+            None => {
+                let margin = x1 - x0;
+                let (c0, c1) = (x0 - margin, x1 + margin);
+                levels.truncate(CACHE_LEVELS - 1);
+                levels.push(Level {
+                    zoom,
+                    len: pts.len(),
+                    x0: c0,
+                    x1: c1,
+                    points: decimate(visible(pts, c0, c1), 2f32.powi(zoom)),
+                });
+                levels.len() - 1
+            }
+        };
+        let level = levels.remove(i);
+        let ret = visible(&level.points, x0, x1).to_vec();
+        levels.insert(0, level);
+        ret
+    }
+}
+
+/// returns the points of pts, sorted by x, within x range (x0, x1) plus one point to either side
+fn visible(pts: &[VSPoint], x0: f32, x1: f32) -> &[VSPoint] {
+    let start = pts.partition_point(|p| p.x < x0).saturating_sub(1);
+    let end = (pts.partition_point(|p| p.x <= x1) + 1).min(pts.len());
+    &pts[start..end.max(start)]
+}
+
+/// min/max preserving decimation: of the points falling into each pixel column, only the minimum and maximum
+/// are kept, in their original order. Spikes narrower than a pixel remain visible.
+pub fn decimate(pts: &[VSPoint], x_scale: f32) -> Vec<VSPoint> {
+    let column = |p: &VSPoint| (p.x as f64 * x_scale as f64).floor();
+    let mut ret = Vec::new();
+    let mut i = 0;
+    while i < pts.len() {
+        let col = column(&pts[i]);
+        let (mut imin, mut imax) = (i, i);
+        i += 1;
+        while i < pts.len() && column(&pts[i]) == col {
+            if pts[i].y < pts[imin].y {
+                imin = i;
+            }
+            if pts[i].y > pts[imax].y {
+                imax = i;
+            }
+            i += 1;
+        }
+        ret.push(pts[imin.min(imax)]);
+        if imin != imax {
+            ret.push(pts[imin.max(imax)]);
+        }
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// n points over x from 0 to 1, with a noisy y
+    fn noise(n: usize) -> Vec<VSPoint> {
+        (0..n)
+            .map(|i| VSPoint::new(i as f32 / n as f32, ((i * 7919) % 1000) as f32 / 1000.0))
+            .collect()
+    }
+
+    #[test]
+    fn bounded_by_columns() {
+        let pts = noise(100_000);
+        let ret = decimate(&pts, 500.0);
+        assert!(ret.len() <= 2 * 500, "{}", ret.len());
+        assert!(ret.windows(2).all(|w| w[0].x < w[1].x));
+        // the visible range, plus a point to either side
+        let lod = Lod::default();
+        let visible = lod.points(&pts, 500.0, 0.25, 0.5);
+        assert!(visible.len() <= 4 * 125 + 2, "{}", visible.len());
+        // points sparser than the columns are kept
+        let sparse = noise(100);
+        assert_eq!(decimate(&sparse, 500.0), sparse);
+    }
+
+    #[test]
+    fn spike_survives() {
+        let mut pts = noise(100_000);
+        pts[54_321].y = 10.0;
+        pts[12_345].y = -10.0;
+        let ret = decimate(&pts, 100.0);
+        assert!(ret.contains(&pts[54_321]));
+        assert!(ret.contains(&pts[12_345]));
+    }
+
+    #[test]
+    fn levels_cover_the_visible_range() {
+        let pts = noise(100_000);
+        let lod = Lod::default();
+        lod.points(&pts, 1000.0, 0.4, 0.5);
+        // zoomed far in, only the range around the visible one is decimated
+        let n = lod.levels.borrow()[0].points.len();
+        assert!(n < 2 * 3 * 128 + 2, "{}", n);
+        // zooming within the same power of two, and panning within the margin, reuse the level
+        lod.points(&pts, 900.0, 0.35, 0.45);
+        assert_eq!(lod.levels.borrow().len(), 1);
+        // leaving the margin rebuilds it
+        lod.points(&pts, 900.0, 0.8, 0.9);
+        assert_eq!(lod.levels.borrow().len(), 2);
+        // away from its ends, the same points as decimating the visible range directly
+        let inner = |v: &[VSPoint]| -> Vec<VSPoint> {
+            v.iter()
+                .filter(|p| p.x > 0.81 && p.x < 0.89)
+                .copied()
+                .collect()
+        };
+        let direct = decimate(visible(&pts, 0.8, 0.9), 1024.0);
+        assert_eq!(inner(&lod.points(&pts, 1000.0, 0.8, 0.9)), inner(&direct));
+    }
+}
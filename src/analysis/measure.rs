@@ -0,0 +1,448 @@
+//! measure
+//! Waveform measurements over trace data, e.g. rms, rise time, frequency
+
+use crate::analysis::trace::{family_base, windowed, Trace};
+use crate::transforms::VSPoint;
+
+/// waveform measurements which can be made on a trace
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Measurement {
+    Min,
+    Max,
+    PkPk,
+    Average,
+    Rms,
+    RiseTime,
+    FallTime,
+    Overshoot,
+    SettlingTime,
+    Period,
+    Frequency,
+    /// delay from the reference trace to the measured trace
+    Delay,
+}
+
+impl Measurement {
+    pub const ALL: [Measurement; 12] = [
+        Measurement::Min,
+        Measurement::Max,
+        Measurement::PkPk,
+        Measurement::Average,
+        Measurement::Rms,
+        Measurement::RiseTime,
+        Measurement::FallTime,
+        Measurement::Overshoot,
+        Measurement::SettlingTime,
+        Measurement::Period,
+        Measurement::Frequency,
+        Measurement::Delay,
+    ];
+    /// returns true if the measurement compares against a reference trace
+    pub fn needs_reference(&self) -> bool {
+        matches!(self, Measurement::Delay)
+    }
+    /// returns the unit of the measurement result, given the units of the trace x and y values
+    pub fn unit(&self, x_unit: &str, y_unit: &str) -> String {
+        match self {
+            Measurement::Min
+            | Measurement::Max
+            | Measurement::PkPk
+            | Measurement::Average
+            | Measurement::Rms => y_unit.to_string(),
+            Measurement::RiseTime
+            | Measurement::FallTime
+            | Measurement::SettlingTime
+            | Measurement::Period
+            | Measurement::Delay => x_unit.to_string(),
+            Measurement::Overshoot => String::from("%"),
+            Measurement::Frequency if x_unit == "s" => String::from("Hz"),
+            Measurement::Frequency => format!("1/{}", x_unit),
+        }
+    }
+    /// perform the measurement on pts within window. Returns None if the measurement is undefined for the data,
+    /// e.g. the period of a waveform which does not cross its mid level twice.
+    pub fn measure(
+        &self,
+        pts: &[VSPoint],
+        reference: Option<&[VSPoint]>,
+        window: Option<(f32, f32)>,
+        config: &MeasureConfig,
+    ) -> Option<f32> {
+        let pts = windowed(pts, window);
+        match self {
+            Measurement::Min => min(&pts),
+            Measurement::Max => max(&pts),
+            Measurement::PkPk => Some(max(&pts)? - min(&pts)?),
+            Measurement::Average => average(&pts),
+            Measurement::Rms => rms(&pts),
+            Measurement::RiseTime => transition_time(&pts, config.lo, config.hi, true),
+            Measurement::FallTime => transition_time(&pts, config.lo, config.hi, false),
+            Measurement::Overshoot => overshoot(&pts),
+            Measurement::SettlingTime => settling_time(&pts, config.settle),
+            Measurement::Period => period(&pts),
+            Measurement::Frequency => period(&pts).map(|p| 1.0 / p),
+            Measurement::Delay => delay(&windowed(reference?, window), &pts),
+        }
+    }
+}
+
+impl std::fmt::Display for Measurement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Measurement::Min => "min",
+            Measurement::Max => "max",
+            Measurement::PkPk => "pk-pk",
+            Measurement::Average => "average",
+            Measurement::Rms => "rms",
+            Measurement::RiseTime => "rise time",
+            Measurement::FallTime => "fall time",
+            Measurement::Overshoot => "overshoot",
+            Measurement::SettlingTime => "settling time",
+            Measurement::Period => "period",
+            Measurement::Frequency => "frequency",
+            Measurement::Delay => "delay",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// measurement thresholds, as fractions of the waveform's low to high range
+#[derive(Debug, Clone, Copy)]
+pub struct MeasureConfig {
+    /// lower threshold for rise/fall time
+    pub lo: f32,
+    /// upper threshold for rise/fall time
+    pub hi: f32,
+    /// settling band, as a fraction of the step size
+    pub settle: f32,
+}
+
+impl Default for MeasureConfig {
+    fn default() -> Self {
+        MeasureConfig {
+            lo: 0.1,
+            hi: 0.9,
+            settle: 0.02,
+        }
+    }
+}
+
+/// a measurement to be made on a named trace, kept across simulation runs
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeasurementSpec {
+    pub kind: Measurement,
+    /// name of the measured trace
+    pub trace: String,
+    /// name of the reference trace, for measurements between two traces
+    pub reference: Option<String>,
+}
+
+impl MeasurementSpec {
+    /// evaluate the measurement over traces, returning the result and its unit
+    pub fn evaluate(
+        &self,
+        traces: &[&Trace],
+        window: Option<(f32, f32)>,
+        config: &MeasureConfig,
+    ) -> Option<(f32, String)> {
+        let t = traces.iter().find(|t| t.name == self.trace)?;
+        let r = match &self.reference {
+            Some(name) => Some(traces.iter().find(|t| &t.name == name)?.points.as_slice()),
+            None => None,
+        };
+        let v = self.kind.measure(&t.points, r, window, config)?;
+        Some((v, self.kind.unit(&t.x_unit, &t.unit)))
+    }
+    /// evaluate the measurement on every run of a sweep, monte carlo or corner analysis:
+    /// traces named after the measured vector with a run label, measured against the reference of the same run.
+    /// Runs where the measurement is undefined are left out.
+    pub fn evaluate_family(
+        &self,
+        traces: &[&Trace],
+        window: Option<(f32, f32)>,
+        config: &MeasureConfig,
+    ) -> Vec<f32> {
+        let base = family_base(&self.trace);
+        traces
+            .iter()
+            .filter(|t| t.name != base && family_base(&t.name) == base)
+            .filter_map(|t| {
+                // the run label, e.g. ` (mc=3)`
+                let label = &t.name[base.len()..];
+                let r = match &self.reference {
+                    Some(name) => {
+                        let name = format!("{}{}", family_base(name), label);
+                        Some(traces.iter().find(|t| t.name == name)?.points.as_slice())
+                    }
+                    None => None,
+                };
+                self.kind.measure(&t.points, r, window, config)
+            })
+            .collect()
+    }
+}
+
+/// statistics of a measurement over the runs of an analysis
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stats {
+    pub n: usize,
+    pub mean: f32,
+    /// sample standard deviation
+    pub sigma: f32,
+    pub min: f32,
+    pub max: f32,
+}
+
+impl Stats {
+    /// returns the statistics of values, if there are any
+    pub fn new(values: &[f32]) -> Option<Self> {
+        let n = values.len();
+        if n == 0 {
+            return None;
+        }
+        let mean = values.iter().map(|v| *v as f64).sum::<f64>() / n as f64;
+        let var = if n > 1 {
+            values
+                .iter()
+                .map(|v| (*v as f64 - mean).powi(2))
+                .sum::<f64>()
+                / (n - 1) as f64
+        } else {
+            0.0
+        };
+        Some(Stats {
+            n,
+            mean: mean as f32,
+            sigma: var.sqrt() as f32,
+            min: values.iter().copied().reduce(f32::min)?,
+            max: values.iter().copied().reduce(f32::max)?,
+        })
+    }
+}
+
+/// counts of values in bins of equal width, from the minimum to the maximum value
+pub fn histogram(values: &[f32], bins: usize) -> Vec<usize> {
+    let mut counts = vec![0; bins];
+    let Some(stats) = Stats::new(values) else {
+        return counts;
+    };
+    let width = (stats.max - stats.min) / bins as f32;
+    for v in values {
+        let i = if width > 0.0 {
+            ((v - stats.min) / width) as usize
+        } else {
+            0
+        };
+        counts[i.min(bins - 1)] += 1;
+    }
+    counts
+}
+
+impl std::fmt::Display for MeasurementSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.reference {
+            Some(r) => write!(f, "{}({} -> {})", self.kind, r, self.trace),
+            None => write!(f, "{}({})", self.kind, self.trace),
+        }
+    }
+}
+
+fn min(pts: &[VSPoint]) -> Option<f32> {
+    pts.iter().map(|p| p.y).reduce(f32::min)
+}
+
+fn max(pts: &[VSPoint]) -> Option<f32> {
+    pts.iter().map(|p| p.y).reduce(f32::max)
+}
+
+/// integral of f(y) over x by the trapezoid rule, along with the x span. Time steps need not be uniform.
+fn integrate(pts: &[VSPoint], f: impl Fn(f64) -> f64) -> Option<(f64, f64)> {
+    let span = (pts.last()?.x - pts.first()?.x) as f64;
+    if span <= 0.0 {
+        return None;
+    }
+    let sum = pts
+        .windows(2)
+        .map(|w| (w[1].x - w[0].x) as f64 * (f(w[0].y as f64) + f(w[1].y as f64)) / 2.0)
+        .sum();
+    Some((sum, span))
+}
+
+fn average(pts: &[VSPoint]) -> Option<f32> {
+    let (sum, span) = integrate(pts, |y| y)?;
+    Some((sum / span) as f32)
+}
+
+fn rms(pts: &[VSPoint]) -> Option<f32> {
+    let (sum, span) = integrate(pts, |y| y * y)?;
+    Some((sum / span).sqrt() as f32)
+}
+
+/// returns the x positions where pts crosses level. If rising is some, only crossings in that direction are returned.
+pub fn crossings(pts: &[VSPoint], level: f32, rising: Option<bool>) -> Vec<f32> {
+    pts.windows(2)
+        .filter_map(|w| {
+            let (p0, p1) = (w[0], w[1]);
+            let up = p0.y < level && p1.y >= level;
+            let down = p0.y > level && p1.y <= level;
+            let wanted = match rising {
+                Some(true) => up,
+                Some(false) => down,
+                None => up || down,
+            };
+            if !wanted {
+                return None;
+            }
+            Some(p0.x + (level - p0.y) * (p1.x - p0.x) / (p1.y - p0.y))
+        })
+        .collect()
+}
+
+/// returns the level at frac between the minimum and maximum of pts
+fn level_at(pts: &[VSPoint], frac: f32) -> Option<f32> {
+    let (lo, hi) = (min(pts)?, max(pts)?);
+    Some(lo + (hi - lo) * frac)
+}
+
+/// time for the first full transition from the lo to the hi threshold (rising), or hi to lo (falling)
+fn transition_time(pts: &[VSPoint], lo: f32, hi: f32, rising: bool) -> Option<f32> {
+    let (lo, hi) = (level_at(pts, lo)?, level_at(pts, hi)?);
+    let (start, end) = if rising { (lo, hi) } else { (hi, lo) };
+    // the last start crossing before the first end crossing, so that glitches and ringing are skipped
+    let t_end = *crossings(pts, end, Some(rising)).first()?;
+    let t_start = crossings(pts, start, Some(rising))
+        .into_iter()
+        .rev()
+        .find(|t| *t <= t_end)?;
+    Some(t_end - t_start)
+}
+
+/// peak excursion beyond the final value, in percent of the step from initial to final value
+fn overshoot(pts: &[VSPoint]) -> Option<f32> {
+    let (initial, fin) = (pts.first()?.y, pts.last()?.y);
+    let step = fin - initial;
+    if step == 0.0 {
+        return None;
+    }
+    let peak = if step > 0.0 {
+        max(pts)? - fin
+    } else {
+        fin - min(pts)?
+    };
+    Some((peak.max(0.0) / step.abs()) * 100.0)
+}
+
+/// time from the start of pts until the waveform last enters the band of tol * step around its final value
+fn settling_time(pts: &[VSPoint], tol: f32) -> Option<f32> {
+    let (first, last) = (pts.first()?, pts.last()?);
+    let band = (last.y - first.y).abs() * tol;
+    let outside = pts.windows(2).rfind(|w| (w[0].y - last.y).abs() > band);
+    match outside {
+        Some(w) => Some(w[1].x - first.x),
+        None => Some(0.0),
+    }
+}
+
+/// average period between rising crossings of the mid level
+fn period(pts: &[VSPoint]) -> Option<f32> {
+    let xs = crossings(pts, level_at(pts, 0.5)?, Some(true));
+    if xs.len() < 2 {
+        return None;
+    }
+    Some((xs[xs.len() - 1] - xs[0]) / (xs.len() - 1) as f32)
+}
+
+/// delay between the first mid level crossings of reference and pts, in the same direction
+fn delay(reference: &[VSPoint], pts: &[VSPoint]) -> Option<f32> {
+    let r_level = level_at(reference, 0.5)?;
+    let level = level_at(pts, 0.5)?;
+    let r_crossings = crossings(reference, r_level, None);
+    let t_ref = *r_crossings.first()?;
+    let rising = reference
+        .iter()
+        .find(|p| p.x >= t_ref)
+        .map(|p| p.y >= r_level)?;
+    let t = crossings(pts, level, Some(rising))
+        .into_iter()
+        .find(|t| *t >= t_ref)?;
+    Some(t - t_ref)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// samples f at n + 1 points from 0 to end
+    fn sample(f: impl Fn(f32) -> f32, end: f32, n: usize) -> Vec<VSPoint> {
+        (0..=n)
+            .map(|i| {
+                let x = end * i as f32 / n as f32;
+                VSPoint::new(x, f(x))
+            })
+            .collect()
+    }
+
+    /// square wave of period 2, high first, delayed by shift samples of 0.01
+    fn square(shift: usize) -> Vec<VSPoint> {
+        (0..1000)
+            .map(|i| {
+                let high = ((i + 200 - shift) / 100).is_multiple_of(2);
+                VSPoint::new(i as f32 * 0.01, if high { 1.0 } else { 0.0 })
+            })
+            .collect()
+    }
+
+    fn measure(m: Measurement, pts: &[VSPoint], reference: Option<&[VSPoint]>) -> f32 {
+        m.measure(pts, reference, None, &MeasureConfig::default())
+            .unwrap()
+    }
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-3, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn rise_and_fall_time() {
+        let ramp = |x: f32| (x - 1.0).clamp(0.0, 1.0);
+        let rise = sample(ramp, 4.0, 400);
+        assert_close(measure(Measurement::RiseTime, &rise, None), 0.8);
+        let fall = sample(|x| 1.0 - ramp(x), 4.0, 400);
+        assert_close(measure(Measurement::FallTime, &fall, None), 0.8);
+        assert_eq!(
+            Measurement::FallTime.measure(&rise, None, None, &MeasureConfig::default()),
+            None
+        );
+    }
+
+    #[test]
+    fn overshoot_and_settling_time() {
+        // rises to 1.2 at x = 2, then decays linearly to its final value of 1 at x = 3
+        let step = sample(
+            |x| match x {
+                x if x < 1.0 => 0.0,
+                x if x < 2.0 => 1.2 * (x - 1.0),
+                x if x < 3.0 => 1.2 - 0.2 * (x - 2.0),
+                _ => 1.0,
+            },
+            5.0,
+            500,
+        );
+        assert_close(measure(Measurement::Overshoot, &step, None), 20.0);
+        // within 2% of the final value from x = 2.9
+        let settling = measure(Measurement::SettlingTime, &step, None);
+        assert!((settling - 2.9).abs() <= 0.01, "{}", settling);
+    }
+
+    #[test]
+    fn period_frequency_and_delay() {
+        let reference = square(0);
+        assert_close(measure(Measurement::Period, &reference, None), 2.0);
+        assert_close(measure(Measurement::Frequency, &reference, None), 0.5);
+        let delayed = square(30);
+        assert_close(measure(Measurement::Delay, &delayed, Some(&reference)), 0.3);
+        assert_eq!(
+            Measurement::Delay.measure(&delayed, None, None, &MeasureConfig::default()),
+            None
+        );
+    }
+}
@@ -1,3 +1,10 @@
+mod cursors;
+pub(crate) mod expr;
+mod lod;
+mod measure;
 mod plot;
 pub(crate) mod plot_page;
+mod spectrum;
+pub(crate) mod trace;
 mod viewport;
+mod waveio;
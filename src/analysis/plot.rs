@@ -1,6 +1,8 @@
 //! plot
 //! Space in which math values form plots
 
+use crate::analysis::cursors::{CursorId, CursorReadout, Cursors};
+use crate::analysis::trace::{RcRTrace, Trace, PALETTE};
 use crate::analysis::viewport;
 use crate::transforms::CSVec;
 use crate::transforms::{CSPoint, Point, VCTransform, VSBox, VSPoint};
@@ -15,30 +17,36 @@ use std::collections::HashSet;
 use std::default::Default;
 use std::hash::Hash;
 
+/// hover/click hit tolerance, in canvas space (pixels)
+const HIT_TOLERANCE: f32 = 5.0;
+
 pub trait PlotElement: Hash + Eq + Drawable + Clone {
     fn bounding_box(&self) -> VSBox;
+    /// returns the shortest distance in canvas space between csp and the element
+    fn csp_distance(&self, vct: VCTransform, csp: CSPoint) -> f32;
+    /// returns true if the element intersects vsb
+    fn intersects_vsb(&self, vsb: &VSBox) -> bool;
 }
 
-pub type PlotTrace = Vec<VSPoint>;
-
 /// an enum to unify different types in schematic (nets and devices)
 #[derive(Debug, Clone)]
 pub enum ChartElement {
-    PlotTrace(PlotTrace),
+    PlotTrace(RcRTrace),
 }
 
 impl Default for ChartElement {
     fn default() -> Self {
-        ChartElement::PlotTrace(Vec::from([VSPoint::origin(), VSPoint::new(1.0, 1.0)]))
+        ChartElement::PlotTrace(RcRTrace::new(Trace::new(
+            String::new(),
+            Vec::from([VSPoint::origin(), VSPoint::new(1.0, 1.0)]),
+        )))
     }
 }
 
 impl PartialEq for ChartElement {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
-            (Self::PlotTrace(l0), Self::PlotTrace(r0)) => {
-                by_address::ByAddress(l0) == by_address::ByAddress(r0)
-            }
+            (Self::PlotTrace(l0), Self::PlotTrace(r0)) => l0 == r0,
         }
     }
 }
@@ -48,26 +56,33 @@ impl Eq for ChartElement {}
 impl std::hash::Hash for ChartElement {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         match self {
-            ChartElement::PlotTrace(d) => by_address::ByAddress(d).hash(state),
+            ChartElement::PlotTrace(d) => d.hash(state),
         }
     }
 }
 
+/// stroke the trace polyline with the given width and color
+fn stroke_trace(trace: &Trace, vct: VCTransform, width: f32, color: Color, frame: &mut Frame) {
+    let stroke = Stroke {
+        width,
+        style: stroke::Style::Solid(color),
+        line_cap: LineCap::Square,
+        ..Stroke::default()
+    };
+    let mut path_builder = Builder::new();
+    // decimated to the visible range, so the cost is bounded by canvas width rather than trace length
+    for vsp in &trace.lod_points(vct, 0.0, frame.width()) {
+        path_builder.line_to(Point::from(vct.transform_point(*vsp)).into());
+    }
+    frame.stroke(&path_builder.build(), stroke);
+}
+
 impl Drawable for ChartElement {
     fn draw_persistent(&self, vct: VCTransform, _vcscale: f32, frame: &mut Frame) {
         match self {
             ChartElement::PlotTrace(trace) => {
-                let stroke = Stroke {
-                    width: 1.0,
-                    style: stroke::Style::Solid(Color::from_rgb(0.8, 0.8, 0.8)),
-                    line_cap: LineCap::Square,
-                    ..Stroke::default()
-                };
-                let mut path_builder = Builder::new();
-                for vsp in trace {
-                    path_builder.line_to(Point::from(vct.transform_point(*vsp)).into());
-                }
-                frame.stroke(&path_builder.build(), stroke.clone());
+                let trace = trace.0.borrow();
+                stroke_trace(&trace, vct, 1.0, trace.color, frame);
             }
         }
     }
@@ -75,17 +90,8 @@ impl Drawable for ChartElement {
     fn draw_selected(&self, vct: VCTransform, _vcscale: f32, frame: &mut Frame) {
         match self {
             ChartElement::PlotTrace(trace) => {
-                let stroke = Stroke {
-                    width: 1.0,
-                    style: stroke::Style::Solid(Color::from_rgb(0.9, 0.9, 0.9)),
-                    line_cap: LineCap::Square,
-                    ..Stroke::default()
-                };
-                let mut path_builder = Builder::new();
-                for vsp in trace {
-                    path_builder.line_to(Point::from(vct.transform_point(*vsp)).into());
-                }
-                frame.stroke(&path_builder.build(), stroke.clone());
+                let trace = trace.0.borrow();
+                stroke_trace(&trace, vct, 2.5, trace.color, frame);
             }
         }
     }
@@ -93,17 +99,8 @@ impl Drawable for ChartElement {
     fn draw_preview(&self, vct: VCTransform, _vcscale: f32, frame: &mut Frame) {
         match self {
             ChartElement::PlotTrace(trace) => {
-                let stroke = Stroke {
-                    width: 1.0,
-                    style: stroke::Style::Solid(Color::from_rgb(1.0, 1.0, 1.0)),
-                    line_cap: LineCap::Square,
-                    ..Stroke::default()
-                };
-                let mut path_builder = Builder::new();
-                for vsp in trace {
-                    path_builder.line_to(Point::from(vct.transform_point(*vsp)).into());
-                }
-                frame.stroke(&path_builder.build(), stroke.clone());
+                let trace = trace.0.borrow();
+                stroke_trace(&trace, vct, 2.0, Color::from_rgb(1.0, 1.0, 1.0), frame);
             }
         }
     }
@@ -112,7 +109,17 @@ impl Drawable for ChartElement {
 impl PlotElement for ChartElement {
     fn bounding_box(&self) -> VSBox {
         match self {
-            ChartElement::PlotTrace(trace) => VSBox::from_points(trace),
+            ChartElement::PlotTrace(trace) => VSBox::from_points(&trace.0.borrow().points),
+        }
+    }
+    fn csp_distance(&self, vct: VCTransform, csp: CSPoint) -> f32 {
+        match self {
+            ChartElement::PlotTrace(trace) => trace.0.borrow().csp_distance(vct, csp),
+        }
+    }
+    fn intersects_vsb(&self, vsb: &VSBox) -> bool {
+        match self {
+            ChartElement::PlotTrace(trace) => trace.0.borrow().intersects_vsb(vsb),
         }
     }
 }
@@ -129,8 +136,22 @@ pub trait ContentMsg {
 pub enum Msg {
     /// do nothing
     None,
-    /// new trace data
-    Traces(Vec<Vec<VSPoint>>),
+    /// new trace data, replaces all traces
+    Traces(Vec<Trace>),
+    /// add a trace to the plot
+    AddTrace(Trace),
+    /// show or hide the named trace
+    SetVisible(String, bool),
+    /// hide all traces except the named trace
+    Isolate(String),
+    /// remove the named trace from the plot
+    Delete(String),
+    /// snap the measurement cursors to the named trace
+    CursorTrace(String),
+    /// remove the measurement cursors
+    ClearCursors,
+    /// end dragging a trace, e.g. after it was dropped onto another plot
+    EndTraceDrag,
     /// iced canvas event, along with cursor position inside canvas bounds
     Event(Event, VSPoint),
 }
@@ -155,6 +176,10 @@ pub enum PlotSt {
     Idle,
     /// left click-drag area selection
     AreaSelect(VSBox),
+    /// dragging a measurement cursor
+    DragCursor(CursorId),
+    /// Ctrl-click-drag a trace, to be dropped onto another plot
+    DragTrace,
 }
 
 /// struct holding schematic state (nets, devices, and their locations)
@@ -163,8 +188,12 @@ pub struct Plot<E>
 where
     E: PlotElement,
 {
-    /// chart contents
+    /// chart contents - visible elements
     content: HashSet<E>,
+    /// all traces on the plot in legend order, including hidden traces
+    traces: Vec<RcRTrace>,
+    /// watermark for assigning trace colors from palette
+    color_wm: usize,
     /// active element
     pub active_element: Option<E>,
     /// schematic state
@@ -177,6 +206,14 @@ where
     tentatives: HashSet<E>,
     /// cursor position in schematic space
     curpos_vsp: VSPoint,
+    /// viewport to canvas transform, used for hit testing in canvas space
+    vct: Option<VCTransform>,
+    /// A/B measurement cursors
+    cursors: Cursors,
+    /// trace being dragged
+    dragged: Option<RcRTrace>,
+    /// keyboard modifiers held, as mouse button events do not carry them
+    modifiers: iced::keyboard::Modifiers,
 }
 
 /// implement Schematic as viewport content
@@ -186,6 +223,8 @@ impl viewport::Content<Msg> for Plot<ChartElement> {
         match self.state {
             PlotSt::Idle => mouse::Interaction::default(),
             PlotSt::AreaSelect(_) => mouse::Interaction::Crosshair,
+            PlotSt::DragCursor(_) => mouse::Interaction::ResizingHorizontally,
+            PlotSt::DragTrace => mouse::Interaction::Grabbing,
         }
     }
 
@@ -232,6 +271,20 @@ impl viewport::Content<Msg> for Plot<ChartElement> {
             .map(|e| e.draw_preview(vct, 1.0, frame))
             .collect();
 
+        // draw the value of the hovered trace at the cursor x position
+        if let Some((name, vsp)) = self.hover_readout() {
+            let csp = vct.transform_point(vsp);
+            let marker = Path::circle(Point::from(csp).into(), 3.0);
+            frame.fill(&marker, Color::from_rgb(1.0, 1.0, 1.0));
+            frame.fill_text(canvas::Text {
+                content: format!("{}: ({:.3e}, {:.3e})", name, vsp.x, vsp.y),
+                position: Point::from(csp + CSVec::new(6.0, -14.0)).into(),
+                color: Color::from_rgb(1.0, 1.0, 1.0),
+                size: 14.0,
+                ..Default::default()
+            });
+        }
+
         /// draw the cursor onto canvas
         pub fn draw_cursor(vct: VCTransform, frame: &mut Frame, curpos_vsp: VSPoint) {
             let cursor_stroke = || -> Stroke {
@@ -250,21 +303,33 @@ impl viewport::Content<Msg> for Plot<ChartElement> {
             frame.stroke(&c, cursor_stroke());
         }
         draw_cursor(vct, frame, self.curpos_vsp);
+
+        let cursor_trace = self.cursor_trace();
+        self.cursors.draw(
+            vct,
+            cursor_trace.as_ref().map(|t| t.0.borrow()).as_deref(),
+            frame,
+        );
     }
     /// draw onto passive cache
     fn draw_passive(&self, vct: VCTransform, frame: &mut Frame) {
         let _: Vec<_> = self
-            .selected
+            .content
             .iter()
-            .map(|e| e.draw_selected(vct, 1.0, frame))
+            .map(|e| e.draw_persistent(vct, 1.0, frame))
             .collect();
         let _: Vec<_> = self
-            .content
+            .selected
             .iter()
             .map(|e| e.draw_selected(vct, 1.0, frame))
             .collect();
     }
 
+    /// update the transform used for hit testing
+    fn vct_update(&mut self, vct: VCTransform) {
+        self.vct = Some(vct);
+    }
+
     /// returns the bouding box of schematic content
     fn bounds(&self) -> VSBox {
         if !self.content.is_empty() {
@@ -281,30 +346,278 @@ impl viewport::Content<Msg> for Plot<ChartElement> {
     /// mutate state based on message and cursor position
     fn update(&mut self, msg: Msg) -> bool {
         let mut clear_passive = false;
+        const NO_MODIFIER: iced::keyboard::Modifiers = iced::keyboard::Modifiers::empty();
 
         match msg {
-            Msg::Event(event, curpos_vsp) => {
-                if let Event::Mouse(iced::mouse::Event::CursorMoved { .. }) = event {
+            Msg::Event(event, curpos_vsp) => match (self.state, event) {
+                (_, Event::Keyboard(iced::keyboard::Event::ModifiersChanged(modifiers))) => {
+                    self.modifiers = modifiers;
+                }
+                // ctrl-click-drag a trace onto another plot
+                (
+                    PlotSt::Idle,
+                    Event::Mouse(iced::mouse::Event::ButtonPressed(iced::mouse::Button::Left)),
+                ) if self.modifiers.control() && !self.tentatives.is_empty() => {
+                    if let Some(ChartElement::PlotTrace(t)) = self.tentatives.iter().next() {
+                        self.dragged = Some(t.clone());
+                        self.state = PlotSt::DragTrace;
+                    }
+                }
+                (
+                    PlotSt::DragTrace,
+                    Event::Mouse(iced::mouse::Event::ButtonReleased(iced::mouse::Button::Left)),
+                ) => {
+                    self.dragged = None;
+                    self.state = PlotSt::Idle;
+                }
+                // measurement cursors
+                (PlotSt::DragCursor(id), Event::Mouse(iced::mouse::Event::CursorMoved { .. })) => {
+                    self.curpos_vsp = curpos_vsp;
+                    self.place_cursor(id, curpos_vsp.x);
+                }
+                (
+                    PlotSt::DragCursor(_),
+                    Event::Mouse(iced::mouse::Event::ButtonReleased(iced::mouse::Button::Left)),
+                ) => {
+                    self.state = PlotSt::Idle;
+                }
+                (
+                    PlotSt::Idle,
+                    Event::Keyboard(iced::keyboard::Event::KeyPressed {
+                        key_code: iced::keyboard::KeyCode::A,
+                        modifiers: NO_MODIFIER,
+                    }),
+                ) => {
+                    self.place_cursor(CursorId::A, curpos_vsp.x);
+                }
+                (
+                    PlotSt::Idle,
+                    Event::Keyboard(iced::keyboard::Event::KeyPressed {
+                        key_code: iced::keyboard::KeyCode::B,
+                        modifiers: NO_MODIFIER,
+                    }),
+                ) => {
+                    self.place_cursor(CursorId::B, curpos_vsp.x);
+                }
+                (_, Event::Mouse(iced::mouse::Event::CursorMoved { .. })) => {
                     self.update_cursor_vsp(curpos_vsp);
                 }
-            }
+                // click or click-drag selection, or grab a measurement cursor
+                (
+                    PlotSt::Idle,
+                    Event::Mouse(iced::mouse::Event::ButtonPressed(iced::mouse::Button::Left)),
+                ) => {
+                    let grabbed = self.vct.and_then(|vct| {
+                        let csp_x = vct.transform_point(curpos_vsp).x;
+                        self.cursors.cursor_near(vct, csp_x, HIT_TOLERANCE)
+                    });
+                    self.state = match grabbed {
+                        Some(id) => PlotSt::DragCursor(id),
+                        None => PlotSt::AreaSelect(VSBox::new(curpos_vsp, curpos_vsp)),
+                    };
+                }
+                (
+                    PlotSt::AreaSelect(_),
+                    Event::Mouse(iced::mouse::Event::ButtonReleased(iced::mouse::Button::Left)),
+                ) => {
+                    self.tentatives_to_selected();
+                    self.state = PlotSt::Idle;
+                    self.update_cursor_vsp(curpos_vsp);
+                    clear_passive = true;
+                }
+                // cycle tentative selection
+                (
+                    PlotSt::Idle,
+                    Event::Keyboard(iced::keyboard::Event::KeyPressed {
+                        key_code: iced::keyboard::KeyCode::C,
+                        modifiers: iced::keyboard::Modifiers::SHIFT,
+                    }),
+                ) => {
+                    self.tentative_next_by_vsp(curpos_vsp);
+                }
+                // clear selection
+                (
+                    _,
+                    Event::Keyboard(iced::keyboard::Event::KeyPressed {
+                        key_code: iced::keyboard::KeyCode::Escape,
+                        ..
+                    }),
+                ) => {
+                    self.selected.clear();
+                    self.active_element = None;
+                    self.state = PlotSt::Idle;
+                    self.dragged = None;
+                    clear_passive = true;
+                }
+                _ => {}
+            },
             Msg::None => {}
             Msg::Traces(traces) => {
                 self.selected.clear();
                 self.tentatives.clear();
                 self.content.clear();
+                self.traces.clear();
+                self.active_element = None;
+                self.color_wm = 0;
 
                 for trace in traces {
-                    self.content.insert(ChartElement::PlotTrace(trace));
+                    self.add_trace(trace);
                 }
 
                 clear_passive = true;
             }
+            Msg::AddTrace(trace) => {
+                self.add_trace(trace);
+                clear_passive = true;
+            }
+            Msg::SetVisible(name, visible) => {
+                if let Some(t) = self.trace_by_name(&name) {
+                    self.set_visible(&t, visible);
+                    clear_passive = true;
+                }
+            }
+            Msg::Isolate(name) => {
+                for t in self.traces.clone() {
+                    let visible = t.0.borrow().name == name;
+                    self.set_visible(&t, visible);
+                }
+                clear_passive = true;
+            }
+            Msg::CursorTrace(name) => {
+                self.cursors.trace = Some(name);
+                // re-snap cursors onto the new trace
+                for id in [CursorId::A, CursorId::B] {
+                    if let Some(x) = self.cursors.x(id) {
+                        self.place_cursor(id, x);
+                    }
+                }
+            }
+            Msg::ClearCursors => {
+                self.cursors.clear();
+            }
+            Msg::EndTraceDrag => {
+                if let PlotSt::DragTrace = self.state {
+                    self.state = PlotSt::Idle;
+                }
+                self.dragged = None;
+            }
+            Msg::Delete(name) => {
+                if let Some(t) = self.trace_by_name(&name) {
+                    self.set_visible(&t, false);
+                    self.traces.retain(|x| *x != t);
+                    clear_passive = true;
+                }
+            }
         }
         clear_passive
     }
 }
 
+impl Plot<ChartElement> {
+    /// returns the traces on the plot in legend order, including hidden traces
+    pub fn traces(&self) -> &[RcRTrace] {
+        &self.traces
+    }
+    /// returns the trace being dragged, if any
+    pub fn dragged_trace(&self) -> Option<RcRTrace> {
+        self.dragged.clone()
+    }
+    /// returns the selected traces in legend order
+    pub fn selected_traces(&self) -> Vec<RcRTrace> {
+        self.traces
+            .iter()
+            .filter(|t| {
+                self.selected
+                    .contains(&ChartElement::PlotTrace((*t).clone()))
+            })
+            .cloned()
+            .collect()
+    }
+    /// returns the name of the hovered trace and its interpolated value at the cursor x position
+    pub fn hover_readout(&self) -> Option<(String, VSPoint)> {
+        if !matches!(self.state, PlotSt::Idle) {
+            return None;
+        }
+        match self.tentatives.iter().next()? {
+            ChartElement::PlotTrace(t) => {
+                let t = t.0.borrow();
+                let y = t.value_at(self.curpos_vsp.x)?;
+                Some((t.name.clone(), VSPoint::new(self.curpos_vsp.x, y)))
+            }
+        }
+    }
+    /// returns the trace the measurement cursors snap to: the chosen trace, else the active trace, else the first visible trace
+    pub fn cursor_trace(&self) -> Option<RcRTrace> {
+        if let Some(t) = self
+            .cursors
+            .trace
+            .as_ref()
+            .and_then(|name| self.trace_by_name(name))
+        {
+            return Some(t);
+        }
+        if let Some(ChartElement::PlotTrace(t)) = &self.active_element {
+            return Some(t.clone());
+        }
+        self.traces.iter().find(|t| t.0.borrow().visible).cloned()
+    }
+    /// returns the measurement cursor positions on the cursor trace
+    pub fn cursor_readout(&self) -> CursorReadout {
+        let t = self.cursor_trace();
+        let r = self
+            .cursors
+            .readout(t.as_ref().map(|t| t.0.borrow()).as_deref());
+        r
+    }
+    /// returns the x range between the measurement cursors, if both are placed
+    pub fn cursor_window(&self) -> Option<(f32, f32)> {
+        Some((self.cursors.x(CursorId::A)?, self.cursors.x(CursorId::B)?))
+    }
+    /// place measurement cursor id at x, snapping to the cursor trace
+    fn place_cursor(&mut self, id: CursorId, x: f32) {
+        let t = self.cursor_trace();
+        self.cursors
+            .place(id, x, t.as_ref().map(|t| t.0.borrow()).as_deref());
+    }
+    /// returns the trace with the given name, if any
+    pub fn trace_by_name(&self, name: &str) -> Option<RcRTrace> {
+        self.traces
+            .iter()
+            .find(|t| t.0.borrow().name == name)
+            .cloned()
+    }
+    /// add trace to the plot, assigning it the next color in palette. Replaces any existing trace of the same name.
+    fn add_trace(&mut self, mut trace: Trace) {
+        if let Some(t) = self.trace_by_name(&trace.name) {
+            trace.color = t.0.borrow().color;
+            self.set_visible(&t, false);
+            self.traces.retain(|x| *x != t);
+        } else {
+            trace.color = PALETTE[self.color_wm % PALETTE.len()];
+            self.color_wm += 1;
+        }
+        let visible = trace.visible;
+        let t = RcRTrace::new(trace);
+        self.traces.push(t.clone());
+        self.set_visible(&t, visible);
+    }
+    /// show or hide trace. Hidden traces are removed from content so they are neither drawn nor selectable.
+    fn set_visible(&mut self, t: &RcRTrace, visible: bool) {
+        t.0.borrow_mut().visible = visible;
+        let e = ChartElement::PlotTrace(t.clone());
+        if visible {
+            self.content.insert(e);
+        } else {
+            self.selected.remove(&e);
+            self.tentatives.remove(&e);
+            if self.active_element.as_ref() == Some(&e) {
+                self.active_element = None;
+            }
+            self.content.remove(&e);
+        }
+    }
+}
+
 impl<E> Plot<E>
 where
     E: PlotElement,
@@ -325,8 +638,13 @@ where
     }
     /// set tentative flags by intersection with ssb
     pub fn tentatives_by_vsbox(&mut self, vsb: &VSBox) {
-        let _ssb_p = VSBox::from_points([vsb.min, vsb.max]);
-        // self.tentatives = self.content.intersects_vsb(ssb_p)
+        let vsb_p = VSBox::from_points([vsb.min, vsb.max]);
+        self.tentatives = self
+            .content
+            .iter()
+            .filter(|e| e.intersects_vsb(&vsb_p))
+            .cloned()
+            .collect();
     }
     /// set 1 tentative flag by ssp, skipping skip elements which contains ssp. Returns netname if tentative is a net segment
     pub fn tentative_by_vspoint(&mut self, vsp: VSPoint, skip: &mut usize) {
@@ -347,21 +665,28 @@ where
         if self.tentatives.len() == 1 {
             let mut v: Vec<_> = self.tentatives.iter().collect();
             self.active_element = v.pop().cloned();
+        } else {
+            self.active_element = None;
         }
         self.tentatives.clear();
     }
     /// set 1 tentative flag based on ssp and skip number. Returns the flagged element, if any.
-    fn selectable(&mut self, _vsp: VSPoint, skip: &mut usize) -> Option<E> {
-        loop {
-            let count = 0; // tracks the number of skipped elements
-                           // if let Some(e) = self.content.selectable(vsp, *skip, &mut count) {
-                           //     return Some(e);
-                           // }
-            if count == 0 {
-                *skip = 0;
-                return None;
-            }
-            *skip -= count;
+    /// Elements within hit tolerance of vsp are ordered nearest first, skip wraps around.
+    fn selectable(&mut self, vsp: VSPoint, skip: &mut usize) -> Option<E> {
+        let vct = self.vct?;
+        let csp = vct.transform_point(vsp);
+        let mut hits: Vec<(f32, &E)> = self
+            .content
+            .iter()
+            .map(|e| (e.csp_distance(vct, csp), e))
+            .filter(|(d, _)| *d <= HIT_TOLERANCE)
+            .collect();
+        if hits.is_empty() {
+            *skip = 0;
+            return None;
         }
+        hits.sort_by(|a, b| a.0.total_cmp(&b.0));
+        *skip %= hits.len();
+        Some(hits[*skip].1.clone())
     }
 }
@@ -1,64 +1,820 @@
 //! Schematic GUI page
 //! includes paramter editor, toolbar, and the canvas itself
 
+use crate::analysis::expr;
+use crate::analysis::measure::{histogram, MeasureConfig, Measurement, MeasurementSpec, Stats};
 use crate::analysis::plot::{ChartElement, Msg as PlotMsg, Plot};
-use crate::analysis::viewport::Content;
+use crate::analysis::spectrum::{Spectrum, Window};
+use crate::analysis::trace::{family_base, windowed, Trace};
 use crate::analysis::viewport::VCTransformFreeAspect;
+use crate::analysis::waveio;
 use crate::analysis::{plot, viewport};
-use crate::transforms::VSPoint;
 
 use crate::IcedStruct;
-use iced::widget::row;
-use iced::Element;
+use iced::widget::{button, checkbox, column, pick_list, row, scrollable, text, text_input};
+use iced::{Element, Length};
 
 #[derive(Debug, Clone)]
 pub enum PlotPageMsg {
-    ViewportEvt(viewport::CompositeMsg<plot::Msg>),
-    Traces(Vec<Vec<VSPoint>>),
+    /// viewport event of the plot pane at index
+    ViewportEvt(usize, viewport::CompositeMsg<plot::Msg>),
+    /// add an empty plot pane at the bottom of the stack
+    AddPane,
+    /// remove the plot pane at index
+    RemovePane(usize),
+    /// simulation results, one trace per vector
+    Traces(Vec<Trace>),
+    /// add the named simulated vector to the plot
+    AddVector(String),
+    /// add all simulated vectors to the plot
+    AddAllVectors,
+    /// show or hide the named trace of the pane at index
+    TraceVisible(usize, String, bool),
+    /// show only the named trace of the pane at index
+    TraceIsolate(usize, String),
+    /// remove the named trace from the pane at index
+    TraceDelete(usize, String),
+    /// snap the measurement cursors to the named trace
+    CursorTrace(String),
+    /// remove the measurement cursors
+    ClearCursors,
+    /// add a measurement on the cursor trace
+    AddMeasurement(Measurement),
+    /// remove the measurement at index
+    DeleteMeasurement(usize),
+    /// lower rise/fall threshold (percent) edited
+    MeasureLoChanged(String),
+    /// upper rise/fall threshold (percent) edited
+    MeasureHiChanged(String),
+    /// settling band (percent) edited
+    MeasureSettleChanged(String),
+    /// spectrum pane viewport event
+    SpectrumEvt(viewport::CompositeMsg<plot::Msg>),
+    /// FFT window function selected
+    FftWindow(Window),
+    /// FFT fundamental frequency (hertz) edited
+    FftFundamentalChanged(String),
+    /// add the spectrum of the selected trace to the spectrum pane
+    Fft,
+    /// close the spectrum pane
+    CloseSpectrum,
+    /// trace expression edited
+    ExprChanged(String),
+    /// add the trace derived from the edited expression
+    AddExpr,
+    /// waveform file path edited
+    FilePathChanged(String),
+    /// export the visible traces as CSV to the waveform file path
+    ExportCsv,
+    /// import reference waveforms from the waveform file path
+    ImportReference,
+    /// plot the trace expression probed in the schematic, e.g. `v(n1)`
+    Probe(String),
+}
+
+/// distortion and noise figures of the last FFT
+#[derive(Debug, Clone)]
+struct FftSummary {
+    /// name of the transformed trace
+    name: String,
+    /// fundamental frequency (hertz)
+    fundamental: f64,
+    /// total harmonic distortion (percent)
+    thd: Option<f64>,
+    /// signal to noise ratio (dB)
+    snr: Option<f64>,
 }
 
 /// schematic
 pub struct PlotPage {
-    /// viewport
-    viewport: viewport::Viewport<Plot<ChartElement>, plot::Msg>,
+    /// stack of plot panes, sharing the x axis
+    panes: Vec<viewport::Viewport<Plot<ChartElement>, plot::Msg>>,
+    /// index of the pane last interacted with, which the side panel controls
+    active_pane: usize,
+    /// pane index and name of the trace being dragged between panes
+    drag: Option<(usize, String)>,
+    /// all vectors from the last simulation, available to be added to the plot
+    vectors: Vec<Trace>,
+    /// measurements, evaluated on the current traces every time the page is drawn
+    measurements: Vec<MeasurementSpec>,
+    /// measurement thresholds
+    measure_config: MeasureConfig,
+    /// measurement threshold editor text (lower, upper, settling band) in percent
+    measure_text: (String, String, String),
+    /// spectrum pane, shown once an FFT is made
+    spectrum: Option<viewport::Viewport<Plot<ChartElement>, plot::Msg>>,
+    /// FFT window function
+    fft_window: Window,
+    /// FFT fundamental frequency text (hertz), largest non-dc component if empty
+    fft_fundamental: String,
+    /// figures of the last FFT
+    fft_summary: Option<FftSummary>,
+    /// trace expressions, re-evaluated on every simulation run
+    expressions: Vec<String>,
+    /// trace expression editor text
+    expr_text: String,
+    /// error from the last expression added
+    expr_error: Option<String>,
+    /// probed expressions not simulated yet, plotted in the active pane once a run provides their vectors
+    pending_probes: Vec<String>,
+    /// imported reference waveforms, overlaid on every simulation run
+    references: Vec<Trace>,
+    /// waveform file path for export and import
+    file_path: String,
+    /// result of the last export or import
+    file_status: Option<String>,
 }
 impl Default for PlotPage {
     fn default() -> Self {
-        let vct = VCTransformFreeAspect::identity()
-            .pre_flip_y()
-            .then_scale(10.0, 10.0);
         PlotPage {
-            viewport: viewport::Viewport::new(1.0, f32::EPSILON, f32::MAX, vct),
+            panes: vec![new_pane()],
+            active_pane: 0,
+            drag: None,
+            vectors: vec![],
+            measurements: vec![],
+            measure_config: MeasureConfig::default(),
+            measure_text: (String::from("10"), String::from("90"), String::from("2")),
+            spectrum: None,
+            fft_window: Window::default(),
+            fft_fundamental: String::new(),
+            fft_summary: None,
+            expressions: vec![],
+            expr_text: String::new(),
+            expr_error: None,
+            pending_probes: vec![],
+            references: vec![],
+            file_path: String::from("waveforms.csv"),
+            file_status: None,
+        }
+    }
+}
+
+/// number of harmonics considered for THD and SNR
+const FFT_HARMONICS: usize = 10;
+
+/// number of bins of the histogram of a measurement over the runs of an analysis
+const HISTOGRAM_BINS: usize = 10;
+
+/// returns an empty plot pane
+fn new_pane() -> viewport::Viewport<Plot<ChartElement>, plot::Msg> {
+    let vct = VCTransformFreeAspect::identity()
+        .pre_flip_y()
+        .then_scale(10.0, 10.0);
+    viewport::Viewport::new(1.0, f32::EPSILON, f32::MAX, vct)
+}
+
+impl PlotPage {
+    /// FFT of the selected trace (or the cursor trace), between the cursors if both are placed.
+    /// The spectrum is added to the spectrum pane, which is opened if needed.
+    fn fft(&mut self) {
+        let plot = self.plot();
+        let Some(t) = plot
+            .selected_traces()
+            .into_iter()
+            .next()
+            .or_else(|| plot.cursor_trace())
+        else {
+            return;
+        };
+        let t = t.0.borrow();
+        let Some(spectrum) =
+            Spectrum::new(&windowed(&t.points, plot.cursor_window()), self.fft_window)
+        else {
+            return;
+        };
+        let fundamental = match self.fft_fundamental.parse::<f64>() {
+            Ok(hz) => Some(hz),
+            Err(_) => spectrum.peak_hz(),
+        };
+        self.fft_summary = fundamental.map(|hz| FftSummary {
+            name: t.name.clone(),
+            fundamental: hz,
+            thd: spectrum.thd(hz, FFT_HARMONICS),
+            snr: spectrum.snr(hz, FFT_HARMONICS),
+        });
+
+        let mut trace = Trace::new(format!("fft({})", t.name), spectrum.db_points());
+        trace.unit = String::from("dB");
+        trace.x_unit = String::from("Hz");
+        let content_msg = PlotMsg::AddTrace(trace);
+        let vp = self.spectrum.get_or_insert_with(new_pane);
+        vp.update(viewport::CompositeMsg {
+            content_msg,
+            viewport_msg: viewport::Msg::None,
+        });
+    }
+    /// FFT controls and the distortion/noise figures of the last FFT
+    fn fft_panel(&self) -> Element<PlotPageMsg> {
+        let mut panel = column![
+            row![
+                text("fft").width(Length::Fill),
+                button("fft selected").on_press(PlotPageMsg::Fft),
+                button("close").on_press(PlotPageMsg::CloseSpectrum),
+            ]
+            .spacing(4)
+            .align_items(iced::Alignment::Center),
+            row![
+                pick_list(
+                    &Window::ALL[..],
+                    Some(self.fft_window),
+                    PlotPageMsg::FftWindow
+                ),
+                text("f0 (Hz)").size(14),
+                text_input("auto", &self.fft_fundamental)
+                    .size(14)
+                    .on_input(PlotPageMsg::FftFundamentalChanged),
+            ]
+            .spacing(4)
+            .align_items(iced::Alignment::Center),
+        ]
+        .spacing(2);
+        if let Some(summary) = &self.fft_summary {
+            let fmt = |v: Option<f64>| match v {
+                Some(v) => format!("{:.4}", v),
+                None => String::from("-"),
+            };
+            panel = panel.push(
+                text(format!(
+                    "{}: f0 {:.4e} Hz\nTHD {} %  SNR {} dB",
+                    summary.name,
+                    summary.fundamental,
+                    fmt(summary.thd),
+                    fmt(summary.snr)
+                ))
+                .size(14),
+            );
+        }
+        panel.into()
+    }
+    /// measurement results table, over the cursor window if both cursors are placed
+    fn measurement_panel(&self) -> Element<PlotPageMsg> {
+        let borrowed: Vec<_> = self
+            .panes
+            .iter()
+            .flat_map(|p| p.content.traces().iter().map(|t| t.0.borrow()))
+            .collect();
+        let traces: Vec<&Trace> = borrowed.iter().map(|t| &**t).collect();
+        let window = self.plot().cursor_window();
+
+        let mut panel = column![
+            text(if window.is_some() {
+                "measurements (A to B)"
+            } else {
+                "measurements"
+            }),
+            pick_list(&Measurement::ALL[..], None, PlotPageMsg::AddMeasurement)
+                .placeholder("measure cursor trace")
+                .width(Length::Fill),
+            row![
+                text("lo%").size(14),
+                text_input("", &self.measure_text.0)
+                    .size(14)
+                    .on_input(PlotPageMsg::MeasureLoChanged),
+                text("hi%").size(14),
+                text_input("", &self.measure_text.1)
+                    .size(14)
+                    .on_input(PlotPageMsg::MeasureHiChanged),
+                text("settle%").size(14),
+                text_input("", &self.measure_text.2)
+                    .size(14)
+                    .on_input(PlotPageMsg::MeasureSettleChanged),
+            ]
+            .spacing(2)
+            .align_items(iced::Alignment::Center),
+        ]
+        .spacing(2);
+        for (i, m) in self.measurements.iter().enumerate() {
+            let result = match m.evaluate(&traces, window, &self.measure_config) {
+                Some((v, unit)) => format!("{:+.4e} {}", v, unit),
+                None => String::from("-"),
+            };
+            panel = panel.push(
+                row![
+                    text(format!("{} = {}", m, result))
+                        .size(14)
+                        .width(Length::Fill),
+                    button("x").on_press(PlotPageMsg::DeleteMeasurement(i)),
+                ]
+                .spacing(4)
+                .align_items(iced::Alignment::Center),
+            );
+            // over the runs of a sweep, monte carlo or corner analysis: statistics and histogram
+            let values = m.evaluate_family(&traces, window, &self.measure_config);
+            if let Some(stats) = Stats::new(&values).filter(|s| s.n > 1) {
+                panel = panel.push(
+                    text(format!(
+                        "  n = {}, mean = {:+.4e}, σ = {:.4e}\n  min = {:+.4e}, max = {:+.4e}",
+                        stats.n, stats.mean, stats.sigma, stats.min, stats.max
+                    ))
+                    .size(14),
+                );
+                let counts = histogram(&values, HISTOGRAM_BINS);
+                let width = (stats.max - stats.min) / HISTOGRAM_BINS as f32;
+                for (j, c) in counts.iter().enumerate() {
+                    panel = panel.push(
+                        text(format!(
+                            "  {:+.3e} {:>3} {}",
+                            stats.min + width * j as f32,
+                            c,
+                            "█".repeat(*c)
+                        ))
+                        .font(iced::Font::MONOSPACE)
+                        .size(12),
+                    );
+                }
+            }
+        }
+        panel.into()
+    }
+    /// measurement cursor readout: position of each cursor on the cursor trace and their differences
+    fn cursor_panel(&self) -> Element<PlotPageMsg> {
+        let plot = self.plot();
+        let names: Vec<String> = plot
+            .traces()
+            .iter()
+            .map(|t| t.0.borrow().name.clone())
+            .collect();
+        let selected = plot.cursor_trace().map(|t| t.0.borrow().name.clone());
+        let readout = plot.cursor_readout();
+
+        let fmt = |v: Option<f32>| match v {
+            Some(v) => format!("{:+.4e}", v),
+            None => String::from("-"),
+        };
+        let delta = readout.delta();
+        let lines = [
+            format!(
+                "A: x {}  y {}",
+                fmt(readout.a.map(|p| p.x)),
+                fmt(readout.a.map(|p| p.y))
+            ),
+            format!(
+                "B: x {}  y {}",
+                fmt(readout.b.map(|p| p.x)),
+                fmt(readout.b.map(|p| p.y))
+            ),
+            format!(
+                "dx {}  dy {}",
+                fmt(delta.map(|d| d.x)),
+                fmt(delta.map(|d| d.y))
+            ),
+            format!(
+                "1/dx {}  dy/dx {}",
+                fmt(readout.inv_dx()),
+                fmt(readout.slope())
+            ),
+        ];
+
+        let mut panel = column![row![
+            text("cursors (A/B)").width(Length::Fill),
+            button("clear").on_press(PlotPageMsg::ClearCursors),
+        ]
+        .align_items(iced::Alignment::Center)]
+        .spacing(2);
+        panel = panel.push(
+            pick_list(names, selected, PlotPageMsg::CursorTrace)
+                .placeholder("snap to trace")
+                .width(Length::Fill),
+        );
+        for l in lines {
+            panel = panel.push(text(l).size(14));
+        }
+        panel.into()
+    }
+    /// evaluate a trace expression over the simulated vectors
+    fn derive(&self, src: &str) -> Result<Trace, expr::ExprError> {
+        let vectors: Vec<&Trace> = self.vectors.iter().collect();
+        let mut t = Trace::new(src.to_string(), expr::evaluate(src, &vectors)?);
+        t.unit = String::new();
+        t.x_unit = self
+            .vectors
+            .first()
+            .map(|v| v.x_unit.clone())
+            .unwrap_or_default();
+        Ok(t)
+    }
+    /// trace expression editor
+    fn expr_panel(&self) -> Element<PlotPageMsg> {
+        let mut panel = column![row![
+            text_input("expression, e.g. v(out)/v(in)", &self.expr_text)
+                .size(14)
+                .on_input(PlotPageMsg::ExprChanged)
+                .on_submit(PlotPageMsg::AddExpr),
+            button("add").on_press(PlotPageMsg::AddExpr),
+        ]
+        .spacing(4)
+        .align_items(iced::Alignment::Center)]
+        .spacing(2);
+        if let Some(e) = &self.expr_error {
+            panel = panel.push(text(e).size(14).style(iced::Color::from_rgb(1.0, 0.4, 0.4)));
+        }
+        panel.into()
+    }
+    /// waveform file export/import controls
+    fn file_panel(&self) -> Element<PlotPageMsg> {
+        let mut panel = column![
+            text_input("file path", &self.file_path)
+                .size(14)
+                .on_input(PlotPageMsg::FilePathChanged),
+            row![
+                button("export csv").on_press(PlotPageMsg::ExportCsv),
+                button("import reference").on_press(PlotPageMsg::ImportReference),
+            ]
+            .spacing(4),
+        ]
+        .spacing(2);
+        if let Some(status) = &self.file_status {
+            panel = panel.push(text(status).size(14));
+        }
+        panel.into()
+    }
+    /// returns the names of the traces selected in the active pane
+    pub fn selected_trace_names(&self) -> Vec<String> {
+        self.plot()
+            .selected_traces()
+            .iter()
+            .map(|t| t.0.borrow().name.clone())
+            .collect()
+    }
+    /// returns the plot of the active pane
+    fn plot(&self) -> &Plot<ChartElement> {
+        &self.panes[self.active_pane].content
+    }
+    /// pass message to plot content of the active pane
+    fn update_plot(&mut self, content_msg: PlotMsg) {
+        self.update_pane(self.active_pane, content_msg);
+    }
+    /// pass message to plot content of the pane at index
+    fn update_pane(&mut self, i: usize, content_msg: PlotMsg) {
+        if let Some(pane) = self.panes.get_mut(i) {
+            pane.update(viewport::CompositeMsg {
+                content_msg,
+                viewport_msg: viewport::Msg::None,
+            });
+        }
+    }
+    /// share the x axis of the pane at index with all other panes, keeping their own y axis
+    fn sync_x(&mut self, i: usize) {
+        let vct = self.panes[i].vct();
+        for (j, pane) in self.panes.iter_mut().enumerate() {
+            if j != i {
+                let csp = pane.curpos_csp();
+                pane.update(viewport::CompositeMsg {
+                    content_msg: PlotMsg::None,
+                    viewport_msg: viewport::Msg::NewView(pane.vct().with_x_of(&vct), csp),
+                });
+            }
         }
     }
+    /// handle a viewport event of the pane at index: x axis sync and dragging traces between panes
+    fn pane_event(&mut self, i: usize, msgs: viewport::CompositeMsg<PlotMsg>) {
+        if i >= self.panes.len() {
+            return;
+        }
+        self.active_pane = i;
+        let released = matches!(
+            msgs.content_msg,
+            PlotMsg::Event(
+                iced::widget::canvas::Event::Mouse(iced::mouse::Event::ButtonReleased(
+                    iced::mouse::Button::Left
+                )),
+                _
+            )
+        );
+        // drop a trace dragged from another pane
+        if let (true, Some((src, name))) = (released, self.drag.take()) {
+            if src != i {
+                let t = self
+                    .panes
+                    .get(src)
+                    .and_then(|p| p.content.trace_by_name(&name));
+                if let Some(t) = t {
+                    let trace = t.0.borrow().clone();
+                    self.update_pane(src, PlotMsg::Delete(name));
+                    self.update_pane(i, PlotMsg::AddTrace(trace));
+                }
+            }
+            self.update_pane(src, PlotMsg::EndTraceDrag);
+        }
+        let new_view = matches!(msgs.viewport_msg, viewport::Msg::NewView(..));
+        self.panes[i].update(msgs);
+        if new_view {
+            self.sync_x(i);
+        }
+        if let Some(t) = self.panes[i].content.dragged_trace() {
+            self.drag = Some((i, t.0.borrow().name.clone()));
+        }
+    }
+    /// legend listing the plotted traces of each pane, with visibility toggle, isolate and delete for each
+    fn legend(&self) -> Element<PlotPageMsg> {
+        let plotted: Vec<String> = self
+            .plot()
+            .traces()
+            .iter()
+            .map(|t| t.0.borrow().name.clone())
+            .collect();
+        let available: Vec<String> = self
+            .vectors
+            .iter()
+            .map(|t| t.name.clone())
+            .filter(|n| !plotted.contains(n))
+            .collect();
+
+        let mut entries: Vec<Element<PlotPageMsg>> = vec![];
+        for (i, pane) in self.panes.iter().enumerate() {
+            let mut header = row![text(if i == self.active_pane {
+                format!("pane {} (active)", i + 1)
+            } else {
+                format!("pane {}", i + 1)
+            })
+            .width(Length::Fill)]
+            .spacing(4)
+            .align_items(iced::Alignment::Center);
+            if self.panes.len() > 1 {
+                header = header.push(button("x").on_press(PlotPageMsg::RemovePane(i)));
+            }
+            entries.push(header.into());
+            entries.extend(pane.content.traces().iter().map(|t| {
+                let t = t.0.borrow();
+                let name = t.name.clone();
+                let n0 = name.clone();
+                let n1 = name.clone();
+                let n2 = name.clone();
+                row![
+                    checkbox("", t.visible, move |b| PlotPageMsg::TraceVisible(
+                        i,
+                        n0.clone(),
+                        b
+                    )),
+                    text(format!("{} [{}]", name, t.unit))
+                        .style(t.color)
+                        .width(Length::Fill),
+                    button("solo").on_press(PlotPageMsg::TraceIsolate(i, n1)),
+                    button("x").on_press(PlotPageMsg::TraceDelete(i, n2)),
+                ]
+                .spacing(4)
+                .align_items(iced::Alignment::Center)
+                .into()
+            }));
+        }
+
+        column![
+            self.cursor_panel(),
+            self.measurement_panel(),
+            self.fft_panel(),
+            self.expr_panel(),
+            self.file_panel(),
+            row![
+                pick_list(available, None, PlotPageMsg::AddVector)
+                    .placeholder("add vector")
+                    .width(Length::Fill),
+                button("all").on_press(PlotPageMsg::AddAllVectors),
+                button("+ pane").on_press(PlotPageMsg::AddPane),
+            ]
+            .spacing(4),
+            scrollable(iced::widget::Column::with_children(entries).spacing(2)),
+        ]
+        .spacing(6)
+        .width(240)
+        .into()
+    }
 }
 
 impl IcedStruct<PlotPageMsg> for PlotPage {
     fn update(&mut self, msg: PlotPageMsg) {
         match msg {
-            PlotPageMsg::ViewportEvt(msgs) => {
-                self.viewport.update(msgs);
+            PlotPageMsg::ViewportEvt(i, msgs) => self.pane_event(i, msgs),
+            PlotPageMsg::AddPane => {
+                let mut pane = new_pane();
+                let vct = pane.vct().with_x_of(&self.panes[0].vct());
+                pane.update(viewport::CompositeMsg {
+                    content_msg: PlotMsg::None,
+                    viewport_msg: viewport::Msg::NewView(vct, pane.curpos_csp()),
+                });
+                self.panes.push(pane);
+                self.active_pane = self.panes.len() - 1;
+            }
+            PlotPageMsg::RemovePane(i) => {
+                if self.panes.len() > 1 && i < self.panes.len() {
+                    self.panes.remove(i);
+                    self.active_pane = self.active_pane.min(self.panes.len() - 1);
+                    self.drag = None;
+                }
             }
             PlotPageMsg::Traces(traces) => {
-                let content_msg = PlotMsg::Traces(traces);
-                self.viewport.content.update(content_msg);
+                // keep plotting the same vectors in each pane across runs, all of them in the first pane if none were chosen
+                let mut plotted: Vec<Vec<String>> = self
+                    .panes
+                    .iter()
+                    .map(|p| {
+                        p.content
+                            .traces()
+                            .iter()
+                            .map(|t| t.0.borrow().name.clone())
+                            .collect()
+                    })
+                    .collect();
+                let none_plotted = plotted.iter().all(|p| p.is_empty());
+                self.vectors = traces;
+                self.vectors.extend(self.references.iter().cloned());
+                // derived traces are kept across runs, expressions which no longer evaluate are dropped
+                let derived: Vec<Trace> = self
+                    .expressions
+                    .iter()
+                    .filter_map(|src| self.derive(src).ok())
+                    .collect();
+                self.vectors.extend(derived);
+                let vectors = &self.vectors;
+                let (ready, pending): (Vec<String>, Vec<String>) = self
+                    .pending_probes
+                    .drain(..)
+                    .partition(|src| vectors.iter().any(|t| t.name == *src));
+                self.pending_probes = pending;
+                plotted[self.active_pane].extend(ready);
+                for (i, names) in plotted.iter().enumerate() {
+                    // a vector plotted from one run of a sweep is plotted from every run, and vice versa
+                    let shown: Vec<Trace> = self
+                        .vectors
+                        .iter()
+                        .filter(|t| {
+                            (none_plotted && i == 0)
+                                || names.iter().any(|n| family_base(n) == family_base(&t.name))
+                        })
+                        .cloned()
+                        .collect();
+                    self.update_pane(i, PlotMsg::Traces(shown));
+                }
+            }
+            PlotPageMsg::AddVector(name) => {
+                if let Some(t) = self.vectors.iter().find(|t| t.name == name).cloned() {
+                    self.update_plot(PlotMsg::AddTrace(t));
+                }
+            }
+            PlotPageMsg::AddAllVectors => {
+                self.update_plot(PlotMsg::Traces(self.vectors.clone()));
+            }
+            PlotPageMsg::TraceVisible(i, name, visible) => {
+                self.update_pane(i, PlotMsg::SetVisible(name, visible));
+            }
+            PlotPageMsg::TraceIsolate(i, name) => {
+                self.update_pane(i, PlotMsg::Isolate(name));
+            }
+            PlotPageMsg::TraceDelete(i, name) => {
+                self.update_pane(i, PlotMsg::Delete(name));
+            }
+            PlotPageMsg::CursorTrace(name) => {
+                self.update_plot(PlotMsg::CursorTrace(name));
+            }
+            PlotPageMsg::ClearCursors => {
+                self.update_plot(PlotMsg::ClearCursors);
+            }
+            PlotPageMsg::AddMeasurement(kind) => {
+                let plot = self.plot();
+                if let Some(t) = plot.cursor_trace() {
+                    let trace = t.0.borrow().name.clone();
+                    // measurements between two traces use another selected trace as reference
+                    let reference = plot
+                        .selected_traces()
+                        .iter()
+                        .map(|t| t.0.borrow().name.clone())
+                        .find(|n| *n != trace);
+                    if !kind.needs_reference() || reference.is_some() {
+                        self.measurements.push(MeasurementSpec {
+                            kind,
+                            trace,
+                            reference: reference.filter(|_| kind.needs_reference()),
+                        });
+                    }
+                }
+            }
+            PlotPageMsg::DeleteMeasurement(i) => {
+                if i < self.measurements.len() {
+                    self.measurements.remove(i);
+                }
+            }
+            PlotPageMsg::MeasureLoChanged(s) => {
+                if let Ok(v) = s.parse::<f32>() {
+                    self.measure_config.lo = v / 100.0;
+                }
+                self.measure_text.0 = s;
+            }
+            PlotPageMsg::MeasureHiChanged(s) => {
+                if let Ok(v) = s.parse::<f32>() {
+                    self.measure_config.hi = v / 100.0;
+                }
+                self.measure_text.1 = s;
+            }
+            PlotPageMsg::SpectrumEvt(msgs) => {
+                if let Some(vp) = &mut self.spectrum {
+                    vp.update(msgs);
+                }
+            }
+            PlotPageMsg::FftWindow(w) => self.fft_window = w,
+            PlotPageMsg::FftFundamentalChanged(s) => self.fft_fundamental = s,
+            PlotPageMsg::Fft => self.fft(),
+            PlotPageMsg::ExprChanged(s) => self.expr_text = s,
+            PlotPageMsg::FilePathChanged(s) => self.file_path = s,
+            PlotPageMsg::ExportCsv => {
+                let borrowed: Vec<_> = self
+                    .panes
+                    .iter()
+                    .flat_map(|p| p.content.traces().iter().map(|t| t.0.borrow()))
+                    .filter(|t| t.visible)
+                    .collect();
+                let traces: Vec<&Trace> = borrowed.iter().map(|t| &**t).collect();
+                self.file_status = Some(
+                    match waveio::export(std::path::Path::new(&self.file_path), &traces) {
+                        Ok(()) => format!("exported {} traces", traces.len()),
+                        Err(e) => format!("export failed: {}", e),
+                    },
+                );
+            }
+            PlotPageMsg::ImportReference => {
+                match waveio::import(std::path::Path::new(&self.file_path)) {
+                    Ok(traces) => {
+                        self.file_status = Some(format!("imported {} traces", traces.len()));
+                        for t in traces {
+                            self.references.retain(|r| r.name != t.name);
+                            self.references.push(t.clone());
+                            self.vectors.retain(|v| v.name != t.name);
+                            self.vectors.push(t.clone());
+                            self.update_plot(PlotMsg::AddTrace(t));
+                        }
+                    }
+                    Err(e) => self.file_status = Some(format!("import failed: {}", e)),
+                }
+            }
+            PlotPageMsg::AddExpr => {
+                let src = self.expr_text.trim().to_string();
+                match self.derive(&src) {
+                    Ok(t) => {
+                        self.expr_error = None;
+                        self.vectors.retain(|v| v.name != src);
+                        self.vectors.push(t.clone());
+                        if !self.expressions.contains(&src) {
+                            self.expressions.push(src);
+                        }
+                        self.update_plot(PlotMsg::AddTrace(t));
+                    }
+                    Err(e) => self.expr_error = Some(e.to_string()),
+                }
+            }
+            PlotPageMsg::Probe(src) => {
+                if !self.expressions.contains(&src) {
+                    self.expressions.push(src.clone());
+                }
+                match self.derive(&src) {
+                    Ok(t) => {
+                        self.vectors.retain(|v| v.name != src);
+                        self.vectors.push(t.clone());
+                        self.update_plot(PlotMsg::AddTrace(t));
+                    }
+                    // e.g. a device current, which is only saved from the next run on
+                    Err(_) => {
+                        if !self.pending_probes.contains(&src) {
+                            self.pending_probes.push(src);
+                        }
+                    }
+                }
+            }
+            PlotPageMsg::CloseSpectrum => {
+                self.spectrum = None;
+                self.fft_summary = None;
+            }
+            PlotPageMsg::MeasureSettleChanged(s) => {
+                if let Ok(v) = s.parse::<f32>() {
+                    self.measure_config.settle = v / 100.0;
+                }
+                self.measure_text.2 = s;
             }
         }
     }
 
     fn view(&self) -> Element<PlotPageMsg> {
+        let active = &self.panes[self.active_pane];
         let str_ssp = format!(
             "curpos: x: {:.2e}; y: {:.2e}",
-            self.viewport.curpos_vsp().x,
-            self.viewport.curpos_vsp().y
+            active.curpos_vsp().x,
+            active.curpos_vsp().y
         );
         let str_xyscales = format!(
             "scale: x: {:.2e}; y: {:.2e}",
-            self.viewport.vct().x_scale(),
-            self.viewport.vct().y_scale(),
+            active.vct().x_scale(),
+            active.vct().y_scale(),
         );
 
-        let canvas = self.viewport.view().map(PlotPageMsg::ViewportEvt);
+        let str_selected = active
+            .content
+            .selected_traces()
+            .iter()
+            .map(|t| t.0.borrow().name.clone())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let str_hover = match active.content.hover_readout() {
+            Some((name, vsp)) => format!("{}: x: {:.3e}; y: {:.3e}", name, vsp.x, vsp.y),
+            None => String::new(),
+        };
+
         let infobar = row![
             iced::widget::text(str_ssp)
                 .size(16)
@@ -68,11 +824,30 @@ impl IcedStruct<PlotPageMsg> for PlotPage {
                 .size(16)
                 .height(16)
                 .vertical_alignment(iced::alignment::Vertical::Center),
+            iced::widget::text(str_hover)
+                .size(16)
+                .height(16)
+                .vertical_alignment(iced::alignment::Vertical::Center),
+            iced::widget::text(format!("selected: {}", str_selected))
+                .size(16)
+                .height(16)
+                .vertical_alignment(iced::alignment::Vertical::Center),
         ]
         .spacing(10);
 
-        let schematic = iced::widget::column![canvas, infobar,];
+        let mut schematic = iced::widget::Column::with_children(
+            self.panes
+                .iter()
+                .enumerate()
+                .map(|(i, pane)| pane.view().map(move |m| PlotPageMsg::ViewportEvt(i, m)))
+                .collect(),
+        )
+        .spacing(2);
+        if let Some(vp) = &self.spectrum {
+            schematic = schematic.push(vp.view().map(PlotPageMsg::SpectrumEvt));
+        }
+        let schematic = schematic.push(infobar);
 
-        schematic.into()
+        row![schematic, self.legend()].spacing(6).into()
     }
 }
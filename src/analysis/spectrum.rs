@@ -0,0 +1,320 @@
+//! spectrum
+//! FFT of transient traces, with windowing and distortion/noise figures
+
+use crate::analysis::trace::interpolate;
+use crate::transforms::VSPoint;
+use num::Complex;
+
+/// upper bound on the number of FFT points
+const MAX_POINTS: usize = 1 << 16;
+
+/// window function applied to the samples before the FFT
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Window {
+    Rectangular,
+    #[default]
+    Hann,
+    Blackman,
+}
+
+impl Window {
+    pub const ALL: [Window; 3] = [Window::Rectangular, Window::Hann, Window::Blackman];
+    /// returns the window coefficient for sample i of n
+    fn coefficient(&self, i: usize, n: usize) -> f64 {
+        let a = 2.0 * std::f64::consts::PI * i as f64 / n as f64;
+        match self {
+            Window::Rectangular => 1.0,
+            Window::Hann => 0.5 - 0.5 * a.cos(),
+            Window::Blackman => 0.42 - 0.5 * a.cos() + 0.08 * (2.0 * a).cos(),
+        }
+    }
+    /// half width of the window's main lobe in bins - tones spread over this many bins to either side
+    fn half_lobe(&self) -> usize {
+        match self {
+            Window::Rectangular => 1,
+            Window::Hann => 2,
+            Window::Blackman => 3,
+        }
+    }
+}
+
+impl std::fmt::Display for Window {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Window::Rectangular => "rectangular",
+            Window::Hann => "hann",
+            Window::Blackman => "blackman",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// single sided amplitude spectrum
+#[derive(Debug, Clone)]
+pub struct Spectrum {
+    /// frequency resolution (Hz per bin)
+    pub bin_hz: f64,
+    /// amplitude of each bin, corrected for the window's coherent gain
+    pub amplitude: Vec<f64>,
+    /// window used, determines how far tones leak into neighbouring bins
+    window: Window,
+}
+
+impl Spectrum {
+    /// resample pts onto a uniform grid (ngspice time steps are not uniform), apply window and compute the spectrum.
+    /// Returns None if there are too few points or the x span is empty.
+    pub fn new(pts: &[VSPoint], window: Window) -> Option<Self> {
+        let (x0, x1) = (pts.first()?.x as f64, pts.last()?.x as f64);
+        let span = x1 - x0;
+        if pts.len() < 4 || span <= 0.0 {
+            return None;
+        }
+        let n = pts.len().next_power_of_two().min(MAX_POINTS);
+        let dt = span / n as f64;
+
+        let mut gain = 0.0;
+        let mut buf: Vec<Complex<f64>> = (0..n)
+            .map(|i| {
+                let x = x0 + dt * i as f64;
+                let y = interpolate(pts, x as f32).unwrap_or_default() as f64;
+                let w = window.coefficient(i, n);
+                gain += w;
+                Complex::new(y * w, 0.0)
+            })
+            .collect();
+        fft(&mut buf);
+
+        let amplitude = buf[..n / 2 + 1]
+            .iter()
+            .enumerate()
+            .map(|(k, c)| {
+                // single sided: double every bin except dc and nyquist
+                let scale = if k == 0 || k == n / 2 { 1.0 } else { 2.0 };
+                c.norm() * scale / gain
+            })
+            .collect();
+        Some(Spectrum {
+            bin_hz: 1.0 / (dt * n as f64),
+            amplitude,
+            window,
+        })
+    }
+    /// returns the spectrum as (frequency, magnitude in dB) points
+    pub fn db_points(&self) -> Vec<VSPoint> {
+        self.amplitude
+            .iter()
+            .enumerate()
+            .map(|(k, a)| {
+                VSPoint::new(
+                    (k as f64 * self.bin_hz) as f32,
+                    (20.0 * a.max(1e-15).log10()) as f32,
+                )
+            })
+            .collect()
+    }
+    /// returns the frequency of the largest non-dc component
+    pub fn peak_hz(&self) -> Option<f64> {
+        let skip = self.window.half_lobe() + 1;
+        let (k, _) = self
+            .amplitude
+            .iter()
+            .enumerate()
+            .skip(skip)
+            .max_by(|a, b| a.1.total_cmp(b.1))?;
+        Some(k as f64 * self.bin_hz)
+    }
+    /// returns the bin range holding the tone nearest to hz: the largest bin near hz, plus leakage to either side
+    fn tone_bins(&self, hz: f64) -> Option<std::ops::RangeInclusive<usize>> {
+        let w = self.window.half_lobe();
+        let k = (hz / self.bin_hz).round() as usize;
+        if k == 0 || k >= self.amplitude.len() {
+            return None;
+        }
+        let search = k.saturating_sub(w)..=(k + w).min(self.amplitude.len() - 1);
+        let peak = search.max_by(|a, b| self.amplitude[*a].total_cmp(&self.amplitude[*b]))?;
+        Some(peak.saturating_sub(w).max(1)..=(peak + w).min(self.amplitude.len() - 1))
+    }
+    /// returns the power in a range of bins
+    fn power(&self, bins: std::ops::RangeInclusive<usize>) -> f64 {
+        self.amplitude[bins].iter().map(|a| a * a / 2.0).sum()
+    }
+    /// total harmonic distortion in percent, over the harmonics of fundamental_hz up to nyquist
+    pub fn thd(&self, fundamental_hz: f64, harmonics: usize) -> Option<f64> {
+        let p1 = self.power(self.tone_bins(fundamental_hz)?);
+        let ph: f64 = (2..=harmonics)
+            .filter_map(|h| self.tone_bins(fundamental_hz * h as f64))
+            .map(|bins| self.power(bins))
+            .sum();
+        Some((ph / p1).sqrt() * 100.0)
+    }
+    /// signal to noise ratio in dB: fundamental power over all other power excluding dc and harmonics
+    pub fn snr(&self, fundamental_hz: f64, harmonics: usize) -> Option<f64> {
+        let fund = self.tone_bins(fundamental_hz)?;
+        let mut excluded = vec![false; self.amplitude.len()];
+        excluded[..=self.window.half_lobe()].fill(true);
+        for h in 1..=harmonics {
+            if let Some(bins) = self.tone_bins(fundamental_hz * h as f64) {
+                excluded[bins].fill(true);
+            }
+        }
+        let p1 = self.power(fund);
+        let pn: f64 = self
+            .amplitude
+            .iter()
+            .zip(excluded)
+            .filter(|(_, ex)| !ex)
+            .map(|(a, _)| a * a / 2.0)
+            .sum();
+        if pn <= 0.0 {
+            return None;
+        }
+        Some(10.0 * (p1 / pn).log10())
+    }
+}
+
+/// instantaneous phase of pts in degrees, from its analytic signal: the phase of cos(wt) is wt, wrapped to ±180.
+/// The dc component is removed first. Like Spectrum, pts is resampled onto a uniform grid for the FFT,
+/// so the phase near the ends is only accurate over whole periods.
+pub fn phase(pts: &[VSPoint]) -> Vec<VSPoint> {
+    let (Some(first), Some(last)) = (pts.first(), pts.last()) else {
+        return vec![];
+    };
+    let (x0, span) = (first.x as f64, (last.x - first.x) as f64);
+    if pts.len() < 4 || span <= 0.0 {
+        return vec![];
+    }
+    let n = pts.len().next_power_of_two().min(MAX_POINTS);
+    let dt = span / n as f64;
+    let mut buf: Vec<Complex<f64>> = (0..n)
+        .map(|i| {
+            let y = interpolate(pts, (x0 + dt * i as f64) as f32).unwrap_or_default();
+            Complex::new(y as f64, 0.0)
+        })
+        .collect();
+    fft(&mut buf);
+    // analytic signal: no dc, positive frequencies doubled, negative frequencies removed
+    buf[0] = Complex::default();
+    buf[1..n / 2].iter_mut().for_each(|c| *c *= 2.0);
+    buf[n / 2 + 1..].fill(Complex::default());
+    // inverse FFT by conjugation, without the 1/n scale which does not change the phase
+    buf.iter_mut().for_each(|c| *c = c.conj());
+    fft(&mut buf);
+
+    // unwrap the phase on the grid, so that interpolating between grid points does not cross a wrap
+    let mut unwrapped = Vec::with_capacity(n + 1);
+    let mut prev = 0.0;
+    let mut turns = 0.0;
+    for (i, c) in buf.iter().enumerate() {
+        let ph = (-c.im).atan2(c.re).to_degrees();
+        if i > 0 && ph - prev > 180.0 {
+            turns -= 360.0;
+        } else if i > 0 && prev - ph > 180.0 {
+            turns += 360.0;
+        }
+        prev = ph;
+        unwrapped.push(VSPoint::new(
+            (x0 + dt * i as f64) as f32,
+            (ph + turns) as f32,
+        ));
+    }
+    // the grid ends a step before the last point: extend it by a step
+    let end = 2.0 * unwrapped[n - 1].y - unwrapped[n - 2].y;
+    unwrapped.push(VSPoint::new(last.x, end));
+    pts.iter()
+        .map(|p| {
+            let ph = interpolate(&unwrapped, p.x).unwrap_or_default();
+            VSPoint::new(p.x, (ph + 180.0).rem_euclid(360.0) - 180.0)
+        })
+        .collect()
+}
+
+/// in place iterative radix-2 FFT. buf length must be a power of two.
+fn fft(buf: &mut [Complex<f64>]) {
+    let n = buf.len();
+    // bit reversal permutation
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            buf.swap(i, j);
+        }
+    }
+    // butterflies
+    let mut len = 2;
+    while len <= n {
+        let w_len = Complex::from_polar(1.0, -2.0 * std::f64::consts::PI / len as f64);
+        for chunk in buf.chunks_mut(len) {
+            let mut w = Complex::new(1.0, 0.0);
+            let (lo, hi) = chunk.split_at_mut(len / 2);
+            for (a, b) in lo.iter_mut().zip(hi.iter_mut()) {
+                let t = *b * w;
+                *b = *a - t;
+                *a += t;
+                w *= w_len;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    /// one second of f, sampled 1024 times: the spectrum has 1 Hz bins
+    fn sample(f: impl Fn(f32) -> f32) -> Vec<VSPoint> {
+        (0..=1024)
+            .map(|i| {
+                let x = i as f32 / 1024.0;
+                VSPoint::new(x, f(x))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn pure_sine() {
+        let pts = sample(|x| 0.5 + (2.0 * PI * 8.0 * x).sin());
+        for window in Window::ALL {
+            let spectrum = Spectrum::new(&pts, window).unwrap();
+            assert_eq!(spectrum.bin_hz, 1.0);
+            assert_eq!(spectrum.peak_hz(), Some(8.0));
+            assert!((spectrum.amplitude[8] - 1.0).abs() < 1e-2, "{}", window);
+            assert!(spectrum.thd(8.0, 5).unwrap() < 0.1, "{}", window);
+        }
+    }
+
+    /// angle between phases a and b in degrees
+    fn angle(a: f32, b: f32) -> f32 {
+        let d = (a - b).rem_euclid(360.0);
+        d.min(360.0 - d)
+    }
+
+    #[test]
+    fn phase_of_sines() {
+        let cos = sample(|x| 0.5 + (2.0 * PI * 8.0 * x).cos());
+        for p in phase(&cos) {
+            assert!(angle(p.y, 360.0 * 8.0 * p.x) < 1.0, "{:?}", p);
+        }
+        let sin = sample(|x| (2.0 * PI * 8.0 * x).sin());
+        for p in phase(&sin) {
+            assert!(angle(p.y, 360.0 * 8.0 * p.x - 90.0) < 1.0, "{:?}", p);
+        }
+    }
+
+    #[test]
+    fn second_harmonic() {
+        let pts = sample(|x| (2.0 * PI * 8.0 * x).sin() + 0.1 * (2.0 * PI * 16.0 * x).sin());
+        for window in Window::ALL {
+            let spectrum = Spectrum::new(&pts, window).unwrap();
+            assert_eq!(spectrum.peak_hz(), Some(8.0));
+            let thd = spectrum.thd(8.0, 5).unwrap();
+            assert!((thd - 10.0).abs() < 0.1, "{}: {}", window, thd);
+        }
+    }
+}
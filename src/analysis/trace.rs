@@ -0,0 +1,249 @@
+//! trace
+//! Named simulation vectors as drawn on the plot
+
+use crate::analysis::lod::Lod;
+use crate::transforms::{CSPoint, VCTransform, VSBox, VSPoint};
+use by_address::ByAddress;
+use iced::Color;
+use std::{cell::RefCell, hash::Hasher, rc::Rc};
+
+/// palette from which trace colors are assigned, in order
+pub const PALETTE: [Color; 8] = [
+    Color::from_rgb(0.98, 0.75, 0.18),
+    Color::from_rgb(0.30, 0.69, 0.96),
+    Color::from_rgb(0.96, 0.36, 0.36),
+    Color::from_rgb(0.45, 0.86, 0.45),
+    Color::from_rgb(0.80, 0.52, 0.96),
+    Color::from_rgb(0.31, 0.90, 0.85),
+    Color::from_rgb(0.98, 0.56, 0.80),
+    Color::from_rgb(0.85, 0.85, 0.85),
+];
+
+/// a named vector of simulation results, with x values taken from the scale vector
+#[derive(Debug, Clone)]
+pub struct Trace {
+    /// ngspice vector name, e.g. `v(out)`
+    pub name: String,
+    /// unit of the y values, e.g. `V`
+    pub unit: String,
+    /// unit of the x values, i.e. of the scale vector, e.g. `s`
+    pub x_unit: String,
+    /// color the trace is drawn in
+    pub color: Color,
+    /// hidden traces are kept in the legend but not drawn
+    pub visible: bool,
+    /// trace data points (scale value, vector value)
+    pub points: Vec<VSPoint>,
+    /// decimated points for drawing, per zoom level
+    lod: Lod,
+}
+
+impl Trace {
+    /// creates a new visible trace. The unit is inferred from the ngspice vector name.
+    pub fn new(name: String, points: Vec<VSPoint>) -> Self {
+        Trace {
+            unit: unit_from_name(&name).to_string(),
+            x_unit: String::new(),
+            name,
+            color: PALETTE[0],
+            visible: true,
+            points,
+            lod: Lod::default(),
+        }
+    }
+    /// returns the linearly interpolated value of the trace at x, if x is within the trace's x range.
+    /// Assumes points are sorted by x, as is the case for simulation results.
+    pub fn value_at(&self, x: f32) -> Option<f32> {
+        interpolate(&self.points, x)
+    }
+    /// returns the data point with x value nearest to x, if any
+    pub fn nearest_point(&self, x: f32) -> Option<VSPoint> {
+        let i = self.points.partition_point(|p| p.x < x);
+        let after = self.points.get(i);
+        let before = i.checked_sub(1).and_then(|i| self.points.get(i));
+        match (before, after) {
+            (Some(p0), Some(p1)) => {
+                if x - p0.x <= p1.x - x {
+                    Some(*p0)
+                } else {
+                    Some(*p1)
+                }
+            }
+            (Some(p), None) | (None, Some(p)) => Some(*p),
+            (None, None) => None,
+        }
+    }
+    /// returns the points to draw between canvas x positions csx0 and csx1, decimated to the zoom level of vct
+    pub fn lod_points(&self, vct: VCTransform, csx0: f32, csx1: f32) -> Vec<VSPoint> {
+        if vct.m11 == 0.0 {
+            return vec![];
+        }
+        let x = |csx: f32| (csx - vct.m31) / vct.m11;
+        self.lod
+            .points(&self.points, vct.m11.abs(), x(csx0), x(csx1))
+    }
+    /// returns the shortest distance in canvas space between csp and the trace polyline
+    pub fn csp_distance(&self, vct: VCTransform, csp: CSPoint) -> f32 {
+        // only the part of the trace near csp can be within hit tolerance
+        let lod = self.lod_points(vct, csp.x - 16.0, csp.x + 16.0);
+        let mut pts = lod.iter().map(|vsp| vct.transform_point(*vsp));
+        let Some(mut prev) = pts.next() else {
+            return f32::INFINITY;
+        };
+        let mut dist = (csp - prev).length();
+        for this in pts {
+            let seg = this - prev;
+            let len2 = seg.square_length();
+            let t = if len2 > 0.0 {
+                ((csp - prev).dot(seg) / len2).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            dist = dist.min((csp - (prev + seg * t)).length());
+            prev = this;
+        }
+        dist
+    }
+    /// returns true if any part of the trace polyline lies inside vsb
+    pub fn intersects_vsb(&self, vsb: &VSBox) -> bool {
+        if self.points.len() == 1 {
+            return vsb.contains(self.points[0]);
+        }
+        self.points
+            .windows(2)
+            .any(|w| segment_intersects_vsb(w[0], w[1], vsb))
+    }
+}
+
+/// returns the linearly interpolated value of pts at x, if x is within the x range of pts. pts must be sorted by x.
+pub fn interpolate(pts: &[VSPoint], x: f32) -> Option<f32> {
+    if x < pts.first()?.x || x > pts.last()?.x {
+        return None;
+    }
+    let i = pts.partition_point(|p| p.x < x);
+    if i == 0 {
+        return Some(pts[0].y);
+    }
+    let (p0, p1) = (pts[i - 1], pts[i]);
+    // exact at data points, e.g. for export
+    if p1.x == x || p1.x == p0.x {
+        return Some(p1.y);
+    }
+    Some(p0.y + (p1.y - p0.y) * (x - p0.x) / (p1.x - p0.x))
+}
+
+/// returns the points within window, with the window edges interpolated onto the trace
+pub fn windowed(pts: &[VSPoint], window: Option<(f32, f32)>) -> Vec<VSPoint> {
+    let Some((x0, x1)) = window else {
+        return pts.to_vec();
+    };
+    let (x0, x1) = (x0.min(x1), x0.max(x1));
+    let mut ret = Vec::with_capacity(pts.len());
+    if let Some(y) = interpolate(pts, x0) {
+        ret.push(VSPoint::new(x0, y));
+    }
+    ret.extend(pts.iter().filter(|p| p.x > x0 && p.x < x1));
+    if let Some(y) = interpolate(pts, x1) {
+        ret.push(VSPoint::new(x1, y));
+    }
+    ret
+}
+
+/// returns the points of a digital waveform drawn as steps, from the (x, level) of its value changes, the last level held until end
+pub fn digital_points(changes: &[(f32, f32)], end: f32) -> Vec<VSPoint> {
+    let mut ret: Vec<VSPoint> = Vec::with_capacity(changes.len() * 2 + 1);
+    for (x, level) in changes {
+        if let Some(prev) = ret.last().copied() {
+            ret.push(VSPoint::new(*x, prev.y));
+        }
+        ret.push(VSPoint::new(*x, *level));
+    }
+    if let Some(last) = ret.last().copied() {
+        if last.x < end {
+            ret.push(VSPoint::new(end, last.y));
+        }
+    }
+    ret
+}
+
+/// returns true if the line segment from p0 to p1 intersects vsb (Liang-Barsky clipping)
+fn segment_intersects_vsb(p0: VSPoint, p1: VSPoint, vsb: &VSBox) -> bool {
+    let (min, max) = (vsb.min.min(vsb.max), vsb.min.max(vsb.max));
+    let d = p1 - p0;
+    let mut t0 = 0.0f32;
+    let mut t1 = 1.0f32;
+    for (p, q) in [
+        (-d.x, p0.x - min.x),
+        (d.x, max.x - p0.x),
+        (-d.y, p0.y - min.y),
+        (d.y, max.y - p0.y),
+    ] {
+        if p == 0.0 {
+            if q < 0.0 {
+                return false;
+            }
+        } else {
+            let r = q / p;
+            if p < 0.0 {
+                t0 = t0.max(r);
+            } else {
+                t1 = t1.min(r);
+            }
+            if t0 > t1 {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+/// returns the name of a trace of one run of a sweep, labeled by the sweep value, e.g. `n1 (r1=2k)`
+pub fn family_name(name: &str, label: &str) -> String {
+    format!("{} ({})", name, label)
+}
+
+/// returns the name of the vector a trace of a sweep run was named after, or the name itself if not from a sweep
+pub fn family_base(name: &str) -> &str {
+    match name.rsplit_once(" (") {
+        Some((base, label)) if label.ends_with(')') && label.contains('=') => base,
+        _ => name,
+    }
+}
+
+/// returns the unit of a vector based on ngspice naming conventions
+pub fn unit_from_name(name: &str) -> &'static str {
+    let lname = name.to_lowercase();
+    if lname == "time" {
+        "s"
+    } else if lname == "frequency" {
+        "Hz"
+    } else if lname.ends_with("#branch") || lname.starts_with("i(") || lname.contains("[i") {
+        "A"
+    } else if lname.contains("[p]") {
+        "W"
+    } else {
+        "V"
+    }
+}
+
+/// newtype wrapper for `Rc<RefCell<Trace>>`. Hashes by memory address.
+#[derive(Debug, Clone)]
+pub struct RcRTrace(pub Rc<RefCell<Trace>>);
+
+impl RcRTrace {
+    pub fn new(trace: Trace) -> Self {
+        RcRTrace(Rc::new(RefCell::new(trace)))
+    }
+}
+
+impl PartialEq for RcRTrace {
+    fn eq(&self, other: &Self) -> bool {
+        ByAddress(self.0.clone()) == ByAddress(other.0.clone())
+    }
+}
+impl Eq for RcRTrace {}
+impl std::hash::Hash for RcRTrace {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        ByAddress(self.0.clone()).hash(state);
+    }
+}
@@ -72,6 +72,11 @@ impl VCTransformFreeAspect {
 
         Self(vct)
     }
+    /// returns this transform with the x scale and translation of other, e.g. to share the x axis between viewports
+    pub fn with_x_of(&self, other: &Self) -> Self {
+        let (s, o) = (self.0, other.0);
+        Self(VCTransform::new(o.m11, s.m12, s.m21, s.m22, o.m31, s.m32))
+    }
     /// return the underlying transform
     pub fn transform(&self) -> VCTransform {
         self.0
@@ -128,6 +133,8 @@ pub trait Content<Msg>: Default {
     fn draw_passive(&self, vct: VCTransform, frame: &mut Frame);
     /// returns the bounding box of all elements in content
     fn bounds(&self) -> VSBox;
+    /// update the viewport to canvas transform known to content, e.g. for hit testing in pixels
+    fn vct_update(&mut self, vct: VCTransform);
 }
 
 /// trait for message type of viewport content
@@ -280,6 +287,7 @@ where
         match msgs.viewport_msg {
             Msg::NewView(vct, curpos_csp) => {
                 self.vct = vct;
+                self.content.vct_update(vct.transform());
                 // update cursor position, otherwise it is displayed according to old vct until cursor is moved again
                 self.curpos_update(curpos_csp);
                 self.passive_cache.clear();
@@ -308,12 +316,14 @@ where
     M: ContentMsg,
 {
     pub fn new(scale: f32, min_zoom: f32, max_zoom: f32, vct: VCTransformFreeAspect) -> Self {
+        let mut content = C::default();
+        content.vct_update(vct.transform());
         Viewport {
             scale,
             min_zoom,
             max_zoom,
             vct,
-            content: C::default(),
+            content,
             active_cache: Default::default(),
             passive_cache: Default::default(),
             background_cache: Default::default(),
@@ -0,0 +1,462 @@
+//! waveio
+//! Reading and writing waveforms as text: CSV export of plotted traces, and import of reference waveforms
+//! from CSV, ngspice `wrdata` output or ngspice raw files
+
+use crate::analysis::trace::{interpolate, unit_from_name, Trace};
+use crate::transforms::VSPoint;
+
+/// prefix given to the names of imported reference traces, so they are told apart from simulated vectors
+pub const REFERENCE_PREFIX: &str = "ref:";
+
+/// error reading or writing a waveform file
+#[derive(Debug)]
+pub enum WaveIoError {
+    Io(std::io::Error),
+    Raw(paprika::PkRawError),
+    /// malformed content at line (1-based)
+    Parse {
+        line: usize,
+        msg: String,
+    },
+    /// the file holds no waveform data
+    Empty,
+}
+
+impl std::fmt::Display for WaveIoError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WaveIoError::Io(e) => write!(f, "{}", e),
+            WaveIoError::Raw(e) => write!(f, "{}", e),
+            WaveIoError::Parse { line, msg } => write!(f, "line {}: {}", line, msg),
+            WaveIoError::Empty => write!(f, "no waveform data"),
+        }
+    }
+}
+
+impl From<std::io::Error> for WaveIoError {
+    fn from(e: std::io::Error) -> Self {
+        WaveIoError::Io(e)
+    }
+}
+
+/// returns the name of the scale column for traces with x values in x_unit
+fn scale_name(x_unit: &str) -> &'static str {
+    match x_unit {
+        "s" => "time",
+        "Hz" => "frequency",
+        _ => "x",
+    }
+}
+
+/// returns the unit of the x values given the name of the scale column
+fn scale_unit(name: &str) -> &'static str {
+    match name.to_lowercase().as_str() {
+        "time" => "s",
+        "frequency" => "Hz",
+        _ => "",
+    }
+}
+
+/// returns the sorted union of the x values of traces
+fn shared_scale(traces: &[&Trace]) -> Vec<f32> {
+    let mut xs: Vec<f32> = traces
+        .iter()
+        .flat_map(|t| t.points.iter().map(|p| p.x))
+        .collect();
+    xs.sort_by(f32::total_cmp);
+    xs.dedup();
+    xs
+}
+
+/// write traces as CSV: a header, then one row per scale value with one column per trace.
+/// Traces on different scales are interpolated onto the union of their scale values, cells outside a trace's range are left empty.
+pub fn to_csv(traces: &[&Trace]) -> String {
+    let x_unit = traces
+        .first()
+        .map(|t| t.x_unit.as_str())
+        .unwrap_or_default();
+    let xs = shared_scale(traces);
+
+    let mut ret = String::from(scale_name(x_unit));
+    for t in traces {
+        ret.push(',');
+        ret.push_str(&csv_field(&t.name));
+    }
+    ret.push('\n');
+    for x in xs {
+        ret.push_str(&format!("{:e}", x));
+        for t in traces {
+            ret.push(',');
+            if let Some(y) = interpolate(&t.points, x) {
+                ret.push_str(&format!("{:e}", y));
+            }
+        }
+        ret.push('\n');
+    }
+    ret
+}
+
+/// quote a CSV field if needed, e.g. names holding commas
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// split a CSV line on commas, semicolons or tabs, honoring double quotes
+fn csv_split(line: &str) -> Vec<String> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' | ';' | '\t' if !quoted => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields.into_iter().map(|f| f.trim().to_string()).collect()
+}
+
+/// a table of numeric columns read from text, with optional column names
+struct Table {
+    names: Option<Vec<String>>,
+    /// rows of cells, None for empty cells
+    rows: Vec<Vec<Option<f32>>>,
+}
+
+/// read a table of numbers from text, split into fields by split.
+/// Blank lines and lines starting with `#` or `*` are skipped, a non-numeric first row is taken as the header.
+fn read_table(s: &str, split: impl Fn(&str) -> Vec<String>) -> Result<Table, WaveIoError> {
+    let mut names = None;
+    let mut rows = vec![];
+    for (i, line) in s.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with('*') {
+            continue;
+        }
+        let fields = split(trimmed);
+        let parsed: Vec<Option<f32>> = fields
+            .iter()
+            .map(|f| f.parse::<f32>().ok().filter(|_| !f.is_empty()))
+            .collect();
+        let numeric = fields
+            .iter()
+            .zip(&parsed)
+            .all(|(f, v)| f.is_empty() || v.is_some());
+        if !numeric {
+            if rows.is_empty() && names.is_none() {
+                names = Some(fields);
+                continue;
+            }
+            return Err(WaveIoError::Parse {
+                line: i + 1,
+                msg: format!("expected numbers, found \"{}\"", trimmed),
+            });
+        }
+        rows.push(parsed);
+    }
+    if rows.is_empty() {
+        return Err(WaveIoError::Empty);
+    }
+    Ok(Table { names, rows })
+}
+
+/// build a reference trace from column col of table against scale column scale_col
+fn column_trace(table: &Table, scale_col: usize, col: usize, name: &str, x_unit: &str) -> Trace {
+    let points = table
+        .rows
+        .iter()
+        .filter_map(|r| Some(VSPoint::new((*r.get(scale_col)?)?, (*r.get(col)?)?)))
+        .collect();
+    let mut t = Trace::new(format!("{}{}", REFERENCE_PREFIX, name), points);
+    t.unit = unit_from_name(name).to_string();
+    t.x_unit = x_unit.to_string();
+    t
+}
+
+/// read reference traces from CSV: the first column is the scale, every other column a trace
+pub fn from_csv(s: &str) -> Result<Vec<Trace>, WaveIoError> {
+    let table = read_table(s, csv_split)?;
+    let ncols = table.rows.iter().map(|r| r.len()).max().unwrap_or(0);
+    let name = |i: usize| {
+        table
+            .names
+            .as_ref()
+            .and_then(|n| n.get(i).cloned())
+            .filter(|n| !n.is_empty())
+            .unwrap_or_else(|| format!("col{}", i))
+    };
+    let x_unit = scale_unit(&name(0));
+    let traces: Vec<Trace> = (1..ncols)
+        .map(|i| column_trace(&table, 0, i, &name(i), x_unit))
+        .filter(|t| !t.points.is_empty())
+        .collect();
+    if traces.is_empty() {
+        return Err(WaveIoError::Empty);
+    }
+    Ok(traces)
+}
+
+/// read reference traces from ngspice `wrdata` output: whitespace separated (scale, value) column pairs,
+/// with a header of vector names if `wr_vecnames` is set. With `wr_singlescale` set, a single scale column is shared.
+pub fn from_wrdata(s: &str) -> Result<Vec<Trace>, WaveIoError> {
+    let table = read_table(s, |l| l.split_whitespace().map(String::from).collect())?;
+    let ncols = table.rows.iter().map(|r| r.len()).max().unwrap_or(0);
+    let names = table.names.clone().unwrap_or_default();
+    // with a header, a single scale is evident from the scale name appearing only once
+    let single_scale = !names.is_empty() && names.iter().filter(|n| **n == names[0]).count() == 1;
+    let x_unit = names.first().map(|n| scale_unit(n)).unwrap_or("s");
+    let traces: Vec<Trace> = if single_scale {
+        (1..ncols)
+            .map(|i| {
+                let name = names.get(i).cloned().unwrap_or_else(|| format!("col{}", i));
+                column_trace(&table, 0, i, &name, x_unit)
+            })
+            .collect()
+    } else {
+        (0..ncols / 2)
+            .map(|k| {
+                let name = names
+                    .get(2 * k + 1)
+                    .cloned()
+                    .unwrap_or_else(|| format!("col{}", k + 1));
+                column_trace(&table, 2 * k, 2 * k + 1, &name, x_unit)
+            })
+            .collect()
+    };
+    let traces: Vec<Trace> = traces
+        .into_iter()
+        .filter(|t| !t.points.is_empty())
+        .collect();
+    if traces.is_empty() {
+        return Err(WaveIoError::Empty);
+    }
+    Ok(traces)
+}
+
+/// read reference traces from the plots of an ngspice raw file. Complex vectors are read as their magnitude.
+pub fn from_raw(plots: &[paprika::PkRawPlot]) -> Result<Vec<Trace>, WaveIoError> {
+    let values = |v: &paprika::PkVectorinfo| -> Vec<f32> {
+        match (&v.realdata, &v.compdata) {
+            (Some(d), _) => d.iter().map(|x| *x as f32).collect(),
+            (None, Some(d)) => d.iter().map(|c| c.norm() as f32).collect(),
+            (None, None) => vec![],
+        }
+    };
+    let mut traces = vec![];
+    for plot in plots {
+        let Some(scale) = plot.scale() else {
+            continue;
+        };
+        let xs = values(scale);
+        let x_unit = scale_unit(&scale.name);
+        for v in &plot.vecs[1..] {
+            let points = xs
+                .iter()
+                .zip(values(v))
+                .map(|(x, y)| VSPoint::new(*x, y))
+                .collect();
+            let mut t = Trace::new(format!("{}{}", REFERENCE_PREFIX, v.name), points);
+            t.unit = unit_from_name(&v.name).to_string();
+            t.x_unit = x_unit.to_string();
+            traces.push(t);
+        }
+    }
+    if traces.is_empty() {
+        return Err(WaveIoError::Empty);
+    }
+    Ok(traces)
+}
+
+/// read reference traces from path, by extension: `.csv` as CSV, `.raw` as ngspice raw file, else as ngspice `wrdata` output
+pub fn import(path: &std::path::Path) -> Result<Vec<Trace>, WaveIoError> {
+    let ext = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "raw" => from_raw(&paprika::read_raw_file(path).map_err(WaveIoError::Raw)?),
+        "csv" => from_csv(&std::fs::read_to_string(path)?),
+        _ => from_wrdata(&std::fs::read_to_string(path)?),
+    }
+}
+
+/// returns the ngspice vector type for a unit
+fn stype_from_unit(unit: &str) -> i32 {
+    match unit {
+        "s" => 1,
+        "Hz" => 2,
+        "V" => 3,
+        "A" => 4,
+        "W" => 18,
+        _ => 0,
+    }
+}
+
+/// write traces as a real ngspice raw plot, on the union of their scale values.
+/// Values outside a trace's range are written as NaN, as the format has no empty cells.
+pub fn to_raw(traces: &[&Trace], title: &str) -> paprika::PkRawPlot {
+    let x_unit = traces
+        .first()
+        .map(|t| t.x_unit.as_str())
+        .unwrap_or_default();
+    let xs = shared_scale(traces);
+    let vector = |name: &str, unit: &str, data: Vec<f64>| paprika::PkVectorinfo {
+        name: name.to_string(),
+        stype: stype_from_unit(unit),
+        flag: paprika::VF_REAL,
+        length: data.len() as i32,
+        realdata: Some(data),
+        compdata: None,
+    };
+    let mut vecs = vec![vector(
+        scale_name(x_unit),
+        x_unit,
+        xs.iter().map(|x| *x as f64).collect(),
+    )];
+    for t in traces {
+        let data = xs
+            .iter()
+            .map(|x| interpolate(&t.points, *x).map_or(f64::NAN, |y| y as f64))
+            .collect();
+        vecs.push(vector(&t.name, &t.unit, data));
+    }
+    let plotname = match x_unit {
+        "s" => "Transient Analysis",
+        "Hz" => "AC Analysis",
+        _ => "DC transfer characteristic",
+    };
+    paprika::PkRawPlot {
+        title: title.to_string(),
+        date: String::new(),
+        plotname: plotname.to_string(),
+        is_complex: false,
+        vecs,
+    }
+}
+
+/// write traces to path, as a binary ngspice raw file if the extension is `.raw`, else as CSV
+pub fn export(path: &std::path::Path, traces: &[&Trace]) -> Result<(), WaveIoError> {
+    let is_raw = path
+        .extension()
+        .map(|e| e.eq_ignore_ascii_case("raw"))
+        .unwrap_or(false);
+    if is_raw {
+        paprika::write_raw_file(path, &[to_raw(traces, "circe")], true).map_err(WaveIoError::Raw)
+    } else {
+        std::fs::write(path, to_csv(traces))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trace(name: &str, pts: &[(f32, f32)]) -> Trace {
+        let pts = pts.iter().map(|(x, y)| VSPoint::new(*x, *y)).collect();
+        let mut t = Trace::new(name.to_string(), pts);
+        t.x_unit = String::from("s");
+        t
+    }
+
+    fn points(t: &Trace) -> Vec<(f32, f32)> {
+        t.points.iter().map(|p| (p.x, p.y)).collect()
+    }
+
+    #[test]
+    fn split() {
+        assert_eq!(csv_split("a, b ,c"), ["a", "b", "c"]);
+        assert_eq!(csv_split("a;b\tc"), ["a", "b", "c"]);
+        assert_eq!(
+            csv_split(r#""v(a,b)","say ""hi""",x"#),
+            ["v(a,b)", r#"say "hi""#, "x"]
+        );
+        assert_eq!(csv_split("a,,"), ["a", "", ""]);
+    }
+
+    #[test]
+    fn csv() {
+        let s = "# comment\ntime,\"v(a,b)\",i(v1)\n0,1,2\n\n1,3\n2,,4\n";
+        let traces = from_csv(s).unwrap();
+        assert_eq!(traces.len(), 2);
+        assert_eq!(traces[0].name, "ref:v(a,b)");
+        assert_eq!(traces[0].x_unit, "s");
+        assert_eq!(traces[0].unit, "V");
+        // ragged rows and empty cells leave out points
+        assert_eq!(points(&traces[0]), [(0.0, 1.0), (1.0, 3.0)]);
+        assert_eq!(traces[1].unit, "A");
+        assert_eq!(points(&traces[1]), [(0.0, 2.0), (2.0, 4.0)]);
+
+        // without a header, columns are numbered
+        let traces = from_csv("0;1e-3\n1;2e-3\n").unwrap();
+        assert_eq!(traces[0].name, "ref:col1");
+        assert_eq!(traces[0].x_unit, "");
+        assert_eq!(points(&traces[0]), [(0.0, 1e-3), (1.0, 2e-3)]);
+
+        assert!(matches!(
+            from_csv("time,v(a)\n0,1\n1,x\n"),
+            Err(WaveIoError::Parse { line: 3, .. })
+        ));
+        assert!(matches!(from_csv("time,v(a)\n"), Err(WaveIoError::Empty)));
+        assert!(matches!(from_csv("0\n1\n"), Err(WaveIoError::Empty)));
+    }
+
+    #[test]
+    fn wrdata() {
+        // a scale column per vector, without a header
+        let traces = from_wrdata(" 0 1  0 2\n 1 3  1 4\n").unwrap();
+        assert_eq!(traces.len(), 2);
+        assert_eq!(traces[0].name, "ref:col1");
+        assert_eq!(traces[0].x_unit, "s");
+        assert_eq!(points(&traces[1]), [(0.0, 2.0), (1.0, 4.0)]);
+
+        // wr_vecnames
+        let traces = from_wrdata("time v(a) time v(b)\n0 1 0 2\n1 3 1 4\n").unwrap();
+        assert_eq!(traces[1].name, "ref:v(b)");
+        assert_eq!(points(&traces[1]), [(0.0, 2.0), (1.0, 4.0)]);
+
+        // wr_vecnames and wr_singlescale
+        let traces = from_wrdata("frequency v(a) v(b)\n0 1 2\n1 3 4\n").unwrap();
+        assert_eq!(traces.len(), 2);
+        assert_eq!(traces[0].x_unit, "Hz");
+        assert_eq!(traces[1].name, "ref:v(b)");
+        assert_eq!(points(&traces[1]), [(0.0, 2.0), (1.0, 4.0)]);
+    }
+
+    #[test]
+    fn round_trip() {
+        let a = trace("v(a,b)", &[(0.0, 1.5), (1e-3, -2.25e-6), (2e-3, 3.0)]);
+        let b = trace("i(v1)", &[(1e-3, 1.0), (1.5e-3, 2.0)]);
+        let dir = std::env::temp_dir();
+        for ext in ["csv", "raw"] {
+            let path = dir.join(format!("circe_waveio_{}.{}", std::process::id(), ext));
+            export(&path, &[&a, &b]).unwrap();
+            let traces = import(&path).unwrap();
+            std::fs::remove_file(&path).unwrap();
+
+            assert_eq!(traces.len(), 2);
+            assert_eq!(traces[0].name, "ref:v(a,b)");
+            assert_eq!(traces[0].x_unit, "s");
+            assert_eq!(traces[1].unit, "A");
+            // on the union of the scales
+            for (x, y) in points(&a) {
+                assert_eq!(traces[0].value_at(x), Some(y), "{}", ext);
+            }
+            assert_eq!(traces[0].points.len(), 4);
+            if ext == "csv" {
+                // cells outside b are empty
+                assert_eq!(points(&traces[1]), points(&b));
+            }
+        }
+    }
+}
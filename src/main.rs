@@ -8,10 +8,10 @@ mod schematic;
 mod transforms;
 
 use analysis::plot_page::{PlotPage, PlotPageMsg};
-use schematic::circuit::CircuitSchematicPage;
+use schematic::circuit::{CircuitPageMsg, CircuitSchematicPage};
 use schematic::symbols::SymbolDesignerPage;
 
-use iced::{executor, Application, Command, Element, Settings, Theme};
+use iced::{executor, Application, Command, Element, Settings, Subscription, Theme};
 
 use iced_aw::{TabLabel, Tabs};
 
@@ -73,26 +73,42 @@ impl Application for Circe {
         match message {
             Msg::TabSel(i) => {
                 self.active_tab = i;
-
-                // transfer simulation results from circuit_schematic to plot
-                if let Some(traces) = self.circuit_schematic.traces.take() {
-                    let msg = PlotPageMsg::Traces(traces);
-                    self.plot_view.update(msg);
-                }
             }
             Msg::DesignerMsg(device_designer_msg) => {
                 self.symbol_designer.update(device_designer_msg);
             }
             Msg::PlotViewMsg(plot_msg) => {
                 self.plot_view.update(plot_msg);
+                // cross-probe: highlight the nets of the selected traces in the schematic
+                let names = self.plot_view.selected_trace_names();
+                self.circuit_schematic
+                    .update(CircuitPageMsg::Highlight(names));
             }
             Msg::SchematicMsg(schematic_msg) => {
                 self.circuit_schematic.update(schematic_msg);
+
+                // transfer simulation results and probes from circuit_schematic to plot
+                if let Some(traces) = self.circuit_schematic.traces.take() {
+                    self.plot_view.update(PlotPageMsg::Traces(traces));
+                }
+                if let Some(probe) = self.circuit_schematic.probe.take() {
+                    self.plot_view.update(PlotPageMsg::Probe(probe));
+                }
             }
         }
         Command::none()
     }
 
+    fn subscription(&self) -> Subscription<Msg> {
+        // poll the simulation running in the background for its waveforms so far and completion
+        if self.circuit_schematic.is_running() {
+            iced::time::every(schematic::circuit::BG_POLL)
+                .map(|_| Msg::SchematicMsg(CircuitPageMsg::Tick))
+        } else {
+            Subscription::none()
+        }
+    }
+
     fn view(&self) -> Element<Msg> {
         let schematic = self.circuit_schematic.view().map(Msg::SchematicMsg);
         let plot = self.plot_view.view().map(Msg::PlotViewMsg);
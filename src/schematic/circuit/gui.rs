@@ -2,8 +2,13 @@
 //! includes paramter editor, toolbar, and the canvas itself
 //! waiting on multiple windows support for new device instance menu
 
+use crate::analysis::expr::parse_number;
+use crate::analysis::trace::{digital_points, family_name, Trace};
 use crate::schematic;
+use crate::schematic::circuit::montecarlo::MonteCarlo;
+use crate::schematic::circuit::sweep::Sweep;
 use crate::schematic::circuit::{Circuit, CircuitElement, Msg};
+use crate::schematic::models::Corner;
 use crate::schematic::viewport::CompositeMsg;
 use crate::schematic::viewport::VCTransformLockedAspect;
 use crate::transforms::VSPoint;
@@ -14,18 +19,29 @@ use crate::schematic::Schematic;
 use crate::IcedStruct;
 use iced::keyboard::Modifiers;
 use iced::widget::canvas::Event;
-use iced::widget::{row, text, text_input};
+use iced::widget::{button, checkbox, row, text, text_input};
 use iced::{Element, Length};
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::rc::Rc;
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use colored::Colorize;
 use paprika::*;
 
+/// interval at which a background analysis is polled for its waveforms so far and completion
+pub const BG_POLL: Duration = Duration::from_millis(100);
+
 /// Spice Manager to facillitate interaction with NgSpice
 #[derive(Debug, Default)]
 struct SpManager {
     vecvals: Mutex<Vec<PkVecvaluesall>>,
     vecinfo: Option<PkVecinfoall>,
+    /// diagnostics of the current run, classified from ngspice's output
+    diagnostics: Mutex<PkDiagnostics>,
+    /// progress of the running analysis
+    progress: Mutex<Option<PkProgress>>,
 }
 
 impl SpManager {
@@ -48,21 +64,63 @@ impl paprika::PkSpiceManager for SpManager {
             _ => msg.magenta().strikethrough(),
         };
         println!("{}", msgc);
+        self.diagnostics.lock().unwrap().push(&msg);
     }
-    fn cb_send_stat(&mut self, msg: String, id: i32) {
-        println!("{}", msg.blue());
+    // status lines are shown by the progress bar, through cb_progress
+    fn cb_send_stat(&mut self, msg: String, id: i32) {}
+    fn cb_progress(&mut self, progress: PkProgress, id: i32) {
+        *self.progress.lock().unwrap() = Some(progress);
     }
     fn cb_ctrldexit(&mut self, status: i32, is_immediate: bool, is_quit: bool, id: i32) {}
     fn cb_send_init(&mut self, pkvecinfoall: PkVecinfoall, id: i32) {
         self.vecinfo = Some(pkvecinfoall);
     }
     fn cb_send_data(&mut self, pkvecvaluesall: PkVecvaluesall, count: i32, id: i32) {
-        // this is called every simulation step when running tran
-        self.vecvals.try_lock().unwrap().push(pkvecvaluesall);
+        // this is called every simulation step when running tran, from the background thread while the page polls it
+        self.vecvals.lock().unwrap().push(pkvecvaluesall);
     }
     fn cb_bgt_state(&mut self, is_fin: bool, id: i32) {}
 }
 
+/// a transient analysis run of a job
+struct TranRun {
+    /// labels the traces of the run, e.g. `r1=2k`. The traces of an unlabeled run are shown as they are, possibly paused
+    label: Option<String>,
+    /// commands sent after sourcing the netlist, e.g. `alter r1 2k`
+    commands: Vec<String>,
+    /// process corner the schematic is netlisted at for the run, when the job starts
+    corner: Option<Corner>,
+    /// time to pause the run at
+    stop_at: Option<f32>,
+}
+
+/// transient analysis runs, run one after another in ngspice's background thread and polled on Tick
+struct TranJob {
+    /// runs not started yet, with the netlist each sources
+    pending: VecDeque<(Rc<str>, TranRun)>,
+    /// label of the running run
+    label: Option<String>,
+    /// number of runs of the job
+    total: usize,
+    /// traces of the finished runs
+    family: Vec<Trace>,
+    /// when the job started
+    started: Instant,
+}
+
+impl TranJob {
+    /// returns the fraction of the job done, given the progress of the running run
+    fn fraction(&self, progress: Option<&PkProgress>) -> f64 {
+        let done = self.total.saturating_sub(self.pending.len() + 1);
+        (done as f64 + progress.map_or(0.0, PkProgress::fraction)) / self.total as f64
+    }
+}
+
+/// returns the time left, extrapolated from the time elapsed for the fraction done
+fn eta(elapsed: Duration, fraction: f64) -> Option<Duration> {
+    (fraction > 0.0).then(|| elapsed.mul_f64((1.0 - fraction) / fraction))
+}
+
 #[derive(Debug, Clone)]
 pub enum CircuitPageMsg {
     ViewportEvt(viewport::CompositeMsg<schematic::Msg<Msg, CircuitElement>>),
@@ -71,6 +129,46 @@ pub enum CircuitPageMsg {
     HzChanged(String),
     StepChanged(String),
     TranChanged(String),
+    /// highlight the nets of the named traces, e.g. those selected on the plot page
+    Highlight(Vec<String>),
+    /// show or hide the operating point annotation
+    OpOverlay(bool),
+    /// sweep definition edited
+    SweepChanged(String),
+    /// run the transient analysis once for every value of the sweep
+    RunSweep,
+    /// run to time edited
+    RunToChanged(String),
+    /// run the transient analysis until the run to time, and pause there
+    RunTo,
+    /// resume the transient analysis paused at the run to time
+    Resume,
+    /// monte carlo definition edited
+    MonteCarloChanged(String),
+    /// model library path edited
+    ModelLibChanged(String),
+    /// run the transient analysis with device values sampled within their tolerance
+    RunMonteCarlo,
+    /// run the transient analysis at every process corner
+    RunCorners,
+    /// code model directories edited
+    CodeModelDirsChanged(String),
+    /// load the code model libraries in the code model directories
+    LoadCodeModels,
+    /// name of the global parameter at index edited
+    GlobalNameChanged(usize, String),
+    /// value of the global parameter at index edited
+    GlobalValueChanged(usize, String),
+    /// apply the edited name and value of the global parameter at index
+    GlobalSubmit(usize),
+    /// add a global parameter
+    AddGlobal,
+    /// remove the global parameter at index
+    DeleteGlobal(usize),
+    /// show all of ngspice's output in the console, or only warnings and errors
+    ConsoleAll(bool),
+    /// poll the transient analysis running in the background
+    Tick,
 }
 
 /// schematic
@@ -84,10 +182,12 @@ pub struct CircuitSchematicPage {
 
     /// spice manager
     spmanager: Arc<SpManager>,
-    /// ngspice library
-    lib: PkSpice<SpManager>,
+    /// ngspice library, or why it is unavailable
+    lib: Result<PkSpice<SpManager>, String>,
     /// traces from certain simulations e.g. transient
-    pub traces: Option<Vec<Vec<VSPoint>>>,
+    pub traces: Option<Vec<Trace>>,
+    /// trace expression requested by probing the schematic, e.g. `v(n1)`
+    pub probe: Option<String>,
 
     /// active device - some if only 1 device selected, otherwise is none
     active_element: Option<CircuitElement>,
@@ -99,52 +199,63 @@ pub struct CircuitSchematicPage {
     tran_step: String,
     /// tran simulation end time (seconds)
     tran_end: String,
+    /// time to pause the transient analysis at (seconds)
+    run_to: String,
+    /// time the transient analysis is paused at, if paused
+    paused_at: Option<f32>,
+    /// transient analysis runs in the background, if running
+    job: Option<TranJob>,
+    /// sweep definition, e.g. `R1 list 1k 2k 5k`
+    sweep: String,
+    /// monte carlo definition, e.g. `runs 20 seed 1 gauss R 5%`
+    montecarlo: String,
+    /// path of the user model library, with a section per corner
+    model_lib: String,
+    /// error from the last sweep, monte carlo or corner run
+    run_error: Option<String>,
+    /// global parameter editor text (name, value), one per parameter
+    globals: Vec<(String, String)>,
+    /// error from the last parameter edit, e.g. a reference to an undefined parameter
+    param_error: Option<String>,
+    /// directories of XSPICE code model libraries, separated as in `PATH`
+    codemodel_dirs: String,
+    /// code model libraries loaded into ngspice
+    codemodels: Vec<std::path::PathBuf>,
+    /// number of libraries loaded by the last code model load, or its errors
+    codemodel_result: Option<Result<usize, String>>,
+    /// diagnostics of the last run
+    diagnostics: Vec<PkDiagnostic>,
+    /// generation of the diagnostics taken up, see `PkDiagnostics::generation`
+    diagnostics_generation: u64,
+    /// the console shows all diagnostics, not only warnings and errors
+    console_all: bool,
+    /// file the netlist is written to for ngspice to source
+    netlist_path: PathBuf,
 }
 impl Default for CircuitSchematicPage {
     fn default() -> Self {
-        let spmanager = Arc::new(SpManager::new());
-        let mut lib;
-        #[cfg(target_family = "windows")]
-        {
-            lib = PkSpice::<SpManager>::new(std::ffi::OsStr::new("paprika/ngspice.dll")).unwrap();
-        }
-        #[cfg(target_os = "macos")]
-        {
-            // retrieve libngspice.dylib from the following possible directories
-            let ret = std::process::Command::new("find")
-                .args(&["/usr/lib", "/usr/local/lib"])
-                .arg("-name")
-                .arg("*libngspice.dylib")
-                .stdout(std::process::Stdio::piped())
-                .output()
-                .unwrap_or_else(|_| {
-                    eprintln!("Error: Could not find libngspice.dylib. Make sure it is installed.");
-                    std::process::exit(1);
-                });
-            let path = String::from_utf8(ret.stdout).unwrap();
-            lib = PkSpice::<SpManager>::new(&std::ffi::OsString::from(path.trim())).unwrap();
-        }
-        #[cfg(target_os = "linux")]
-        {
-            // dynamically retrieves libngspice from system
-            let ret = std::process::Command::new("sh")
-                .arg("-c")
-                .arg("ldconfig -p | grep ngspice | awk '/.*libngspice.so$/{print $4}'")
-                .stdout(std::process::Stdio::piped())
-                .output()
-                .unwrap_or_else(|_| {
-                    eprintln!("Error: Could not find libngspice. Make sure it is installed.");
-                    std::process::exit(1);
-                });
+        CircuitSchematicPage::with_lib(PkSpice::locate)
+    }
+}
 
-            let path = String::from_utf8(ret.stdout).unwrap();
-            lib = PkSpice::<SpManager>::new(&std::ffi::OsString::from(path.trim())).unwrap();
+impl CircuitSchematicPage {
+    /// creates the page, simulating with the ngspice library linked by link
+    fn with_lib(link: impl FnOnce() -> Result<PkSpice<SpManager>, PkSpiceError>) -> Self {
+        let spmanager = Arc::new(SpManager::new());
+        // circe is usable without ngspice, only simulation is unavailable
+        let lib = link()
+            .and_then(|mut lib| {
+                lib.init(Some(spmanager.clone()))?;
+                Ok(lib)
+            })
+            .map_err(|e| format!("simulation unavailable: {}", e));
+        if let Err(e) = &lib {
+            eprintln!("{}", e.red());
         }
-        lib.init(Some(spmanager.clone()));
         let vct = VCTransformLockedAspect::identity()
             .pre_flip_y()
             .then_scale(10.0);
-        CircuitSchematicPage {
+        let mut page = CircuitSchematicPage {
             viewport: viewport::Viewport::new(1.0, 100.0, vct),
             net_name: Default::default(),
             active_element: Default::default(),
@@ -152,9 +263,500 @@ impl Default for CircuitSchematicPage {
             spmanager,
             lib,
             traces: None,
+            probe: None,
             ac_hz: String::from("60"),
             tran_step: String::from("10u"),
             tran_end: String::from("1m"),
+            run_to: String::from("500u"),
+            paused_at: None,
+            job: None,
+            sweep: String::new(),
+            montecarlo: String::from("runs 20 seed 1 gauss R 5% C 10% L 10%"),
+            model_lib: String::new(),
+            run_error: None,
+            globals: vec![],
+            param_error: None,
+            codemodel_dirs: std::env::var("CIRCE_CODEMODEL_PATH").unwrap_or_default(),
+            codemodels: vec![],
+            codemodel_result: None,
+            diagnostics: vec![],
+            diagnostics_generation: 0,
+            console_all: false,
+            netlist_path: PathBuf::from("netlist.cir"),
+        };
+        if page.lib.is_ok() && !page.codemodel_dirs.is_empty() {
+            // code models must be loaded before a circuit using them is sourced
+            page.codemodel_result = Some(page.load_codemodels());
+        }
+        page
+    }
+    /// returns the ngspice library, or why it is unavailable
+    fn lib(&self) -> Result<&PkSpice<SpManager>, String> {
+        self.lib.as_ref().map_err(|e| e.clone())
+    }
+    /// returns the ngspice library if no analysis is running in the background
+    fn idle_lib(&self) -> Result<&PkSpice<SpManager>, String> {
+        if self.job.is_some() {
+            return Err(String::from("simulation already running"));
+        }
+        self.lib()
+    }
+    /// returns true while a transient analysis runs in the background
+    pub fn is_running(&self) -> bool {
+        self.job.is_some()
+    }
+    /// starts collecting the diagnostics of a new run
+    fn clear_diagnostics(&self) {
+        self.spmanager.diagnostics.lock().unwrap().clear();
+    }
+    /// takes up the diagnostics collected so far, flagging the devices and nets they refer to in the schematic
+    fn collect_diagnostics(&mut self) {
+        let diagnostics = {
+            let collected = self.spmanager.diagnostics.lock().unwrap();
+            if collected.generation() == self.diagnostics_generation {
+                return;
+            }
+            self.diagnostics_generation = collected.generation();
+            collected.items().to_vec()
+        };
+        let flagged = || {
+            diagnostics
+                .iter()
+                .filter(|d| d.severity <= PkSeverity::Warning)
+        };
+        let devices = flagged().filter_map(|d| d.device.clone()).collect();
+        let nets = flagged().filter_map(|d| d.node.clone()).collect();
+        if self.viewport.content.content.flag(devices, nets) {
+            self.viewport.passive_cache.clear();
+        }
+        self.diagnostics = diagnostics;
+    }
+    /// runs the dc operating point analysis on the schematic, annotating the devices
+    fn run_op(&mut self) -> Result<(), String> {
+        self.idle_lib()?;
+        self.clear_diagnostics();
+        let netlist = self.viewport.content.content.netlist_text();
+        self.source(&netlist)?;
+        let saves = self.viewport.content.content.op_saves();
+        if !saves.is_empty() {
+            // device currents and mosfet figures are only simulated if saved
+            self.lib()?
+                .command(&format!("save all {}", saves.join(" ")));
+        }
+        self.lib()?.command("op"); // ngspice recommends sending in control statements separately, not as part of netlist
+        if let Some(pkvecvaluesall) = self.spmanager.vecvals.try_lock().unwrap().pop() {
+            self.viewport.update(CompositeMsg {
+                content_msg: schematic::Msg::ContentMsg(Msg::DcOp(pkvecvaluesall.clone())),
+                viewport_msg: viewport::Msg::None,
+            });
+        }
+        Ok(())
+    }
+    /// runs the ac analysis at the ac frequency on the schematic, annotating the devices
+    fn run_ac(&mut self) -> Result<(), String> {
+        self.idle_lib()?;
+        self.clear_diagnostics();
+        let netlist = self.viewport.content.content.netlist_text();
+        self.source(&netlist)?;
+        self.lib()?
+            .command(&format!("ac lin 0 {} {}", self.ac_hz, self.ac_hz)); // ngspice recommends sending in control statements separately, not as part of netlist
+        if let Some(pkvecvaluesall) = self.spmanager.vecvals.try_lock().unwrap().pop() {
+            self.viewport.update(CompositeMsg {
+                content_msg: schematic::Msg::ContentMsg(Msg::Ac(pkvecvaluesall.clone())),
+                viewport_msg: viewport::Msg::None,
+            });
+        }
+        Ok(())
+    }
+    /// netlists the schematic and starts the transient analysis on it in the background
+    fn run_tran(&mut self) -> Result<(), String> {
+        self.start_job(vec![TranRun {
+            label: None,
+            commands: vec![],
+            corner: None,
+            stop_at: None,
+        }])
+    }
+    /// starts the transient analysis in the background, to pause at the run to time until resumed
+    fn run_to(&mut self) -> Result<(), String> {
+        let t =
+            parse_number(&self.run_to).ok_or_else(|| format!("invalid time {}", self.run_to))?;
+        self.start_job(vec![TranRun {
+            label: None,
+            commands: vec![],
+            corner: None,
+            stop_at: Some(t),
+        }])
+    }
+    /// resumes the transient analysis paused at a breakpoint in the background
+    fn resume(&mut self) -> Result<(), String> {
+        let lib = self.idle_lib()?;
+        lib.command("delete all"); // the stop condition would pause again right away
+        *self.spmanager.progress.lock().unwrap() = None;
+        lib.bg_command("resume").map_err(|e| e.to_string())?;
+        self.paused_at = None;
+        self.job = Some(TranJob {
+            pending: VecDeque::new(),
+            label: None,
+            total: 1,
+            family: vec![],
+            started: Instant::now(),
+        });
+        Ok(())
+    }
+    /// netlists the schematic and starts the job of runs in the background. Returns error if it could not be started.
+    /// The netlists of all runs are taken up front, the schematic stays editable while the job runs
+    fn start_job(&mut self, runs: Vec<TranRun>) -> Result<(), String> {
+        self.idle_lib()?;
+        self.clear_diagnostics();
+        self.paused_at = None;
+        let circuit = &mut self.viewport.content.content;
+        let nominal: Rc<str> = circuit.netlist_text().into();
+        let pending = runs
+            .into_iter()
+            .map(|run| match run.corner {
+                Some(corner) => {
+                    circuit.set_corner(corner);
+                    let netlist = circuit.netlist_text().into();
+                    circuit.set_corner(Corner::TT);
+                    (netlist, run)
+                }
+                None => (nominal.clone(), run),
+            })
+            .collect::<VecDeque<_>>();
+        self.job = Some(TranJob {
+            total: pending.len(),
+            pending,
+            label: None,
+            family: vec![],
+            started: Instant::now(),
+        });
+        self.start_next();
+        Ok(())
+    }
+    /// starts the next run of the job in the background, ends the job if there is none or it could not be started
+    fn start_next(&mut self) {
+        let Some(job) = &mut self.job else {
+            return;
+        };
+        let Some((netlist, run)) = job.pending.pop_front() else {
+            let family = std::mem::take(&mut job.family);
+            self.end_job(Ok(family));
+            return;
+        };
+        let label = run.label.clone();
+        if let Err(e) = self.start_run(&netlist, run) {
+            let e = match label {
+                Some(label) => format!("run {} failed: {}", label, e),
+                None => e,
+            };
+            self.end_job(Err(e));
+        }
+    }
+    /// writes the netlist to the netlist file and sources it in ngspice
+    fn source(&self, netlist: &str) -> Result<(), String> {
+        let path = self.netlist_path.display();
+        std::fs::write(&self.netlist_path, netlist)
+            .map_err(|e| format!("could not write {}: {}", path, e))?;
+        self.lib()?.command(&format!("source {}", path)); // results pointer array starts at same address
+        Ok(())
+    }
+    /// starts the run in the background from the netlist sourced afresh, undoing the previous run's changes
+    fn start_run(&mut self, netlist: &str, run: TranRun) -> Result<(), String> {
+        self.source(netlist)?;
+        let lib = self.lib()?;
+        for c in &run.commands {
+            lib.command(c);
+        }
+        self.spmanager.vecvals.lock().unwrap().clear();
+        *self.spmanager.progress.lock().unwrap() = None;
+        match run.stop_at {
+            // the breakpoint makes the analysis land exactly on t, the stop condition pauses it there
+            Some(t) => {
+                lib.set_bkpt(t as f64)
+                    .map_err(|e| format!("could not set breakpoint at {:e}: {}", t, e))?;
+                lib.command(&format!("stop when time >= {:e}", t));
+            }
+            None => {
+                lib.command("delete all"); // stop conditions left from run to
+            }
+        }
+        // ngspice recommends sending in control statements separately, not as part of netlist
+        lib.bg_command(&format!("tran {} {}", self.tran_step, self.tran_end))
+            .map_err(|e| e.to_string())?;
+        if let Some(job) = &mut self.job {
+            job.label = run.label;
+        }
+        Ok(())
+    }
+    /// polls the job running in the background. While a run runs, shows its waveforms so far if it is unlabeled;
+    /// once it stops, at its end or paused, collects its traces and starts the next run
+    fn poll_job(&mut self) {
+        let Some(job) = &self.job else {
+            return;
+        };
+        let label = job.label.clone();
+        match self.lib() {
+            Ok(lib) if lib.is_running() => {
+                // event nodes are read once it stops, ngspice's other API functions may not be called while it runs
+                if label.is_none() {
+                    if let Some(traces) = self.analog_traces() {
+                        self.traces = Some(traces);
+                    }
+                }
+                return;
+            }
+            Ok(_) => {}
+            Err(e) => {
+                self.end_job(Err(e));
+                return;
+            }
+        }
+        let traces = match &label {
+            None => self.tran_paused_traces(),
+            Some(label) => self
+                .tran_traces()
+                .map(|traces| {
+                    traces
+                        .into_iter()
+                        .map(|mut t| {
+                            t.name = family_name(&t.name, label);
+                            t
+                        })
+                        .collect()
+                })
+                .ok_or_else(|| format!("run {} failed: transient analysis failed", label)),
+        };
+        match traces {
+            Ok(traces) => {
+                if let Some(job) = &mut self.job {
+                    job.family.extend(traces);
+                }
+                self.start_next();
+            }
+            Err(e) => self.end_job(Err(e)),
+        }
+    }
+    /// ends the job, showing its traces or error
+    fn end_job(&mut self, result: Result<Vec<Trace>, String>) {
+        self.job = None;
+        self.show_family(result);
+    }
+    /// returns the traces of the transient analysis so far, noting whether it is paused before its end time
+    fn tran_paused_traces(&mut self) -> Result<Vec<Trace>, String> {
+        let traces = self
+            .tran_traces()
+            .ok_or_else(|| String::from("transient analysis failed"))?;
+        let end = parse_number(&self.tran_end).unwrap_or_default();
+        let t = traces
+            .first()
+            .and_then(|t| t.points.last())
+            .map(|p| p.x)
+            .unwrap_or_default();
+        self.paused_at = (t < end * (1.0 - 1e-6)).then_some(t);
+        Ok(traces)
+    }
+    /// returns a trace for every vector and event node of the transient analysis results received so far
+    fn tran_traces(&self) -> Option<Vec<Trace>> {
+        let mut results = self.analog_traces()?;
+        // digital traces end where the analog ones do, e.g. at the run to time
+        let end = results
+            .first()
+            .and_then(|t| t.points.last())
+            .map_or(0.0, |p| p.x);
+        results.extend(self.evt_traces(end));
+        Some(results)
+    }
+    /// returns a trace for every vector of the transient analysis results received so far
+    fn analog_traces(&self) -> Option<Vec<Trace>> {
+        let pk_results = self.spmanager.vecvals.lock().unwrap();
+
+        let first = pk_results.first()?;
+        let mut results: Vec<Trace> = first
+            .vecsa
+            .iter()
+            .map(|v| Trace::new(v.name.clone(), Vec::with_capacity(pk_results.len())))
+            .collect();
+
+        let x_i = first.vecsa.iter().position(|x| x.name == "time")?;
+        for t in results.iter_mut() {
+            t.x_unit = String::from("s");
+        }
+        for step_val in pk_results.iter() {
+            for (trace_i, trace_val) in step_val.vecsa.iter().enumerate() {
+                results[trace_i].points.push(VSPoint::new(
+                    step_val.vecsa[x_i].creal as f32,
+                    trace_val.creal as f32,
+                ));
+            }
+        }
+        results.remove(x_i);
+        Some(results)
+    }
+    /// returns a digital trace for every XSPICE event node with digital values, held at its last level until end
+    fn evt_traces(&self, end: f32) -> Vec<Trace> {
+        let Ok(lib) = self.lib() else {
+            return vec![];
+        };
+        lib.get_all_evt_nodes()
+            .into_iter()
+            .filter_map(|name| {
+                let info = lib.get_evt_node_info(&name)?;
+                let changes: Vec<_> = info
+                    .evts
+                    .iter()
+                    // events of the operating point preceding the transient analysis
+                    .filter(|e| !e.dcop)
+                    .filter_map(|e| {
+                        let level = match e.digital()?.state {
+                            PkDigitalState::Zero => 0.0,
+                            PkDigitalState::One => 1.0,
+                            PkDigitalState::Unknown => 0.5,
+                        };
+                        Some((e.step as f32, level))
+                    })
+                    .collect();
+                if changes.is_empty() {
+                    return None;
+                }
+                let mut t = Trace::new(name, digital_points(&changes, end));
+                t.unit = String::new(); // logic levels
+                t.x_unit = String::from("s");
+                Some(t)
+            })
+            .collect()
+    }
+    /// starts the transient analysis once for each run of (label, commands) in the background, sending the commands after sourcing the netlist.
+    /// The traces of all runs are shown labeled
+    fn run_family(&mut self, runs: Vec<(String, Vec<String>)>) -> Result<(), String> {
+        self.start_job(
+            runs.into_iter()
+                .map(|(label, commands)| TranRun {
+                    label: Some(label),
+                    commands,
+                    corner: None,
+                    stop_at: None,
+                })
+                .collect(),
+        )
+    }
+    /// starts the transient analysis for every value of the sweep, labeling the traces of each run by sweep value
+    fn run_sweep(&mut self) -> Result<(), String> {
+        let sweep = Sweep::parse(&self.sweep)?;
+        let runs = sweep
+            .values()
+            .iter()
+            .map(|v| (sweep.label(v), sweep.commands(v)))
+            .collect();
+        self.run_family(runs)
+    }
+    /// starts the transient analysis with device values sampled within their tolerance, labeling the traces of each run by run
+    fn run_monte_carlo(&mut self) -> Result<(), String> {
+        let mc = MonteCarlo::parse(&self.montecarlo)?;
+        let devices = self.viewport.content.content.nominal_values();
+        let runs = mc
+            .commands(&devices)?
+            .into_iter()
+            .enumerate()
+            .map(|(i, c)| (format!("mc={}", i + 1), c))
+            .collect();
+        self.run_family(runs)
+    }
+    /// starts the transient analysis at every process corner, labeling the traces of each run by corner
+    fn run_corners(&mut self) -> Result<(), String> {
+        self.start_job(
+            Corner::ALL
+                .into_iter()
+                .map(|corner| TranRun {
+                    label: Some(format!("corner={}", corner)),
+                    commands: vec![],
+                    corner: Some(corner),
+                    stop_at: None,
+                })
+                .collect(),
+        )
+    }
+    /// loads the code model libraries in the code model directories not loaded yet, returns the number loaded
+    fn load_codemodels(&mut self) -> Result<usize, String> {
+        let lib = self.lib.as_mut().map_err(|e| e.clone())?;
+        for dir in std::env::split_paths(&self.codemodel_dirs) {
+            if !dir.as_os_str().is_empty() {
+                lib.add_codemodel_path(dir);
+            }
+        }
+        let mut errors = vec![];
+        let mut n = 0;
+        for path in lib.available_codemodels() {
+            if self.codemodels.contains(&path) {
+                continue;
+            }
+            self.spmanager.diagnostics.lock().unwrap().clear();
+            let path = match lib.load_codemodel(&path.to_string_lossy()) {
+                Ok(path) => path,
+                Err(e) => {
+                    errors.push(e.to_string());
+                    continue;
+                }
+            };
+            // ngspice reports a library it fails to load only in its output
+            let failed = self
+                .spmanager
+                .diagnostics
+                .lock()
+                .unwrap()
+                .at_least(PkSeverity::Error)
+                .next()
+                .map(|d| d.message.clone());
+            match failed {
+                Some(e) => errors.push(e),
+                None => {
+                    self.codemodels.push(path);
+                    n += 1;
+                }
+            }
+        }
+        if errors.is_empty() {
+            Ok(n)
+        } else {
+            Err(errors.join("; "))
+        }
+    }
+    /// resets the global parameter editor text to the parameter table
+    fn sync_globals(&mut self) {
+        self.globals = self
+            .viewport
+            .content
+            .content
+            .params()
+            .iter()
+            .map(|(n, v)| (n.to_string(), v.to_string()))
+            .collect();
+    }
+    /// applies the edited name and value of the global parameter at index i
+    fn submit_global(&mut self, i: usize) -> Result<(), String> {
+        let Some((name, value)) = self.globals.get(i).cloned() else {
+            return Ok(());
+        };
+        self.viewport
+            .content
+            .content
+            .set_param(i, name.trim(), value)
+    }
+    /// returns the number of diagnostics of the last run of severity
+    fn count_diagnostics(&self, severity: PkSeverity) -> usize {
+        self.diagnostics
+            .iter()
+            .filter(|d| d.severity == severity)
+            .count()
+    }
+    /// shows the traces of a job, or its error
+    fn show_family(&mut self, result: Result<Vec<Trace>, String>) {
+        match result {
+            Ok(traces) => {
+                self.run_error = None;
+                self.traces = Some(traces);
+            }
+            Err(e) => self.run_error = Some(e),
         }
     }
 }
@@ -171,6 +773,13 @@ impl IcedStruct<CircuitPageMsg> for CircuitSchematicPage {
                     match ad {
                         CircuitElement::NetEdge(_) => {}
                         CircuitElement::Device(d) => {
+                            if let Err(e) =
+                                self.viewport.content.content.validate_param(&self.param)
+                            {
+                                self.param_error = Some(e);
+                                return;
+                            }
+                            self.param_error = None;
                             d.0.borrow_mut()
                                 .class_mut()
                                 .set_raw_param(self.param.clone());
@@ -191,22 +800,7 @@ impl IcedStruct<CircuitPageMsg> for CircuitSchematicPage {
                         }),
                         _,
                     ) => {
-                        self.viewport.update(CompositeMsg {
-                            content_msg: schematic::Msg::ContentMsg(Msg::NetList),
-                            viewport_msg: viewport::Msg::None,
-                        });
-                        self.lib.command("source netlist.cir"); // results pointer array starts at same address
-                        self.lib.command("op"); // ngspice recommends sending in control statements separately, not as part of netlist
-                        if let Some(pkvecvaluesall) =
-                            self.spmanager.vecvals.try_lock().unwrap().pop()
-                        {
-                            self.viewport.update(CompositeMsg {
-                                content_msg: schematic::Msg::ContentMsg(Msg::DcOp(
-                                    pkvecvaluesall.clone(),
-                                )),
-                                viewport_msg: viewport::Msg::None,
-                            });
-                        }
+                        self.run_error = self.run_op().err();
                     }
                     schematic::Msg::Event(
                         Event::Keyboard(iced::keyboard::Event::KeyPressed {
@@ -215,23 +809,7 @@ impl IcedStruct<CircuitPageMsg> for CircuitSchematicPage {
                         }),
                         _,
                     ) => {
-                        self.viewport.update(CompositeMsg {
-                            content_msg: schematic::Msg::ContentMsg(Msg::NetList),
-                            viewport_msg: viewport::Msg::None,
-                        });
-                        self.lib.command("source netlist.cir"); // results pointer array starts at same address
-                        self.lib
-                            .command(&format!("ac lin 0 {} {}", self.ac_hz, self.ac_hz)); // ngspice recommends sending in control statements separately, not as part of netlist
-                        if let Some(pkvecvaluesall) =
-                            self.spmanager.vecvals.try_lock().unwrap().pop()
-                        {
-                            self.viewport.update(CompositeMsg {
-                                content_msg: schematic::Msg::ContentMsg(Msg::Ac(
-                                    pkvecvaluesall.clone(),
-                                )),
-                                viewport_msg: viewport::Msg::None,
-                            });
-                        }
+                        self.run_error = self.run_ac().err();
                     }
                     schematic::Msg::Event(
                         Event::Keyboard(iced::keyboard::Event::KeyPressed {
@@ -240,41 +818,7 @@ impl IcedStruct<CircuitPageMsg> for CircuitSchematicPage {
                         }),
                         _,
                     ) => {
-                        self.viewport.update(CompositeMsg {
-                            content_msg: schematic::Msg::ContentMsg(Msg::NetList),
-                            viewport_msg: viewport::Msg::None,
-                        });
-                        self.lib.command("source netlist.cir"); // results pointer array starts at same address
-                        self.spmanager.vecvals.try_lock().unwrap().clear();
-                        self.lib
-                            .command(&format!("tran {} {}", self.tran_step, self.tran_end)); // ngspice recommends sending in control statements separately, not as part of netlist
-
-                        let pk_results = self.spmanager.vecvals.try_lock().unwrap();
-
-                        let trace_count = pk_results.first().unwrap().count as usize;
-                        let mut results: Vec<Vec<VSPoint>> = Vec::with_capacity(trace_count);
-                        for _ in 0..trace_count {
-                            results.push(Vec::with_capacity(pk_results.len()));
-                        }
-
-                        let x_i = pk_results
-                            .first()
-                            .unwrap()
-                            .vecsa
-                            .iter()
-                            .position(|x| x.name == "time")
-                            .unwrap();
-                        for step_val in pk_results.iter() {
-                            for (trace_i, trace_val) in step_val.vecsa.iter().enumerate() {
-                                results[trace_i].push(VSPoint::new(
-                                    step_val.vecsa[x_i].creal as f32,
-                                    trace_val.creal as f32,
-                                ));
-                            }
-                        }
-                        results.remove(x_i);
-
-                        self.traces = Some(results);
+                        self.run_error = self.run_tran().err();
                     }
                     _ => {
                         self.viewport.update(msgs);
@@ -298,11 +842,80 @@ impl IcedStruct<CircuitPageMsg> for CircuitSchematicPage {
                 }
 
                 self.net_name = self.viewport.content.content.infobarstr.take();
+                if let Some(p) = self.viewport.content.content.probe.take() {
+                    self.probe = Some(p);
+                }
             }
             CircuitPageMsg::HzChanged(s) => self.ac_hz = s,
             CircuitPageMsg::StepChanged(s) => self.tran_step = s,
             CircuitPageMsg::TranChanged(s) => self.tran_end = s,
+            CircuitPageMsg::SweepChanged(s) => self.sweep = s,
+            CircuitPageMsg::RunSweep => {
+                self.run_error = self.run_sweep().err();
+            }
+            CircuitPageMsg::MonteCarloChanged(s) => self.montecarlo = s,
+            CircuitPageMsg::ModelLibChanged(s) => {
+                self.viewport.content.content.set_model_library(&s);
+                self.model_lib = s;
+            }
+            CircuitPageMsg::RunMonteCarlo => {
+                self.run_error = self.run_monte_carlo().err();
+            }
+            CircuitPageMsg::RunCorners => {
+                self.run_error = self.run_corners().err();
+            }
+            CircuitPageMsg::OpOverlay(show) => {
+                self.viewport.update(CompositeMsg {
+                    content_msg: schematic::Msg::ContentMsg(Msg::OpOverlay(show)),
+                    viewport_msg: viewport::Msg::None,
+                });
+            }
+            CircuitPageMsg::RunToChanged(s) => self.run_to = s,
+            CircuitPageMsg::RunTo => self.run_error = self.run_to().err(),
+            CircuitPageMsg::Resume => self.run_error = self.resume().err(),
+            CircuitPageMsg::CodeModelDirsChanged(s) => self.codemodel_dirs = s,
+            CircuitPageMsg::LoadCodeModels => {
+                self.codemodel_result = Some(self.load_codemodels());
+            }
+            CircuitPageMsg::GlobalNameChanged(i, s) => {
+                if let Some(g) = self.globals.get_mut(i) {
+                    g.0 = s;
+                }
+            }
+            CircuitPageMsg::GlobalValueChanged(i, s) => {
+                if let Some(g) = self.globals.get_mut(i) {
+                    g.1 = s;
+                }
+            }
+            CircuitPageMsg::GlobalSubmit(i) => match self.submit_global(i) {
+                Ok(()) => {
+                    self.param_error = None;
+                    self.sync_globals();
+                    // renaming updates the parameters of the devices using it
+                    self.viewport.passive_cache.clear();
+                }
+                Err(e) => self.param_error = Some(e),
+            },
+            CircuitPageMsg::AddGlobal => {
+                self.viewport.content.content.add_param();
+                self.sync_globals();
+            }
+            CircuitPageMsg::DeleteGlobal(i) => {
+                match self.viewport.content.content.remove_param(i) {
+                    Ok(()) => self.param_error = None,
+                    Err(e) => self.param_error = Some(e),
+                }
+                self.sync_globals();
+            }
+            CircuitPageMsg::Highlight(names) => {
+                if self.viewport.content.content.highlight(&names) {
+                    self.viewport.passive_cache.clear();
+                }
+            }
+            CircuitPageMsg::ConsoleAll(all) => self.console_all = all,
+            CircuitPageMsg::Tick => self.poll_job(),
         }
+        self.collect_diagnostics();
     }
 
     fn view(&self) -> Element<CircuitPageMsg> {
@@ -312,7 +925,7 @@ impl IcedStruct<CircuitPageMsg> for CircuitSchematicPage {
             self.viewport.content.content.curpos_ssp().y
         );
         let canvas = self.viewport.view().map(CircuitPageMsg::ViewportEvt);
-        let infobar = row![
+        let mut infobar = row![
             iced::widget::text(str_ssp)
                 .size(16)
                 .height(16)
@@ -327,7 +940,15 @@ impl IcedStruct<CircuitPageMsg> for CircuitSchematicPage {
                 .vertical_alignment(iced::alignment::Vertical::Center),
         ]
         .spacing(10);
-        let toolbar = row![
+        if let Err(e) = &self.lib {
+            infobar = infobar.push(
+                iced::widget::text(e)
+                    .size(16)
+                    .height(16)
+                    .style(iced::Color::from_rgb(1.0, 0.4, 0.4)),
+            );
+        }
+        let mut toolbar = row![
             // button("wire").on_press(CircuitPageMsg::ViewportEvt(viewport::CompositeMsg {
             //     content_msg: schematic::Msg::ContentMsg(Msg::Wire),
             //     viewport_msg: viewport::Msg::None,
@@ -344,6 +965,17 @@ impl IcedStruct<CircuitPageMsg> for CircuitSchematicPage {
             text_input("", &self.tran_end)
                 .width(50)
                 .on_input(CircuitPageMsg::TranChanged),
+            text("run to (S): "),
+            text_input("", &self.run_to)
+                .width(50)
+                .on_input(CircuitPageMsg::RunToChanged)
+                .on_submit(CircuitPageMsg::RunTo),
+            button("run to").on_press(CircuitPageMsg::RunTo),
+            checkbox(
+                "op",
+                self.viewport.content.content.op_overlay(),
+                CircuitPageMsg::OpOverlay
+            ),
             text("Param: "),
             text_input("", &self.param)
                 .width(iced::Length::Fill)
@@ -351,9 +983,269 @@ impl IcedStruct<CircuitPageMsg> for CircuitSchematicPage {
                 .on_submit(CircuitPageMsg::ParamSubmit),
         ]
         .width(Length::Fill);
+        if let Some(t) = self.paused_at {
+            toolbar = toolbar.push(text(format!("paused at {:.3e} s", t)));
+            toolbar = toolbar.push(button("resume").on_press(CircuitPageMsg::Resume));
+        }
+
+        let mut sweepbar = row![
+            text("sweep: "),
+            text_input("e.g. R1 list 1k 2k 5k, temp lin -40 85 25", &self.sweep)
+                .width(iced::Length::Fill)
+                .on_input(CircuitPageMsg::SweepChanged)
+                .on_submit(CircuitPageMsg::RunSweep),
+            button("run sweep").on_press(CircuitPageMsg::RunSweep),
+            text("monte carlo: "),
+            text_input("e.g. runs 20 seed 1 gauss R 5%", &self.montecarlo)
+                .width(iced::Length::Fill)
+                .on_input(CircuitPageMsg::MonteCarloChanged)
+                .on_submit(CircuitPageMsg::RunMonteCarlo),
+            button("run mc").on_press(CircuitPageMsg::RunMonteCarlo),
+            text("model lib: "),
+            text_input("tt/ff/ss sections", &self.model_lib)
+                .width(iced::Length::Fill)
+                .on_input(CircuitPageMsg::ModelLibChanged)
+                .on_submit(CircuitPageMsg::RunCorners),
+            button("run corners").on_press(CircuitPageMsg::RunCorners),
+        ]
+        .spacing(4)
+        .width(Length::Fill);
+        if let Some(e) = &self.run_error {
+            sweepbar = sweepbar.push(text(e).style(iced::Color::from_rgb(1.0, 0.4, 0.4)));
+        }
+
+        let mut paramsbar = row![text("params: ")]
+            .spacing(4)
+            .align_items(iced::Alignment::Center);
+        for (i, (name, value)) in self.globals.iter().enumerate() {
+            paramsbar = paramsbar.push(
+                row![
+                    text_input("name", name)
+                        .width(60)
+                        .on_input(move |s| CircuitPageMsg::GlobalNameChanged(i, s))
+                        .on_submit(CircuitPageMsg::GlobalSubmit(i)),
+                    text("="),
+                    text_input("value", value)
+                        .width(80)
+                        .on_input(move |s| CircuitPageMsg::GlobalValueChanged(i, s))
+                        .on_submit(CircuitPageMsg::GlobalSubmit(i)),
+                    button("x").on_press(CircuitPageMsg::DeleteGlobal(i)),
+                ]
+                .spacing(2)
+                .align_items(iced::Alignment::Center),
+            );
+        }
+        paramsbar = paramsbar.push(button("+").on_press(CircuitPageMsg::AddGlobal));
+        if let Some(e) = &self.param_error {
+            paramsbar = paramsbar.push(text(e).style(iced::Color::from_rgb(1.0, 0.4, 0.4)));
+        }
+
+        let mut settingsbar = row![
+            text("code model dirs: "),
+            text_input("e.g. /usr/lib/ngspice", &self.codemodel_dirs)
+                .width(iced::Length::Fill)
+                .on_input(CircuitPageMsg::CodeModelDirsChanged)
+                .on_submit(CircuitPageMsg::LoadCodeModels),
+            button("load").on_press(CircuitPageMsg::LoadCodeModels),
+        ]
+        .spacing(4)
+        .align_items(iced::Alignment::Center);
+        match &self.codemodel_result {
+            Some(Ok(n)) => {
+                settingsbar = settingsbar.push(text(format!(
+                    "{} loaded, {} in total",
+                    n,
+                    self.codemodels.len()
+                )));
+            }
+            Some(Err(e)) => {
+                settingsbar = settingsbar.push(text(e).style(iced::Color::from_rgb(1.0, 0.4, 0.4)));
+            }
+            None => {}
+        }
+
+        let shown: Vec<&PkDiagnostic> = self
+            .diagnostics
+            .iter()
+            .filter(|d| self.console_all || d.severity <= PkSeverity::Warning)
+            .collect();
+        let lines: Vec<Element<CircuitPageMsg>> = shown
+            .iter()
+            .map(|d| {
+                let color = match d.severity {
+                    PkSeverity::Error => iced::Color::from_rgb(1.0, 0.4, 0.4),
+                    PkSeverity::Warning => iced::Color::from_rgb(1.0, 0.7, 0.2),
+                    PkSeverity::Info => iced::Color::from_rgb(0.7, 0.7, 0.7),
+                };
+                text(&d.message)
+                    .size(14)
+                    .font(iced::Font::MONOSPACE)
+                    .style(color)
+                    .into()
+            })
+            .collect();
+        let console_height = if shown.is_empty() {
+            Length::Shrink
+        } else {
+            Length::Fixed(100.0)
+        };
+        let console = iced::widget::column![
+            row![
+                text(format!(
+                    "console: {} errors, {} warnings",
+                    self.count_diagnostics(PkSeverity::Error),
+                    self.count_diagnostics(PkSeverity::Warning)
+                )),
+                checkbox("all output", self.console_all, CircuitPageMsg::ConsoleAll),
+            ]
+            .spacing(10),
+            iced::widget::scrollable(iced::widget::Column::with_children(lines))
+                .height(console_height),
+        ];
+
+        let mut schematic =
+            iced::widget::column![canvas, infobar, toolbar, sweepbar, paramsbar, settingsbar];
+        if let Some(job) = &self.job {
+            let progress = self.spmanager.progress.lock().unwrap().clone();
+            let fraction = job.fraction(progress.as_ref());
+            let elapsed = job.started.elapsed();
+            let left = eta(elapsed, fraction)
+                .map_or(String::from("-"), |d| format!("{:.1} s", d.as_secs_f64()));
+            let mut status = format!("tran {:.1}%", fraction * 100.0);
+            if job.total > 1 {
+                status += &format!(", run {} of {}", job.total - job.pending.len(), job.total);
+            }
+            if let Some(label) = &job.label {
+                status += &format!(" ({})", label);
+            }
+            schematic = schematic.push(
+                row![
+                    iced::widget::progress_bar(0.0..=1.0, fraction as f32)
+                        .width(200)
+                        .height(16),
+                    text(format!(
+                        "{}, elapsed {:.1} s, ETA {}",
+                        status,
+                        elapsed.as_secs_f64(),
+                        left
+                    )),
+                ]
+                .spacing(10)
+                .align_items(iced::Alignment::Center),
+            );
+        }
+
+        schematic.push(console).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// returns a job of total runs, of which pending are not started yet
+    fn job(total: usize, pending: usize) -> TranJob {
+        let run = || TranRun {
+            label: None,
+            commands: vec![],
+            corner: None,
+            stop_at: None,
+        };
+        TranJob {
+            pending: (0..pending).map(|_| (Rc::from(""), run())).collect(),
+            label: None,
+            total,
+            family: vec![],
+            started: Instant::now(),
+        }
+    }
+
+    #[test]
+    fn fraction_counts_finished_runs() {
+        // run 2 of 4, half done
+        let half = PkProgress::parse("tran: 50.0%");
+        assert_eq!(job(4, 2).fraction(half.as_ref()), 0.375);
+        assert_eq!(job(4, 3).fraction(None), 0.0);
+        let ready = PkProgress::parse("--ready--");
+        assert_eq!(job(1, 0).fraction(ready.as_ref()), 1.0);
+    }
+
+    #[test]
+    fn eta_extrapolates_elapsed_time() {
+        let elapsed = Duration::from_secs(2);
+        assert_eq!(eta(elapsed, 0.25), Some(Duration::from_secs(6)));
+        assert_eq!(eta(elapsed, 1.0), Some(Duration::ZERO));
+        assert_eq!(eta(elapsed, 0.0), None);
+    }
+
+    /// returns a page simulating with the stand-in sharedspice library, which cargo builds next to the tests,
+    /// writing its netlist in dir
+    fn fake_page(dir: &std::path::Path) -> CircuitSchematicPage {
+        let path = std::env::current_exe().unwrap().with_file_name(format!(
+            "{}fakespice{}",
+            std::env::consts::DLL_PREFIX,
+            std::env::consts::DLL_SUFFIX
+        ));
+        let mut page = CircuitSchematicPage::with_lib(|| PkSpice::new(path.as_os_str()));
+        assert!(page.lib.is_ok());
+        page.netlist_path = dir.join("netlist.cir");
+        page
+    }
+
+    /// polls the job on Tick until it ends
+    fn finish(page: &mut CircuitSchematicPage) {
+        let started = Instant::now();
+        while page.is_running() {
+            assert!(
+                started.elapsed() < Duration::from_secs(10),
+                "job did not end"
+            );
+            std::thread::sleep(Duration::from_millis(1));
+            page.update(CircuitPageMsg::Tick);
+        }
+    }
+
+    /// returns the names of the traces shown, and the time the first one ends at
+    fn shown(page: &CircuitSchematicPage) -> (Vec<String>, f32) {
+        let traces = page.traces.as_ref().unwrap();
+        let end = traces[0].points.last().unwrap().x;
+        (traces.iter().map(|t| t.name.clone()).collect(), end)
+    }
+
+    #[test]
+    fn simulation_flow() {
+        let dir = std::env::temp_dir().join(format!("circe_test_flow_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut page = fake_page(&dir);
+        page.run_tran().unwrap();
+        assert!(page.run_tran().is_err());
+        finish(&mut page);
+        let (names, end) = shown(&page);
+        assert_eq!(names, ["v(in)", "v(out)"]);
+        assert!((end - 1e-3).abs() < 1e-9);
+        assert_eq!(page.paused_at, None);
 
-        let schematic = iced::widget::column![canvas, infobar, toolbar];
+        // paused at the run to time, then resumed to the end
+        page.update(CircuitPageMsg::RunTo);
+        finish(&mut page);
+        assert!(page.paused_at.is_some_and(|t| (t - 500e-6).abs() < 1e-9));
+        assert!((shown(&page).1 - 500e-6).abs() < 1e-9);
+        page.update(CircuitPageMsg::Resume);
+        finish(&mut page);
+        assert_eq!(page.paused_at, None);
+        assert!((shown(&page).1 - 1e-3).abs() < 1e-9);
 
-        schematic.into()
+        // a run per sweep value, labeled
+        page.update(CircuitPageMsg::SweepChanged(String::from(
+            "temp list 25 85",
+        )));
+        page.update(CircuitPageMsg::RunSweep);
+        finish(&mut page);
+        assert_eq!(page.run_error, None);
+        let (names, _) = shown(&page);
+        assert_eq!(names.len(), 4);
+        assert!(names.contains(&family_name("v(out)", "temp=85")));
+        assert!(dir.join("netlist.cir").is_file());
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 }
@@ -7,7 +7,8 @@ use crate::schematic::elements::{NetEdge, NetVertex};
 use crate::schematic::layers::Devices;
 use crate::schematic::layers::NetLabels;
 use crate::schematic::layers::Nets;
-use crate::schematic::models::NgModels;
+use crate::schematic::models::{Corner, NgModels};
+use crate::schematic::params::NgParams;
 use crate::schematic::{self, interactable::Interactive, SchematicElement, SchematicMsg};
 use crate::transforms::VSPoint;
 use crate::transforms::{SSPoint, VCTransform, VSBox, VVTransform};
@@ -19,11 +20,14 @@ use send_wrapper::SendWrapper;
 use std::cell::RefCell;
 use std::rc::Rc;
 
-use std::{collections::HashSet, fs};
+use std::{collections::HashSet, path::PathBuf};
 
 mod gui;
+mod montecarlo;
+mod sweep;
 pub use gui::CircuitPageMsg;
 pub use gui::CircuitSchematicPage;
+pub use gui::BG_POLL;
 
 /// trait for a type of element in schematic. e.g. nets or devices
 pub trait SchematicSet {
@@ -116,9 +120,10 @@ impl SchematicElement for CircuitElement {
 pub enum Msg {
     CanvasEvent(Event),
     Wire,
-    NetList,
     DcOp(PkVecvaluesall),
     Ac(PkVecvaluesall),
+    /// show or hide the operating point annotation
+    OpOverlay(bool),
 }
 
 impl schematic::ContentMsg for Msg {
@@ -132,14 +137,29 @@ pub enum CircuitSt {
     #[default]
     Idle,
     Wiring(Option<Box<Nets>>),
+    /// clicking nets or device ports requests their voltage or current on the plot page.
+    /// Holds the first net of a differential voltage while alt-clicking
+    Probing(Option<String>),
 }
 
 /// struct holding schematic state (nets, devices, and their locations)
 #[derive(Default, Clone)]
 pub struct Circuit {
     pub infobarstr: Option<String>,
+    /// trace expression requested by probing, to be plotted, e.g. `v(n1)`
+    pub probe: Option<String>,
 
     state: CircuitSt,
+    /// keyboard modifiers currently held, alt-click probes differential voltages
+    modifiers: Modifiers,
+    /// device current vectors requested by probing, which ngspice must be told to save
+    saves: Vec<String>,
+    /// names of nets highlighted for the traces selected on the plot page
+    highlighted: Vec<String>,
+    /// ids of devices and names of nets flagged by simulation diagnostics
+    flagged: (Vec<String>, Vec<String>),
+    /// hides the operating point annotation
+    op_hidden: bool,
 
     nets: Nets,
     devices: Devices,
@@ -148,6 +168,8 @@ pub struct Circuit {
     curpos_ssp: SSPoint,
 
     device_models: NgModels,
+    /// global parameters, netlisted as `.param`
+    params: NgParams,
 }
 
 impl Circuit {
@@ -208,12 +230,139 @@ impl Circuit {
     fn electrically_occupies_ssp(&self, ssp: SSPoint) -> bool {
         self.nets.occupies_ssp(ssp) || self.devices.any_port_occupy_ssp(ssp)
     }
+    /// probe at ssp: the current into a device port, otherwise the voltage of the net.
+    /// With alt held, two nets clicked in turn give their differential voltage, first holds the net clicked first.
+    fn probe_at(&mut self, ssp: SSPoint, first: &mut Option<String>) -> Option<String> {
+        if self.modifiers.alt() {
+            let net = self.nets.net_name_at(ssp)?;
+            match first.take() {
+                Some(first) => Some(format!("v({})-v({})", first, net)),
+                None => {
+                    *first = Some(net);
+                    None
+                }
+            }
+        } else if let Some((vector, negate)) = self.devices.port_current_at(ssp) {
+            *first = None;
+            if !self.saves.contains(&vector) {
+                self.saves.push(vector.clone());
+            }
+            Some(if negate {
+                format!("-{}", vector)
+            } else {
+                vector
+            })
+        } else {
+            *first = None;
+            self.nets.net_name_at(ssp).map(|net| format!("v({})", net))
+        }
+    }
+    /// returns true if the operating point annotation is shown
+    pub fn op_overlay(&self) -> bool {
+        !self.op_hidden
+    }
+    /// returns the device vectors ngspice must save for the operating point annotation
+    pub fn op_saves(&self) -> Vec<String> {
+        self.devices
+            .op_saves(|model| self.device_models.mos_level(model))
+    }
+    /// returns (id, id prefix, value) of every device with a value, e.g. resistors. Err holds a value given as expression
+    pub fn nominal_values(&self) -> Vec<(String, &'static str, Result<f32, String>)> {
+        self.devices.nominal_values()
+    }
+    /// selects the process corner the models are netlisted for
+    pub fn set_corner(&mut self, corner: Corner) {
+        self.device_models.set_corner(corner);
+    }
+    /// sets the user model library, with a section per corner, or none if path is empty
+    pub fn set_model_library(&mut self, path: &str) {
+        let path = path.trim();
+        self.device_models
+            .set_library((!path.is_empty()).then(|| PathBuf::from(path)));
+    }
+    /// returns the global parameter table
+    pub fn params(&self) -> &NgParams {
+        &self.params
+    }
+    /// checks a device parameter against the global parameter table
+    pub fn validate_param(&self, src: &str) -> Result<(), String> {
+        self.params.validate(src)
+    }
+    /// appends a new global parameter
+    pub fn add_param(&mut self) {
+        self.params.add();
+    }
+    /// removes the global parameter at index i, unless a device or another parameter refers to it
+    pub fn remove_param(&mut self, i: usize) -> Result<(), String> {
+        let Some((name, _)) = self.params.iter().nth(i) else {
+            return Ok(());
+        };
+        let mut users = self.devices.param_users(name);
+        users.extend(self.params.users(name));
+        if !users.is_empty() {
+            return Err(format!("{} is used by {}", name, users.join(", ")));
+        }
+        self.params.remove(i);
+        Ok(())
+    }
+    /// renames the global parameter at index i and sets its value, updating every device and parameter referring to it.
+    /// Nothing is changed unless both the name and the value are valid
+    pub fn set_param(&mut self, i: usize, name: &str, value: String) -> Result<(), String> {
+        let old = self.params.set(i, name, value)?;
+        self.devices.rename_param(&old, name);
+        Ok(())
+    }
+    /// highlight the nets of the named traces, returns true if the highlighted nets changed
+    pub fn highlight(&mut self, trace_names: &[String]) -> bool {
+        let nets: Vec<String> = trace_names.iter().flat_map(|n| trace_nets(n)).collect();
+        if nets == self.highlighted {
+            return false;
+        }
+        self.highlighted = nets;
+        true
+    }
+    /// flags the devices and nets referred to by simulation diagnostics, returns true if the flagged ones changed
+    pub fn flag(&mut self, devices: Vec<String>, nets: Vec<String>) -> bool {
+        let flagged = (devices, nets);
+        if flagged == self.flagged {
+            return false;
+        }
+        self.flagged = flagged;
+        true
+    }
+}
+
+/// returns the nets a trace is the voltage of: a plain vector name is the net name,
+/// and every `v(net)` of a trace expression names a net
+fn trace_nets(name: &str) -> Vec<String> {
+    let lower = name.to_lowercase();
+    let mut ret = vec![];
+    let mut rest = lower.as_str();
+    while let Some(i) = rest.find("v(") {
+        rest = &rest[i + 2..];
+        if let Some(j) = rest.find(')') {
+            ret.push(rest[..j].to_string());
+            rest = &rest[j..];
+        }
+    }
+    if ret.is_empty() && !lower.contains(['(', '@', '#']) {
+        ret.push(lower);
+    }
+    ret
 }
 
 impl Drawable for Circuit {
     fn draw_persistent(&self, vct: VCTransform, vcscale: f32, frame: &mut Frame) {
         self.nets.draw_persistent(vct, vcscale, frame);
+        self.nets
+            .draw_highlighted(&self.highlighted, vct, vcscale, frame);
+        self.nets.draw_flagged(&self.flagged.1, vct, frame);
         self.devices.draw_persistent(vct, vcscale, frame);
+        self.devices
+            .draw_flagged(&self.flagged.0, vct, vcscale, frame);
+        if !self.op_hidden {
+            self.devices.draw_op(vct, vcscale, frame);
+        }
         self.labels.draw_persistent(vct, vcscale, frame);
     }
 
@@ -226,6 +375,10 @@ impl Drawable for Circuit {
             CircuitSt::Wiring(Some(nets)) => {
                 nets.draw_preview(vct, vcscale, frame);
             }
+            CircuitSt::Probing(Some(net)) => {
+                self.nets
+                    .draw_highlighted(std::slice::from_ref(net), vct, vcscale, frame);
+            }
             CircuitSt::Idle => {}
             _ => {}
         }
@@ -299,6 +452,9 @@ impl schematic::Content<CircuitElement, Msg> for Circuit {
                 let mut ret_msg_tmp = SchematicMsg::None;
                 const NO_MODIFIER: Modifiers = Modifiers::empty();
                 match (&mut state, event) {
+                    (_, Event::Keyboard(iced::keyboard::Event::ModifiersChanged(m))) => {
+                        self.modifiers = m;
+                    }
                     // wiring
                     (
                         CircuitSt::Idle,
@@ -333,6 +489,34 @@ impl schematic::Content<CircuitElement, Msg> for Circuit {
                         }
                         state = CircuitSt::Wiring(new_ws);
                     }
+                    // probing
+                    (
+                        CircuitSt::Idle,
+                        Event::Keyboard(iced::keyboard::Event::KeyPressed {
+                            key_code: iced::keyboard::KeyCode::P,
+                            modifiers: Modifiers::SHIFT,
+                        }),
+                    ) => {
+                        state = CircuitSt::Probing(None);
+                    }
+                    (
+                        CircuitSt::Probing(first),
+                        Event::Mouse(iced::mouse::Event::ButtonPressed(iced::mouse::Button::Left)),
+                    ) => {
+                        self.probe = self.probe_at(self.curpos_ssp(), first);
+                        ret_msg_tmp = SchematicMsg::ClearPassive;
+                    }
+                    // operating point annotation
+                    (
+                        CircuitSt::Idle,
+                        Event::Keyboard(iced::keyboard::Event::KeyPressed {
+                            key_code: iced::keyboard::KeyCode::O,
+                            modifiers: NO_MODIFIER,
+                        }),
+                    ) => {
+                        self.op_hidden = !self.op_hidden;
+                        ret_msg_tmp = SchematicMsg::ClearPassive;
+                    }
                     // label
                     (
                         CircuitSt::Idle,
@@ -460,10 +644,6 @@ impl schematic::Content<CircuitElement, Msg> for Circuit {
                 self.state = state;
                 ret_msg_tmp
             }
-            Msg::NetList => {
-                self.netlist();
-                SchematicMsg::None
-            }
             Msg::Wire => {
                 self.state = CircuitSt::Wiring(None);
                 SchematicMsg::None
@@ -476,6 +656,10 @@ impl schematic::Content<CircuitElement, Msg> for Circuit {
                 self.devices.op(&pkvecvaluesall);
                 SchematicMsg::ClearPassive
             }
+            Msg::OpOverlay(show) => {
+                self.op_hidden = !show;
+                SchematicMsg::ClearPassive
+            }
         };
         ret_msg
     }
@@ -585,11 +769,16 @@ impl schematic::Content<CircuitElement, Msg> for Circuit {
 }
 
 impl Circuit {
-    /// create netlist for the current schematic and save it.
-    pub fn netlist(&mut self) {
+    /// returns the netlist of the schematic
+    pub fn netlist_text(&mut self) -> String {
         self.nets.pre_netlist();
         let mut netlist = String::from("Netlist Created by Circe\n");
         netlist.push_str(&self.device_models.model_definitions());
+        netlist.push_str(&self.params.param_definitions());
+        if !self.saves.is_empty() {
+            // device currents are only simulated if saved, along with all node voltages and branch currents
+            netlist.push_str(&format!(".save all {}\n", self.saves.join(" ")));
+        }
         if self.devices.get_set().is_empty() {
             // empty netlist
             netlist.push_str("V_0 0 n1 0"); // give it something so spice doesnt hang
@@ -598,7 +787,7 @@ impl Circuit {
             netlist.push_str(&d.0.borrow_mut().spice_line(&mut self.nets));
         }
         netlist.push('\n');
-        fs::write("netlist.cir", netlist.as_bytes()).expect("Unable to write file");
+        netlist
     }
     /// clear up nets graph: merging segments, cleaning up segment net names, etc.
     fn prune(&mut self) {
@@ -0,0 +1,266 @@
+//! Monte Carlo runs: device values sampled within their tolerance, reproducible from a seed.
+//! A definition is written as e.g. `runs 20 seed 1 gauss R 5% C 10% R3 1%`: tolerances are given per device class (id prefix)
+//! or per device, a device's own tolerance taking precedence. Gaussian tolerances are 3 sigma, clipped to the tolerance.
+
+use crate::analysis::expr::parse_number;
+
+/// most runs a Monte Carlo definition may ask for
+const MAX_RUNS: usize = 1000;
+
+/// distribution of device values within their tolerance
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Distribution {
+    #[default]
+    Uniform,
+    Gaussian,
+}
+
+/// a parsed Monte Carlo definition
+#[derive(Debug, Clone, PartialEq)]
+pub struct MonteCarlo {
+    /// number of runs
+    pub runs: usize,
+    /// seed of the first run, the same seed gives the same device values
+    pub seed: u64,
+    pub distribution: Distribution,
+    /// relative tolerance per lowercase device id or id prefix, e.g. (`r`, 0.05)
+    tolerances: Vec<(String, f32)>,
+}
+
+impl MonteCarlo {
+    /// parses a Monte Carlo definition, e.g. `runs 20 seed 1 gauss R 5% C 10%`
+    pub fn parse(src: &str) -> Result<Self, String> {
+        let mut mc = MonteCarlo {
+            runs: 10,
+            seed: 1,
+            distribution: Distribution::default(),
+            tolerances: vec![],
+        };
+        let mut tokens = src.split_whitespace();
+        while let Some(t) = tokens.next() {
+            match t.to_lowercase().as_str() {
+                "uniform" => mc.distribution = Distribution::Uniform,
+                "gauss" | "gaussian" => mc.distribution = Distribution::Gaussian,
+                "runs" => {
+                    mc.runs = tokens
+                        .next()
+                        .and_then(|n| n.parse().ok())
+                        .filter(|n| (1..=MAX_RUNS).contains(n))
+                        .ok_or_else(|| format!("runs takes a count from 1 to {}", MAX_RUNS))?;
+                }
+                "seed" => {
+                    mc.seed = tokens
+                        .next()
+                        .and_then(|n| n.parse().ok())
+                        .ok_or_else(|| String::from("seed takes a whole number"))?;
+                }
+                dev => {
+                    let tol = tokens
+                        .next()
+                        .ok_or_else(|| format!("missing tolerance of {}", dev))?;
+                    let frac = match tol.strip_suffix('%') {
+                        Some(pct) => parse_number(pct).map(|v| v / 100.0),
+                        None => parse_number(tol),
+                    }
+                    .filter(|v| *v >= 0.0)
+                    .ok_or_else(|| format!("invalid tolerance {}", tol))?;
+                    mc.tolerances.retain(|(d, _)| d != dev);
+                    mc.tolerances.push((dev.to_string(), frac));
+                }
+            }
+        }
+        if mc.tolerances.is_empty() {
+            return Err(String::from("no tolerances given, e.g. R 5%"));
+        }
+        Ok(mc)
+    }
+    /// returns the relative tolerance of the device with id and id prefix, if any
+    fn tolerance(&self, id: &str, prefix: &str) -> Option<f32> {
+        let find = |k: &str| {
+            self.tolerances
+                .iter()
+                .find(|(d, _)| d.eq_ignore_ascii_case(k))
+                .map(|(_, t)| *t)
+        };
+        find(id).or_else(|| find(prefix))
+    }
+    /// returns the ngspice commands setting the device values of every run, to be sent after sourcing the netlist.
+    /// devices are (id, id prefix, nominal value); the result does not depend on their order.
+    /// Returns error if a device with a tolerance has its value given as expression, which cannot be varied
+    pub fn commands(
+        &self,
+        devices: &[(String, &str, Result<f32, String>)],
+    ) -> Result<Vec<Vec<String>>, String> {
+        let mut devices: Vec<_> = devices
+            .iter()
+            .filter_map(|(id, prefix, nominal)| {
+                let tol = self.tolerance(id, prefix).filter(|t| *t > 0.0)?;
+                Some((id, nominal, tol))
+            })
+            .collect();
+        devices.sort_by(|a, b| a.0.cmp(b.0));
+        let expressions: Vec<String> = devices
+            .iter()
+            .filter_map(|(id, nominal, _)| Some(format!("{} {}", id, nominal.as_ref().err()?)))
+            .collect();
+        if !expressions.is_empty() {
+            return Err(format!(
+                "cannot vary values given as expression: {}. Give them a tolerance of 0%",
+                expressions.join(", ")
+            ));
+        }
+        let devices: Vec<_> = devices
+            .into_iter()
+            .filter_map(|(id, nominal, tol)| Some((id, *nominal.as_ref().ok()?, tol)))
+            .collect();
+        let mut rng = Rng::new(self.seed);
+        Ok((0..self.runs)
+            .map(|_| {
+                devices
+                    .iter()
+                    .map(|(id, nominal, tol)| {
+                        let dev = match self.distribution {
+                            Distribution::Uniform => rng.uniform() * 2.0 - 1.0,
+                            Distribution::Gaussian => (rng.gaussian() / 3.0).clamp(-1.0, 1.0),
+                        };
+                        let value = *nominal as f64 * (1.0 + *tol as f64 * dev);
+                        format!("alter {} = {:e}", id.to_lowercase(), value)
+                    })
+                    .collect()
+            })
+            .collect())
+    }
+}
+
+/// splitmix64 generator: small, seedable, and the same sequence on every platform and build
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+    /// uniform in [0, 1)
+    fn uniform(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+    /// standard normal, by the Box-Muller transform
+    fn gaussian(&mut self) -> f64 {
+        let u1 = 1.0 - self.uniform(); // (0, 1]
+        let u2 = self.uniform();
+        (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn devices() -> Vec<(String, &'static str, Result<f32, String>)> {
+        vec![
+            (String::from("R1"), "R", Ok(1e3)),
+            (String::from("R3"), "R", Ok(10e3)),
+            (String::from("C1"), "C", Ok(1e-6)),
+            (String::from("L1"), "L", Ok(1e-3)),
+        ]
+    }
+
+    /// returns the value set by each command of a run, e.g. `alter r1 = 1.02e3`
+    fn values(run: &[String]) -> Vec<f64> {
+        run.iter()
+            .map(|c| c.rsplit(' ').next().unwrap().parse().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn parse() {
+        let mc = MonteCarlo::parse("runs 20 seed 7 gauss R 5% C 0.5 R3 1% r 2%").unwrap();
+        assert_eq!(mc.runs, 20);
+        assert_eq!(mc.seed, 7);
+        assert_eq!(mc.distribution, Distribution::Gaussian);
+        // a later tolerance of the same class replaces the earlier one
+        assert_eq!(mc.tolerance("R1", "R"), Some(0.02));
+        assert_eq!(mc.tolerance("R3", "R"), Some(0.01));
+        assert_eq!(mc.tolerance("C1", "C"), Some(0.5));
+        assert_eq!(mc.tolerance("L1", "L"), None);
+
+        let mc = MonteCarlo::parse("C 1%").unwrap();
+        assert_eq!((mc.runs, mc.seed), (10, 1));
+        assert_eq!(mc.distribution, Distribution::Uniform);
+
+        for src in [
+            "",
+            "runs 20",
+            "runs 0 R 5%",
+            "runs 1001 R 5%",
+            "seed x R 5%",
+            "R",
+            "R -5%",
+            "R x%",
+        ] {
+            assert!(MonteCarlo::parse(src).is_err(), "{}", src);
+        }
+    }
+
+    #[test]
+    fn reproducible() {
+        let mc = MonteCarlo::parse("runs 5 seed 3 R 5% C 10%").unwrap();
+        let runs = mc.commands(&devices()).unwrap();
+        assert_eq!(runs.len(), 5);
+        assert_eq!(runs[0].len(), 3);
+        assert!(runs[0][0].starts_with("alter c1 = "));
+        assert_ne!(runs[0], runs[1]);
+        // the same seed gives the same values, regardless of device order
+        let mut reversed = devices();
+        reversed.reverse();
+        assert_eq!(mc.commands(&reversed).unwrap(), runs);
+        let other = MonteCarlo::parse("runs 5 seed 4 R 5% C 10%").unwrap();
+        assert_ne!(other.commands(&devices()).unwrap(), runs);
+    }
+
+    #[test]
+    fn within_tolerance() {
+        for dist in ["uniform", "gauss"] {
+            let mc = MonteCarlo::parse(&format!("runs 1000 {} R 5%", dist)).unwrap();
+            let runs = mc.commands(&devices()[..1]).unwrap();
+            let values: Vec<f64> = runs.iter().flat_map(|r| values(r)).collect();
+            // clipped to the tolerance, up to rounding
+            assert!(
+                values.iter().all(|v| (v / 1e3 - 1.0).abs() <= 0.05 + 1e-6),
+                "{}",
+                dist
+            );
+            let mean = values.iter().sum::<f64>() / values.len() as f64;
+            assert!((mean - 1e3).abs() < 5.0, "{}: {}", dist, mean);
+        }
+        // gaussian tolerances are 3 sigma: the 1 sigma band holds about 68% of the values
+        let mc = MonteCarlo::parse("runs 1000 gauss R 3%").unwrap();
+        let runs = mc.commands(&devices()[..1]).unwrap();
+        let inside = runs
+            .iter()
+            .flat_map(|r| values(r))
+            .filter(|v| (990.0..=1010.0).contains(v))
+            .count();
+        assert!((620..=740).contains(&inside), "{}", inside);
+    }
+
+    #[test]
+    fn expression_values() {
+        let mut devices = devices();
+        devices.push((String::from("R4"), "R", Err(String::from("{rload*2}"))));
+        let mc = MonteCarlo::parse("R 5%").unwrap();
+        let e = mc.commands(&devices).unwrap_err();
+        assert!(e.contains("R4 {rload*2}"), "{}", e);
+        // unless left out
+        let mc = MonteCarlo::parse("R 5% R4 0%").unwrap();
+        assert_eq!(mc.commands(&devices).unwrap()[0].len(), 2);
+        let mc = MonteCarlo::parse("C 5%").unwrap();
+        assert_eq!(mc.commands(&devices).unwrap()[0].len(), 1);
+    }
+}
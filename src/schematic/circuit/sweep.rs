@@ -0,0 +1,235 @@
+//! Parameter sweeps: re-running an analysis for a list or range of values, similar to `.step`.
+//! A sweep is written as `<target> <range>`, e.g. `R1 list 1k 2k 5k`, `param rload log 1k 1meg 3`, `temp lin -40 85 25`.
+//! Targets are `temp`, `param <name>` for a `.param`, a device (its value, e.g. `R1`) or a device parameter (e.g. `@mn1[w]`).
+//! Ranges are `list <values..>`, `lin <start> <stop> <step>` and `log <start> <stop> <points per decade>`.
+
+use crate::analysis::expr::parse_number;
+
+/// most runs a sweep may expand to
+const MAX_RUNS: usize = 100;
+
+/// what a sweep varies
+#[derive(Debug, Clone, PartialEq)]
+pub enum Target {
+    /// circuit temperature
+    Temp,
+    /// a global `.param`
+    Param(String),
+    /// a device value, e.g. `r1`, or device parameter, e.g. `@mn1[w]`
+    Device(String),
+}
+
+/// a parsed sweep definition
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sweep {
+    target: Target,
+    /// values in order of the runs, as passed to ngspice
+    values: Vec<String>,
+}
+
+impl Sweep {
+    /// parses a sweep definition, e.g. `R1 list 1k 2k 5k`
+    pub fn parse(src: &str) -> Result<Self, String> {
+        let mut tokens = src.split_whitespace();
+        let target = match tokens.next().map(|t| t.to_lowercase()) {
+            None => return Err(String::from("empty sweep")),
+            Some(t) if t == "temp" => Target::Temp,
+            Some(t) if t == "param" => match tokens.next() {
+                Some(name) => Target::Param(name.to_lowercase()),
+                None => return Err(String::from("missing parameter name")),
+            },
+            Some(t) => Target::Device(t),
+        };
+        let kind = tokens.next().map(|t| t.to_lowercase()).unwrap_or_default();
+        let args: Vec<&str> = tokens.collect();
+        let number = |s: &str| parse_number(s).ok_or_else(|| format!("invalid value {}", s));
+        let values = match kind.as_str() {
+            "list" => {
+                for a in &args {
+                    number(a)?;
+                }
+                args.iter().map(|a| a.to_string()).collect()
+            }
+            "lin" | "log" => {
+                let [start, stop, n] = args[..] else {
+                    return Err(format!("{} takes start, stop and step", kind));
+                };
+                let (start, stop, n) = (number(start)?, number(stop)?, number(n)?);
+                if kind == "lin" {
+                    lin(start, stop, n)?
+                } else {
+                    log(start, stop, n)?
+                }
+            }
+            "" => return Err(String::from("missing range: list, lin or log")),
+            k => return Err(format!("unknown range {}, expected list, lin or log", k)),
+        };
+        if values.is_empty() {
+            return Err(String::from("sweep has no values"));
+        }
+        if values.len() > MAX_RUNS {
+            return Err(format!("sweep has more than {} runs", MAX_RUNS));
+        }
+        Ok(Sweep { target, values })
+    }
+    /// returns the values of the runs, in order
+    pub fn values(&self) -> &[String] {
+        &self.values
+    }
+    /// returns the ngspice commands setting the target to value, to be sent after sourcing the netlist
+    pub fn commands(&self, value: &str) -> Vec<String> {
+        match &self.target {
+            Target::Temp => vec![format!("option temp = {}", value)],
+            Target::Param(name) => vec![
+                format!("alterparam {} = {}", name, value),
+                String::from("reset"),
+            ],
+            Target::Device(dev) => vec![format!("alter {} = {}", dev, value)],
+        }
+    }
+    /// returns the label of the run with value, e.g. `r1=2k`
+    pub fn label(&self, value: &str) -> String {
+        match &self.target {
+            Target::Temp => format!("temp={}", value),
+            Target::Param(name) | Target::Device(name) => format!("{}={}", name, value),
+        }
+    }
+}
+
+/// values from start to stop (inclusive) in increments of step
+fn lin(start: f32, stop: f32, step: f32) -> Result<Vec<String>, String> {
+    if step == 0.0 || (stop - start) * step < 0.0 {
+        return Err(String::from("step does not lead from start to stop"));
+    }
+    let n = runs((stop - start) / step)?;
+    Ok((0..n).map(|i| spice_fmt(start + step * i as f32)).collect())
+}
+
+/// values from start to stop (inclusive), logarithmically spaced with points per decade
+fn log(start: f32, stop: f32, per_decade: f32) -> Result<Vec<String>, String> {
+    if start <= 0.0 || stop <= 0.0 || per_decade < 1.0 {
+        return Err(String::from(
+            "log range needs positive start and stop, and at least 1 point per decade",
+        ));
+    }
+    let decades = (stop / start).log10();
+    let n = runs(decades.abs() * per_decade)?;
+    let step = decades.signum() / per_decade;
+    Ok((0..n)
+        .map(|i| spice_fmt(start * 10f32.powf(step * i as f32)))
+        .collect())
+}
+
+/// returns the number of runs of a range spanning steps, counting both ends.
+/// Checked before converting, as a tiny step makes steps too large for a usize
+fn runs(steps: f32) -> Result<usize, String> {
+    let n = (steps + 1e-3).floor() + 1.0;
+    if n.is_nan() || n > MAX_RUNS as f32 {
+        return Err(format!("sweep has more than {} runs", MAX_RUNS));
+    }
+    Ok(n as usize)
+}
+
+/// formats v with a spice scale suffix, e.g. `4.7k`, `1meg`
+fn spice_fmt(v: f32) -> String {
+    const SUFFIXES: [(f32, &str); 9] = [
+        (1e12, "t"),
+        (1e9, "g"),
+        (1e6, "meg"),
+        (1e3, "k"),
+        (1.0, ""),
+        (1e-3, "m"),
+        (1e-6, "u"),
+        (1e-9, "n"),
+        (1e-12, "p"),
+    ];
+    match SUFFIXES
+        .iter()
+        .find(|(scale, _)| v.abs() >= *scale * 0.9995)
+    {
+        Some((scale, sfx)) => {
+            let s = format!("{:.4}", v / scale);
+            let s = s.trim_end_matches('0').trim_end_matches('.');
+            format!("{}{}", s, sfx)
+        }
+        None if v == 0.0 => String::from("0"),
+        None => format!("{:e}", v),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let sweep = Sweep::parse("R1 list 1k 2k 5k").unwrap();
+        assert_eq!(sweep.target, Target::Device(String::from("r1")));
+        assert_eq!(sweep.values(), ["1k", "2k", "5k"]);
+        assert_eq!(sweep.label("2k"), "r1=2k");
+        assert_eq!(sweep.commands("2k"), ["alter r1 = 2k"]);
+
+        let sweep = Sweep::parse("param Rload log 1k 1meg 1").unwrap();
+        assert_eq!(sweep.target, Target::Param(String::from("rload")));
+        assert_eq!(sweep.values(), ["1k", "10k", "100k", "1meg"]);
+        assert_eq!(sweep.commands("1k"), ["alterparam rload = 1k", "reset"]);
+
+        let sweep = Sweep::parse("temp lin -40 85 25").unwrap();
+        assert_eq!(sweep.values(), ["-40", "-15", "10", "35", "60", "85"]);
+        assert_eq!(sweep.label("10"), "temp=10");
+
+        assert_eq!(Sweep::parse(""), Err(String::from("empty sweep")));
+        assert_eq!(
+            Sweep::parse("r1 list 1k x"),
+            Err(String::from("invalid value x"))
+        );
+        assert!(Sweep::parse("r1").is_err());
+        assert!(Sweep::parse("r1 foo 1").is_err());
+        assert!(Sweep::parse("r1 lin 1 2").is_err());
+        assert!(Sweep::parse("param").is_err());
+    }
+
+    #[test]
+    fn lin_range() {
+        assert_eq!(lin(1.0, 2.0, 0.5).unwrap(), ["1", "1.5", "2"]);
+        assert_eq!(lin(5.0, 1.0, -2.0).unwrap(), ["5", "3", "1"]);
+        // stop need not be a whole number of steps from start
+        assert_eq!(lin(0.0, 1.0, 0.4).unwrap(), ["0", "400m", "800m"]);
+        assert!(lin(0.0, 1.0, 0.0).is_err());
+        assert!(lin(0.0, 1.0, -0.5).is_err());
+        assert!(lin(0.0, 1000.0, 1.0).is_err());
+        // more steps than a usize holds
+        assert!(lin(0.0, 1.0, 1e-30).is_err());
+        assert!(Sweep::parse("R1 lin 0 1 1e-30").is_err());
+    }
+
+    #[test]
+    fn log_range() {
+        assert_eq!(
+            log(1.0, 100.0, 2.0).unwrap(),
+            ["1", "3.1623", "10", "31.6228", "100"]
+        );
+        assert_eq!(log(1e6, 1e3, 1.0).unwrap(), ["1meg", "100k", "10k", "1k"]);
+        assert!(log(0.0, 1.0, 1.0).is_err());
+        assert!(log(1.0, 10.0, 0.5).is_err());
+        assert!(log(1e-12, 1e12, 10.0).is_err());
+        assert!(log(1.0, 10.0, 1e30).is_err());
+        assert!(Sweep::parse("R1 log 1 10 1e30").is_err());
+    }
+
+    #[test]
+    fn spice_format() {
+        assert_eq!(spice_fmt(4700.0), "4.7k");
+        assert_eq!(spice_fmt(1e6), "1meg");
+        assert_eq!(spice_fmt(2.2e-6), "2.2u");
+        assert_eq!(spice_fmt(1e-3), "1m");
+        assert_eq!(spice_fmt(-15.0), "-15");
+        assert_eq!(spice_fmt(0.0), "0");
+        assert_eq!(spice_fmt(1e-15), "1e-15");
+        // formatted values parse back
+        for v in [4.7e3, 33e-9, 1.5e6, 0.25] {
+            let s = spice_fmt(v);
+            assert!((parse_number(&s).unwrap() / v - 1.0).abs() < 1e-4, "{}", s);
+        }
+    }
+}
@@ -3,6 +3,7 @@
 use std::hash::Hasher;
 
 use super::devicetype::DeviceClass;
+use crate::analysis::expr::parse_number;
 
 use iced::{
     widget::canvas::{Frame, Text},
@@ -89,12 +90,33 @@ pub struct Device {
     connected_nets: Vec<String>,
     /// vector of the connect net voltages in order of device ports
     op: Vec<num::Complex<f32>>,
+    /// currents into the device ports in order, where saved (see `op_saves`)
+    op_currents: Vec<Option<f32>>,
+    /// mosfet transconductance, where the model provides it
+    op_gm: Option<f32>,
+    /// mosfet threshold voltage, where the model provides it
+    op_vth: Option<f32>,
 }
 impl Device {
     /// sets the device identifier watermark
     pub fn set_wm(&mut self, wm: usize) {
         self.id.wm = wm;
     }
+    /// returns the device id used in the netlist, e.g. R1
+    pub fn ng_id(&self) -> String {
+        self.id.ng_id()
+    }
+    /// returns the device value, i.e. the leading number of its parameter e.g. `1k` of a resistor, if it has one.
+    /// Err holds a value given as expression, e.g. `{rload*2}`, which is only known to ngspice
+    pub fn nominal_value(&self) -> Option<Result<f32, String>> {
+        let summary = self.class.param_summary();
+        let summary = summary.trim_start();
+        if summary.starts_with('{') {
+            let end = summary.find('}').map_or(summary.len(), |i| i + 1);
+            return Some(Err(summary[..end].to_string()));
+        }
+        parse_number(summary.split_whitespace().next()?).map(Ok)
+    }
     /// returns a reference to the device class
     pub fn class(&self) -> &DeviceClass {
         &self.class
@@ -112,6 +134,9 @@ impl Device {
             class,
             connected_nets: vec![],
             op: vec![],
+            op_currents: vec![],
+            op_gm: None,
+            op_vth: None,
         }
     }
     /// returns the schematic coordiantes of the devices ports in order
@@ -132,6 +157,56 @@ impl Device {
         }
         false
     }
+    /// returns the ngspice vector of the current into the port at index, e.g. `@r1[i]`,
+    /// and whether it must be negated because ngspice measures it flowing out of that port
+    fn port_current(&self, port: usize) -> Option<(String, bool)> {
+        let (param, negate) = self.class.port_current(port)?;
+        Some((
+            format!("@{}[{}]", self.id.ng_id().to_lowercase(), param),
+            negate,
+        ))
+    }
+    /// returns the ngspice vector of the current into the port at ssp, e.g. `@r1[i]`,
+    /// and whether it must be negated because ngspice measures it flowing out of that port
+    pub fn port_current_at(&self, ssp: SSPoint) -> Option<(String, bool)> {
+        let port = self
+            .class
+            .graphics()
+            .ports()
+            .iter()
+            .position(|p| self.transform.transform_point(p.offset) == ssp)?;
+        self.port_current(port)
+    }
+    /// returns the name of the model of a mosfet, the first word of its parameter, e.g. `mosn`
+    pub fn mos_model(&self) -> Option<String> {
+        match self.class {
+            DeviceClass::Nm(_) | DeviceClass::Pm(_) => {
+                let summary = self.class.param_summary();
+                summary.split_whitespace().next().map(str::to_string)
+            }
+            _ => None,
+        }
+    }
+    /// returns the device vectors ngspice must save for the operating point annotation.
+    /// mos_level is the level of a mosfet's model, if known, which decides what its threshold voltage is called
+    pub fn op_saves(&self, mos_level: Option<u32>) -> Vec<String> {
+        let mut ret: Vec<String> = vec![];
+        for i in 0..self.class.graphics().ports().len() {
+            if let Some((v, _)) = self.port_current(i) {
+                if !ret.contains(&v) {
+                    ret.push(v);
+                }
+            }
+        }
+        if matches!(self.class, DeviceClass::Nm(_) | DeviceClass::Pm(_)) {
+            let id = self.id.ng_id().to_lowercase();
+            ret.push(format!("@{}[gm]", id));
+            if let Some(level) = mos_level {
+                ret.push(format!("@{}[{}]", id, threshold_param(level)));
+            }
+        }
+        ret
+    }
     /// returns the composite of the device's transform and the given vct
     fn compose_transform(&self, vct: VCTransform) -> VCTransform {
         sst_to_vvt(self.transform).then(&vct)
@@ -169,19 +244,148 @@ impl Device {
     }
     /// fill in the operating point for the device
     pub fn op(&mut self, pkvecvaluesall: &paprika::PkVecvaluesall) {
+        let find = |name: &str| {
+            pkvecvaluesall
+                .vecsa
+                .iter()
+                .find(|v| v.name.eq_ignore_ascii_case(name))
+        };
+        // device quantities are only annotated from real (dc) results
+        let real = |name: &str| find(name).filter(|v| !v.is_complex).map(|v| v.creal as f32);
+
         self.op.clear();
         for n in &self.connected_nets {
-            for v in &pkvecvaluesall.vecsa {
-                if &v.name == n {
-                    let pkval = (*v.as_ref()).clone();
-                    self.op.push(pkval.into());
-                    break;
-                }
+            if let Some(v) = find(n) {
+                let pkval = (*v.as_ref()).clone();
+                self.op.push(pkval.into());
             }
         }
+        self.op_currents = (0..self.class.graphics().ports().len())
+            .map(|i| {
+                let (v, negate) = self.port_current(i)?;
+                real(&v).map(|c| if negate { -c } else { c })
+            })
+            .collect();
+        let id = self.id.ng_id().to_lowercase();
+        self.op_gm = real(&format!("@{}[gm]", id));
+        self.op_vth = real(&format!("@{}[vth]", id)).or_else(|| real(&format!("@{}[von]", id)));
+    }
+    /// returns the power dissipated in the device, if the voltage and current of every port is known
+    fn op_power(&self) -> Option<f32> {
+        if self.op.len() != self.op_currents.len() || self.op.iter().any(|v| v.im != 0.0) {
+            return None;
+        }
+        self.op
+            .iter()
+            .zip(&self.op_currents)
+            .map(|(v, i)| i.map(|i| v.re * i))
+            .sum()
+    }
+    /// returns the mosfet operating region, from the port voltages (drain, gate, source) and threshold voltage
+    fn op_region(&self) -> Option<&'static str> {
+        let sign = match self.class {
+            DeviceClass::Nm(_) => 1.0,
+            DeviceClass::Pm(_) => -1.0,
+            _ => return None,
+        };
+        let vth = self.op_vth?.abs();
+        let (vd, vg, vs) = (self.op.first()?, self.op.get(1)?, self.op.get(2)?);
+        let vov = sign * (vg.re - vs.re) - vth;
+        let vds = sign * (vd.re - vs.re);
+        Some(if vov <= 0.0 {
+            "cutoff"
+        } else if vds < vov {
+            "triode"
+        } else {
+            "saturation"
+        })
+    }
+    /// draws the operating point: net voltages at the ports, and current, power and mosfet figures under the parameters
+    pub fn draw_op(&self, vct: VCTransform, vcscale: f32, frame: &mut Frame) {
+        let vct_c = self.compose_transform(vct);
+
+        let ports = self.class.graphics().ports();
+        for (i, v) in self.op.iter().enumerate() {
+            let b = Text {
+                content: if v.im != 0.0 {
+                    format! {"{:+.2e}", v}
+                } else {
+                    eng(v.re, "V")
+                },
+                position: Point::from(vct_c.transform_point(ports[i].offset.cast().cast_unit()))
+                    .into(),
+                color: Color::from_rgba(1.0, 1.0, 1.0, 1.0),
+                size: vcscale,
+                ..Default::default()
+            };
+            frame.fill_text(b);
+        }
+
+        let mut lines = vec![];
+        if let Some(Some(i)) = self.op_currents.first() {
+            lines.push(eng(*i, "A"));
+        }
+        if let Some(p) = self.op_power() {
+            lines.push(eng(p, "W"));
+        }
+        if let Some(region) = self.op_region() {
+            lines.push(region.to_string());
+        }
+        if let Some(gm) = self.op_gm {
+            lines.push(format!("gm {}", eng(gm, "S")));
+        }
+        if let Some(vth) = self.op_vth {
+            lines.push(format!("vth {}", eng(vth, "V")));
+        }
+        for (i, l) in lines.into_iter().enumerate() {
+            let t = Text {
+                content: l,
+                position: Point::from(vct_c.transform_point(VSPoint::new(1.0, -1.0 - i as f32)))
+                    .into(),
+                color: Color::from_rgba(1.0, 1.0, 0.5, 1.0),
+                size: vcscale,
+                ..Default::default()
+            };
+            frame.fill_text(t);
+        }
+    }
+    /// draws the device as flagged by a simulation diagnostic
+    pub fn draw_flagged(&self, vct: VCTransform, vcscale: f32, frame: &mut Frame) {
+        let vct_c = self.compose_transform(vct);
+        self.class.graphics().draw_flagged(vct_c, vcscale, frame);
+    }
+}
+
+/// returns the name of the threshold voltage of mosfet models of level: the spice models (levels 1-3, 6 and 9)
+/// call it von, the bsim and other later models vth
+fn threshold_param(level: u32) -> &'static str {
+    match level {
+        1..=3 | 6 | 9 => "von",
+        _ => "vth",
     }
 }
 
+/// formats v with an engineering prefix and unit, e.g. `1.50mA`
+fn eng(v: f32, unit: &str) -> String {
+    const PREFIXES: [(f32, &str); 9] = [
+        (1e9, "G"),
+        (1e6, "M"),
+        (1e3, "k"),
+        (1.0, ""),
+        (1e-3, "m"),
+        (1e-6, "µ"),
+        (1e-9, "n"),
+        (1e-12, "p"),
+        (1e-15, "f"),
+    ];
+    let (scale, prefix) = PREFIXES
+        .iter()
+        .find(|(scale, _)| v.abs() >= *scale)
+        .copied()
+        .unwrap_or(if v == 0.0 { (1.0, "") } else { PREFIXES[8] });
+    format!("{:.3}{}{}", v / scale, prefix, unit)
+}
+
 impl Drawable for Device {
     fn draw_persistent(&self, vct: VCTransform, vcscale: f32, frame: &mut Frame) {
         let vct_c = self.compose_transform(vct);
@@ -204,23 +408,6 @@ impl Drawable for Device {
             ..Default::default()
         };
         frame.fill_text(b);
-
-        let ports = self.class.graphics().ports();
-        for (i, v) in self.op.iter().enumerate() {
-            let b = Text {
-                content: if v.im != 0.0 {
-                    format! {"{:+.2e}", v}
-                } else {
-                    format! {"{:+.2e}", v.re}
-                },
-                position: Point::from(vct_c.transform_point(ports[i].offset.cast().cast_unit()))
-                    .into(),
-                color: Color::from_rgba(1.0, 1.0, 1.0, 1.0),
-                size: vcscale,
-                ..Default::default()
-            };
-            frame.fill_text(b);
-        }
     }
     fn draw_selected(&self, vct: VCTransform, vcscale: f32, frame: &mut Frame) {
         let vct_c = self.compose_transform(vct);
@@ -242,3 +429,154 @@ impl Interactive for Device {
             .cast_unit();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schematic::elements::device::devicetype::{d, nmos, pmos, r};
+    use paprika::{PkVecvalues, PkVecvaluesall};
+
+    /// returns the real operating point values (name, value)
+    fn values(vs: &[(&str, f64)]) -> PkVecvaluesall {
+        PkVecvaluesall {
+            count: vs.len() as i32,
+            index: 0,
+            vecsa: vs
+                .iter()
+                .map(|(name, v)| {
+                    Box::new(PkVecvalues {
+                        name: name.to_string(),
+                        creal: *v,
+                        cimag: 0.0,
+                        is_scale: false,
+                        is_complex: false,
+                    })
+                })
+                .collect(),
+        }
+    }
+
+    /// returns a device of class connected to nets, with the operating point of the vectors (name, value).
+    /// `@id` in a name stands for the device's lowercase id, e.g. `@mn1[id]`
+    fn device(class: DeviceClass, nets: &[&str], vs: &[(&str, f64)]) -> Device {
+        let mut device = Device::new_with_ord_class(1, class);
+        device.connected_nets = nets.iter().map(|n| n.to_string()).collect();
+        let id = format!("@{}", device.ng_id().to_lowercase());
+        let vs: Vec<(String, f64)> = vs
+            .iter()
+            .map(|(n, v)| (n.replace("@id", &id), *v))
+            .collect();
+        let vs: Vec<(&str, f64)> = vs.iter().map(|(n, v)| (n.as_str(), *v)).collect();
+        device.op(&values(&vs));
+        device
+    }
+
+    /// returns an nmos or pmos with the port voltages (vd, vg, vs) and threshold von
+    fn mos(class: DeviceClass, (vd, vg, vs): (f64, f64, f64), von: f64) -> Device {
+        let vs = [
+            ("d", vd),
+            ("g", vg),
+            ("s", vs),
+            ("b", vs),
+            ("@id[von]", von),
+        ];
+        device(class, &["d", "g", "s", "b"], &vs)
+    }
+
+    #[test]
+    fn eng_prefixes() {
+        assert_eq!(eng(1.5e-3, "A"), "1.500mA");
+        assert_eq!(eng(-2200.0, "W"), "-2.200kW");
+        assert_eq!(eng(2e-6, "A"), "2.000µA");
+        assert_eq!(eng(0.0, "V"), "0.000V");
+        // below the smallest prefix
+        assert_eq!(eng(1e-18, "A"), "0.001fA");
+    }
+
+    #[test]
+    fn regions() {
+        let nm = || DeviceClass::Nm(nmos::M::default());
+        assert_eq!(mos(nm(), (2.0, 0.4, 0.0), 0.5).op_region(), Some("cutoff"));
+        assert_eq!(mos(nm(), (0.5, 1.5, 0.0), 0.5).op_region(), Some("triode"));
+        assert_eq!(
+            mos(nm(), (2.0, 1.5, 0.0), 0.5).op_region(),
+            Some("saturation")
+        );
+        // overdrive exactly at the drain source voltage
+        assert_eq!(
+            mos(nm(), (1.0, 1.5, 0.0), 0.5).op_region(),
+            Some("saturation")
+        );
+        // pmos voltages and threshold are negated
+        let pm = || DeviceClass::Pm(pmos::M::default());
+        assert_eq!(mos(pm(), (0.0, 2.6, 3.0), -0.5).op_region(), Some("cutoff"));
+        assert_eq!(mos(pm(), (2.5, 1.5, 3.0), -0.5).op_region(), Some("triode"));
+        assert_eq!(
+            mos(pm(), (0.0, 1.5, 3.0), -0.5).op_region(),
+            Some("saturation")
+        );
+        // without a threshold, or not a mosfet
+        let r = device(DeviceClass::R(r::R::default()), &["a", "b"], &[]);
+        assert_eq!(r.op_region(), None);
+    }
+
+    #[test]
+    fn currents_and_power() {
+        // ngspice measures the current of two terminal devices into their first port, out of the second
+        let r = device(
+            DeviceClass::R(r::R::default()),
+            &["a", "b"],
+            &[("a", 3.0), ("b", 1.0), ("@id[i]", 1e-3)],
+        );
+        assert_eq!(r.op_currents, [Some(1e-3), Some(-1e-3)]);
+        assert!((r.op_power().unwrap() - 2e-3).abs() < 1e-9);
+        let d = device(
+            DeviceClass::D(d::D::default()),
+            &["a", "k"],
+            &[("a", 0.7), ("k", 0.0), ("@id[id]", 2e-3)],
+        );
+        assert_eq!(d.op_currents, [Some(2e-3), Some(-2e-3)]);
+        // the power sums over all ports
+        let m = device(
+            DeviceClass::Nm(nmos::M::default()),
+            &["d", "g", "s", "b"],
+            &[
+                ("d", 2.0),
+                ("g", 1.0),
+                ("s", 0.5),
+                ("b", 0.0),
+                ("@id[id]", 1e-3),
+                ("@id[ig]", 0.0),
+                ("@id[is]", -1e-3),
+                ("@id[ib]", 0.0),
+            ],
+        );
+        assert!((m.op_power().unwrap() - 1.5e-3).abs() < 1e-9);
+        // unknown without the current of every port
+        let m = mos(DeviceClass::Nm(nmos::M::default()), (2.0, 1.0, 0.0), 0.5);
+        assert_eq!(m.op_power(), None);
+    }
+
+    #[test]
+    fn threshold_saves() {
+        let m = Device::new_with_ord_class(1, DeviceClass::Nm(nmos::M::default()));
+        assert_eq!(m.mos_model().as_deref(), Some("mosn"));
+        assert!(m.op_saves(Some(1)).contains(&String::from("@mn1[von]")));
+        let bsim = m.op_saves(Some(54));
+        assert!(bsim.contains(&String::from("@mn1[vth]")));
+        assert!(!bsim.contains(&String::from("@mn1[von]")));
+        // a model only ngspice knows
+        let unknown = m.op_saves(None);
+        assert!(unknown.contains(&String::from("@mn1[gm]")));
+        assert!(!unknown
+            .iter()
+            .any(|v| v.ends_with("[vth]") || v.ends_with("[von]")));
+        // the threshold is read under either name
+        let bsim = device(
+            DeviceClass::Nm(nmos::M::default()),
+            &["d", "g", "s", "b"],
+            &[("@id[vth]", 0.4)],
+        );
+        assert_eq!(bsim.op_vth, Some(0.4));
+    }
+}
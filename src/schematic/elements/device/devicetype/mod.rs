@@ -74,6 +74,27 @@ impl Graphics {
         }
         frame.stroke(&path_builder.build(), stroke.clone());
     }
+    /// draws the symbol as flagged by a simulation diagnostic, distinct from selected and highlighted
+    pub fn draw_flagged(&self, vct: VCTransform, vcscale: f32, frame: &mut Frame) {
+        let stroke = Stroke {
+            width: 2.0 * STROKE_WIDTH,
+            style: stroke::Style::Solid(Color::from_rgb(1.0, 0.4, 0.4)),
+            line_cap: LineCap::Round,
+            ..Stroke::default()
+        };
+        self.stroke_bounds(
+            vct,
+            frame,
+            Stroke {
+                line_dash: LineDash {
+                    segments: &[2.0],
+                    offset: 0,
+                },
+                ..stroke.clone()
+            },
+        );
+        self.stroke_symbol(vct, vcscale, frame, stroke);
+    }
 }
 impl Drawable for Graphics {
     fn draw_persistent(&self, vct: VCTransform, vcscale: f32, frame: &mut Frame) {
@@ -210,4 +231,26 @@ impl DeviceClass {
             DeviceClass::D(_) => d::ID_PREFIX,
         }
     }
+    /// returns the ngspice device parameter (`@dev[param]`) holding the current into the port at index,
+    /// and whether it must be negated because ngspice measures it flowing out of that port
+    pub fn port_current(&self, port: usize) -> Option<(&'static str, bool)> {
+        match (self, port) {
+            // drain, gate, source, bulk
+            (DeviceClass::Pm(_) | DeviceClass::Nm(_), 0) => Some(("id", false)),
+            (DeviceClass::Pm(_) | DeviceClass::Nm(_), 1) => Some(("ig", false)),
+            (DeviceClass::Pm(_) | DeviceClass::Nm(_), 2) => Some(("is", false)),
+            (DeviceClass::Pm(_) | DeviceClass::Nm(_), 3) => Some(("ib", false)),
+            (DeviceClass::D(_), 0 | 1) => Some(("id", port == 1)),
+            (DeviceClass::I(_), 0 | 1) => Some(("current", port == 1)),
+            (
+                DeviceClass::Gnd(_)
+                | DeviceClass::R(_)
+                | DeviceClass::L(_)
+                | DeviceClass::C(_)
+                | DeviceClass::V(_),
+                0 | 1,
+            ) => Some(("i", port == 1)),
+            _ => None,
+        }
+    }
 }
@@ -86,6 +86,19 @@ fn draw_with(src: SSPoint, dst: SSPoint, vct: VCTransform, frame: &mut Frame, st
 /// width of the wire segment
 const WIRE_WIDTH: f32 = 1.0;
 
+impl NetEdge {
+    /// draws the segment as flagged by a simulation diagnostic, distinct from selected and highlighted
+    pub fn draw_flagged(&self, vct: VCTransform, frame: &mut Frame) {
+        let wire_stroke = Stroke {
+            width: 2.0 * self::WIRE_WIDTH,
+            style: stroke::Style::Solid(Color::from_rgb(1.0, 0.4, 0.4)),
+            line_cap: LineCap::Round,
+            ..Stroke::default()
+        };
+        draw_with(self.src, self.dst, vct, frame, wire_stroke);
+    }
+}
+
 impl Drawable for NetEdge {
     fn draw_persistent(&self, vct: VCTransform, _vcscale: f32, frame: &mut Frame) {
         // let wire_width = self::WIRE_WIDTH;
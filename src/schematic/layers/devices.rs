@@ -5,6 +5,7 @@ use std::collections::HashSet;
 use crate::schematic::elements::DeviceClass;
 use crate::schematic::elements::RcRDevice;
 use crate::schematic::interactable::Interactive;
+use crate::schematic::params;
 use crate::transforms::{self, SSPoint, VCTransform, VSBox, VSPoint};
 use crate::Drawable;
 
@@ -124,12 +125,72 @@ impl Devices {
         });
         VSBox::from_points(pts)
     }
-    /// process dc operating point simulation results - draws the voltage of connected nets near the connected port
+    /// process dc operating point simulation results - net voltages, device currents and mosfet figures to be drawn on the devices
     pub fn op(&mut self, pkvecvaluesall: &paprika::PkVecvaluesall) {
         for d in &self.set {
             d.0.borrow_mut().op(pkvecvaluesall);
         }
     }
+    /// draws the operating point annotation of all devices
+    pub fn draw_op(&self, vct: VCTransform, vcscale: f32, frame: &mut Frame) {
+        for d in &self.set {
+            d.0.borrow().draw_op(vct, vcscale, frame);
+        }
+    }
+    /// draws the devices with the given ids as flagged by simulation diagnostics.
+    /// Ids are compared case insensitively, as ngspice lowercases them
+    pub fn draw_flagged(&self, ids: &[String], vct: VCTransform, vcscale: f32, frame: &mut Frame) {
+        for d in &self.set {
+            let d = d.0.borrow();
+            if ids.iter().any(|id| id.eq_ignore_ascii_case(&d.ng_id())) {
+                d.draw_flagged(vct, vcscale, frame);
+            }
+        }
+    }
+    /// returns the device vectors ngspice must save for the operating point annotation,
+    /// given the level of a mosfet model by name, see `NgModels::mos_level`
+    pub fn op_saves(&self, mos_level: impl Fn(&str) -> Option<u32>) -> Vec<String> {
+        self.set
+            .iter()
+            .flat_map(|d| {
+                let d = d.0.borrow();
+                d.op_saves(d.mos_model().and_then(|m| mos_level(&m)))
+            })
+            .collect()
+    }
+    /// returns (id, id prefix, value) of every device with a value, e.g. resistors. See `Device::nominal_value`
+    pub fn nominal_values(&self) -> Vec<(String, &'static str, Result<f32, String>)> {
+        self.set
+            .iter()
+            .filter_map(|d| {
+                let d = d.0.borrow();
+                Some((d.ng_id(), d.class().id_prefix(), d.nominal_value()?))
+            })
+            .collect()
+    }
+    /// returns the ids of the devices whose parameter refers to the global parameter name
+    pub fn param_users(&self, name: &str) -> Vec<String> {
+        self.set
+            .iter()
+            .filter_map(|d| {
+                let d = d.0.borrow();
+                params::references(&d.class().param_summary())
+                    .iter()
+                    .any(|r| r.eq_ignore_ascii_case(name))
+                    .then(|| d.ng_id())
+            })
+            .collect()
+    }
+    /// renames references to the global parameter old in the parameters of all devices
+    pub fn rename_param(&mut self, old: &str, new: &str) {
+        for d in &self.set {
+            let summary = d.0.borrow().class().param_summary();
+            let renamed = params::rename_references(&summary, old, new);
+            if renamed != summary {
+                d.0.borrow_mut().class_mut().set_raw_param(renamed);
+            }
+        }
+    }
     /// inserts device d into self.
     pub fn insert(&mut self, d: RcRDevice) {
         if !self.set.contains(&d) {
@@ -232,6 +293,12 @@ impl Devices {
         }
         false
     }
+    /// returns the ngspice vector of the current into the first device port at ssp, and whether it must be negated
+    pub fn port_current_at(&self, ssp: SSPoint) -> Option<(String, bool)> {
+        self.set
+            .iter()
+            .find_map(|d| d.0.borrow().port_current_at(ssp))
+    }
     pub fn delete_item(&mut self, d: &RcRDevice) {
         self.set.remove(d);
     }
@@ -255,6 +255,39 @@ impl Nets {
     pub fn delete_edge(&mut self, e: &NetEdge) {
         self.graph.remove_edge(NetVertex(e.src), NetVertex(e.dst));
     }
+    /// returns the segments of the named nets. Names are compared case insensitively, as ngspice lowercases them
+    fn named_edges<'a>(&'a self, names: &'a [String]) -> impl Iterator<Item = &'a NetEdge> {
+        self.graph.all_edges().filter_map(|(_, _, edge)| {
+            let label = edge.label.as_ref()?;
+            names
+                .iter()
+                .any(|n| n.eq_ignore_ascii_case(label))
+                .then_some(edge)
+        })
+    }
+    /// draws the segments of the named nets as selected
+    pub fn draw_highlighted(
+        &self,
+        names: &[String],
+        vct: VCTransform,
+        vcscale: f32,
+        frame: &mut iced::widget::canvas::Frame,
+    ) {
+        for edge in self.named_edges(names) {
+            edge.draw_selected(vct, vcscale, frame);
+        }
+    }
+    /// draws the segments of the named nets as flagged by simulation diagnostics
+    pub fn draw_flagged(
+        &self,
+        names: &[String],
+        vct: VCTransform,
+        frame: &mut iced::widget::canvas::Frame,
+    ) {
+        for edge in self.named_edges(names) {
+            edge.draw_flagged(vct, frame);
+        }
+    }
 }
 
 impl Drawable for Nets {
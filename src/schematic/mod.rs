@@ -6,6 +6,7 @@ mod elements;
 mod interactable;
 mod layers;
 mod models;
+mod params;
 pub mod symbols;
 mod viewport;
 
@@ -2,37 +2,80 @@
 //!
 //!
 
+use std::path::PathBuf;
+
+/// process corner, selecting the typical, fast or slow variant of the models
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Corner {
+    #[default]
+    TT,
+    FF,
+    SS,
+}
+
+impl Corner {
+    pub const ALL: [Corner; 3] = [Corner::TT, Corner::FF, Corner::SS];
+}
+
+impl std::fmt::Display for Corner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Corner::TT => write!(f, "tt"),
+            Corner::FF => write!(f, "ff"),
+            Corner::SS => write!(f, "ss"),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct NgModels {
     models: Vec<NgModel>,
+    /// user model library with a section per corner, `.lib tt` .. `.endl tt` and likewise ff and ss
+    library: Option<PathBuf>,
+    /// process corner the model definitions are written for
+    corner: Corner,
 }
 
 impl Default for NgModels {
     fn default() -> Self {
-        // basic elementary models for major semiconductor devices
+        // basic elementary models for major semiconductor devices, with a model set per corner:
+        // fast/slow mosfets have their threshold shifted 0.1V towards/away from conduction and transconductance +/-20%,
+        // fast/slow diodes and bjts have saturation current doubled/halved, bjts also gain +/-50%
         Self {
             models: vec![
                 NgModel {
                     name: String::from("MOSN"),
-                    definition: String::from("NMOS level=1"),
+                    typical: "NMOS level=1",
+                    fast: "NMOS level=1 VTO=-0.1 KP=24u",
+                    slow: "NMOS level=1 VTO=0.1 KP=16u",
                 },
                 NgModel {
                     name: String::from("MOSP"),
-                    definition: String::from("PMOS level=1"),
+                    typical: "PMOS level=1",
+                    fast: "PMOS level=1 VTO=0.1 KP=24u",
+                    slow: "PMOS level=1 VTO=-0.1 KP=16u",
                 },
                 NgModel {
                     name: String::from("DMOD"),
-                    definition: String::from("D"),
+                    typical: "D",
+                    fast: "D IS=2e-14",
+                    slow: "D IS=5e-15",
                 },
                 NgModel {
                     name: String::from("BJTP"),
-                    definition: String::from("PNP"),
+                    typical: "PNP",
+                    fast: "PNP IS=2e-16 BF=150",
+                    slow: "PNP IS=5e-17 BF=50",
                 },
                 NgModel {
                     name: String::from("BJTN"),
-                    definition: String::from("NPN"),
+                    typical: "NPN",
+                    fast: "NPN IS=2e-16 BF=150",
+                    slow: "NPN IS=5e-17 BF=50",
                 },
             ],
+            library: None,
+            corner: Corner::default(),
         }
     }
 }
@@ -41,20 +84,108 @@ impl NgModels {
     pub fn model_definitions(&self) -> String {
         let mut ret = String::new();
         for m in &self.models {
-            ret.push_str(&m.model_line())
+            ret.push_str(&m.model_line(self.corner))
+        }
+        if let Some(path) = &self.library {
+            ret.push_str(&format!(".lib {} {}\n", path.display(), self.corner));
         }
         ret
     }
+    /// selects the process corner the model definitions are written for
+    pub fn set_corner(&mut self, corner: Corner) {
+        self.corner = corner;
+    }
+    /// sets the user model library, of which the section of the corner is netlisted
+    pub fn set_library(&mut self, library: Option<PathBuf>) {
+        self.library = library;
+    }
+    /// returns the level of the named mosfet model, 1 if not given, or none if it is not a mosfet model defined here,
+    /// e.g. one of the user model library. Names are compared case insensitively, as ngspice does
+    pub fn mos_level(&self, name: &str) -> Option<u32> {
+        let model = self
+            .models
+            .iter()
+            .find(|m| m.name.eq_ignore_ascii_case(name))?;
+        let mut words = model.definition(self.corner).split_whitespace();
+        let kind = words.next()?;
+        if !kind.eq_ignore_ascii_case("nmos") && !kind.eq_ignore_ascii_case("pmos") {
+            return None;
+        }
+        words
+            .find_map(|w| {
+                let (key, value) = w.split_once('=')?;
+                if !key.eq_ignore_ascii_case("level") {
+                    return None;
+                }
+                value.parse().ok()
+            })
+            .or(Some(1))
+    }
 }
 
 #[derive(Debug, Clone)]
 struct NgModel {
     name: String,
-    definition: String,
+    /// model definition of the typical corner
+    typical: &'static str,
+    /// model definition of the fast corner
+    fast: &'static str,
+    /// model definition of the slow corner
+    slow: &'static str,
 }
 
 impl NgModel {
-    fn model_line(&self) -> String {
-        format!(".model {} {}\n", self.name, self.definition)
+    /// returns the model definition of the corner
+    fn definition(&self, corner: Corner) -> &'static str {
+        match corner {
+            Corner::TT => self.typical,
+            Corner::FF => self.fast,
+            Corner::SS => self.slow,
+        }
+    }
+    fn model_line(&self, corner: Corner) -> String {
+        format!(".model {} {}\n", self.name, self.definition(corner))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn corner_model_sets() {
+        let mut models = NgModels::default();
+        let tt = models.model_definitions();
+        assert!(tt.contains(".model MOSN NMOS level=1\n"));
+        assert!(tt.contains(".model DMOD D\n"));
+        assert!(!tt.contains(".lib"));
+        models.set_corner(Corner::FF);
+        let ff = models.model_definitions();
+        assert!(ff.contains(".model DMOD D IS=2e-14\n"));
+        assert!(ff.contains(".model BJTN NPN IS=2e-16 BF=150\n"));
+        // every model has a definition of its own per corner
+        models.set_corner(Corner::SS);
+        let ss = models.model_definitions();
+        for (t, (f, s)) in tt.lines().zip(ff.lines().zip(ss.lines())) {
+            assert!(t != f && f != s && s != t, "{}", t);
+        }
+
+        models.set_library(Some(PathBuf::from("models.lib")));
+        assert!(models.model_definitions().ends_with(".lib models.lib ss\n"));
+    }
+
+    #[test]
+    fn mos_levels() {
+        let mut models = NgModels::default();
+        assert_eq!(models.mos_level("mosn"), Some(1));
+        assert_eq!(models.mos_level("DMOD"), None);
+        assert_eq!(models.mos_level("bsim4n"), None);
+        models.models.push(NgModel {
+            name: String::from("BSIM4N"),
+            typical: "NMOS level=54 version=4.8",
+            fast: "NMOS level=54 version=4.8",
+            slow: "NMOS level=54 version=4.8",
+        });
+        assert_eq!(models.mos_level("bsim4n"), Some(54));
     }
 }
@@ -0,0 +1,301 @@
+//! global parameters
+//! `.param` definitions of the schematic, which device parameters may refer to in `{expr}` values, e.g. `{rload*2}`
+//!
+
+/// identifiers in expressions which are not parameters
+const BUILTINS: [&str; 4] = ["pi", "temper", "hertz", "time"];
+
+/// table of global parameters, netlisted as `.param` lines in order
+#[derive(Debug, Clone, Default)]
+pub struct NgParams {
+    params: Vec<NgParam>,
+}
+
+impl NgParams {
+    pub fn param_definitions(&self) -> String {
+        let mut ret = String::new();
+        for p in &self.params {
+            ret.push_str(&format!(".param {}={}\n", p.name, p.value));
+        }
+        ret
+    }
+    /// returns (name, value) of every parameter, in order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.params
+            .iter()
+            .map(|p| (p.name.as_str(), p.value.as_str()))
+    }
+    /// returns true if a parameter of name exists, ignoring case as ngspice does
+    pub fn contains(&self, name: &str) -> bool {
+        self.params
+            .iter()
+            .any(|p| p.name.eq_ignore_ascii_case(name))
+    }
+    /// returns the parameters referred to by src which are not in the table
+    pub fn undefined(&self, src: &str) -> Vec<String> {
+        references(src)
+            .into_iter()
+            .filter(|r| !self.contains(r))
+            .collect()
+    }
+    /// returns the names of the parameters whose value refers to parameter name
+    pub fn users(&self, name: &str) -> Vec<String> {
+        self.params
+            .iter()
+            .filter(|p| {
+                references(&p.value)
+                    .iter()
+                    .any(|r| r.eq_ignore_ascii_case(name))
+            })
+            .map(|p| p.name.clone())
+            .collect()
+    }
+    /// checks that src is a valid device parameter: balanced braces, and only defined parameters referred to
+    pub fn validate(&self, src: &str) -> Result<(), String> {
+        let mut depth = 0;
+        for c in src.chars() {
+            match c {
+                '{' => depth += 1,
+                '}' if depth == 0 => return Err(String::from("unmatched }")),
+                '}' => depth -= 1,
+                _ => {}
+            }
+        }
+        if depth != 0 {
+            return Err(String::from("unmatched {"));
+        }
+        let undefined = self.undefined(src);
+        if !undefined.is_empty() {
+            return Err(format!("undefined parameter {}", undefined.join(", ")));
+        }
+        Ok(())
+    }
+    /// appends a new parameter with an unused name, returns its index
+    pub fn add(&mut self) -> usize {
+        let name = (1..)
+            .map(|i| format!("p{}", i))
+            .find(|n| !self.contains(n))
+            .unwrap();
+        self.params.push(NgParam {
+            name,
+            value: String::from("1"),
+        });
+        self.params.len() - 1
+    }
+    /// removes the parameter at index i
+    pub fn remove(&mut self, i: usize) {
+        if i < self.params.len() {
+            self.params.remove(i);
+        }
+    }
+    /// sets the value of the parameter at index i, which may refer to the parameters before it
+    pub fn set_value(&mut self, i: usize, value: String) -> Result<(), String> {
+        let Some(p) = self.params.get(i) else {
+            return Ok(());
+        };
+        let before = NgParams {
+            params: self.params[..i].to_vec(),
+        };
+        let value = value.trim().to_string();
+        if value.is_empty() {
+            return Err(format!("{} has no value", p.name));
+        }
+        if references(&value)
+            .iter()
+            .any(|r| r.eq_ignore_ascii_case(&p.name))
+        {
+            return Err(format!("{} refers to itself", p.name));
+        }
+        before.validate(&value)?;
+        self.params[i].value = value;
+        Ok(())
+    }
+    /// renames the parameter at index i, as well as references to it in the other parameters. Returns the old name.
+    pub fn rename(&mut self, i: usize, name: &str) -> Result<String, String> {
+        let Some(p) = self.params.get(i) else {
+            return Err(String::from("no such parameter"));
+        };
+        if !is_identifier(name) || BUILTINS.contains(&name.to_lowercase().as_str()) {
+            return Err(format!("{} is not a valid parameter name", name));
+        }
+        if !p.name.eq_ignore_ascii_case(name) && self.contains(name) {
+            return Err(format!("{} already exists", name));
+        }
+        let old = std::mem::replace(&mut self.params[i].name, name.to_string());
+        for p in &mut self.params {
+            p.value = rename_references(&p.value, &old, name);
+        }
+        Ok(old)
+    }
+    /// renames the parameter at index i and sets its value, see rename and set_value.
+    /// Both are validated before either is applied. Returns the old name.
+    pub fn set(&mut self, i: usize, name: &str, value: String) -> Result<String, String> {
+        let mut updated = self.clone();
+        let old = updated.rename(i, name)?;
+        updated.set_value(i, value)?;
+        *self = updated;
+        Ok(old)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct NgParam {
+    name: String,
+    /// number or expression, e.g. `10k` or `{rload*2}`
+    value: String,
+}
+
+/// returns true if s is a parameter name: a letter followed by letters, digits and underscores
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// splits the `{expr}` parts of src into identifiers, calling f with the byte range of each parameter reference
+fn for_each_reference(src: &str, mut f: impl FnMut(std::ops::Range<usize>)) {
+    let bytes = src.as_bytes();
+    let mut depth = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i];
+        match c {
+            b'{' => depth += 1,
+            b'}' if depth > 0 => depth -= 1,
+            _ if depth > 0 && (c.is_ascii_alphanumeric() || c == b'_' || c == b'.') => {
+                let start = i;
+                while i < bytes.len()
+                    && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_' || bytes[i] == b'.')
+                {
+                    i += 1;
+                }
+                let word = &src[start..i];
+                // numbers (with scale suffixes, e.g. 2k), function calls and builtins are not references
+                let is_call = bytes.get(i) == Some(&b'(');
+                if is_identifier(word)
+                    && !is_call
+                    && !BUILTINS.contains(&word.to_lowercase().as_str())
+                {
+                    f(start..i);
+                }
+                continue;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+}
+
+/// returns the lowercase names of the parameters referred to in the `{expr}` parts of src
+pub fn references(src: &str) -> Vec<String> {
+    let mut ret: Vec<String> = vec![];
+    for_each_reference(src, |r| {
+        let name = src[r].to_lowercase();
+        if !ret.contains(&name) {
+            ret.push(name);
+        }
+    });
+    ret
+}
+
+/// returns src with references to parameter old in its `{expr}` parts renamed to new
+pub fn rename_references(src: &str, old: &str, new: &str) -> String {
+    let mut ret = String::new();
+    let mut last = 0;
+    for_each_reference(src, |r| {
+        if src[r.clone()].eq_ignore_ascii_case(old) {
+            ret.push_str(&src[last..r.start]);
+            ret.push_str(new);
+            last = r.end;
+        }
+    });
+    ret.push_str(&src[last..]);
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// parameters a = 1k and b = {a*2}
+    fn params() -> NgParams {
+        let mut params = NgParams::default();
+        params.add();
+        params.add();
+        params.set(0, "a", String::from("1k")).unwrap();
+        params.set(1, "b", String::from("{a*2}")).unwrap();
+        params
+    }
+
+    #[test]
+    fn references_in_expressions() {
+        // numbers with scale suffixes, function calls and builtins are not references
+        assert_eq!(
+            references("{rload*2k + sin(w) + 1.5meg + PI + temper}"),
+            ["rload", "w"]
+        );
+        // only within braces, once each, in lowercase
+        assert_eq!(references("r {Ra} ra {ra + rb_2}"), ["ra", "rb_2"]);
+        assert!(references("1k").is_empty());
+        let mut ranges = vec![];
+        for_each_reference("{a}+{b_1 * a}", |r| ranges.push(r));
+        assert_eq!(ranges, [1..2, 5..8, 11..12]);
+    }
+
+    #[test]
+    fn rename() {
+        assert_eq!(
+            rename_references("{RLoad*2} rload {rload2 + rload}", "rload", "rl"),
+            "{rl*2} rload {rload2 + rl}"
+        );
+        assert_eq!(
+            rename_references("{max(a, 2k)}", "max", "m"),
+            "{max(a, 2k)}"
+        );
+
+        let mut params = params();
+        assert_eq!(params.rename(0, "x"), Ok(String::from("a")));
+        assert_eq!(params.iter().nth(1), Some(("b", "{x*2}")));
+        // a change of case only
+        assert_eq!(params.rename(0, "X"), Ok(String::from("x")));
+        for name in ["b", "B", "1a", "a-b", "", "pi", "Time"] {
+            assert!(params.rename(0, name).is_err(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn validate() {
+        let params = params();
+        assert_eq!(params.validate("{A*2k}"), Ok(()));
+        assert_eq!(params.validate("{sqrt(b) + pi}"), Ok(()));
+        assert_eq!(params.validate("1k"), Ok(()));
+        assert_eq!(params.validate("{a"), Err(String::from("unmatched {")));
+        assert_eq!(params.validate("a}"), Err(String::from("unmatched }")));
+        assert_eq!(params.validate("{{a}"), Err(String::from("unmatched {")));
+        assert_eq!(
+            params.validate("{c + a + d}"),
+            Err(String::from("undefined parameter c, d"))
+        );
+    }
+
+    #[test]
+    fn set() {
+        let mut params = params();
+        // values may only refer to the parameters before them
+        assert!(params.set_value(0, String::from("{b}")).is_err());
+        assert!(params.set_value(1, String::from("{b+1}")).is_err());
+        assert!(params.set_value(1, String::from(" ")).is_err());
+        assert_eq!(params.set_value(1, String::from(" {a/2} ")), Ok(()));
+        assert_eq!(params.param_definitions(), ".param a=1k\n.param b={a/2}\n");
+
+        // nothing changes unless both the name and the value are valid
+        assert!(params.set(1, "c", String::from("{c}")).is_err());
+        assert!(params.set(1, "a", String::from("2")).is_err());
+        assert_eq!(params.param_definitions(), ".param a=1k\n.param b={a/2}\n");
+        assert_eq!(
+            params.set(0, "r", String::from("2k")),
+            Ok(String::from("a"))
+        );
+        assert_eq!(params.param_definitions(), ".param r=2k\n.param b={r/2}\n");
+    }
+}